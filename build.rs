@@ -0,0 +1,194 @@
+// sd-id128: build script selecting the supported systemd version
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// For every systemd version which introduced functions of sd-id128, the cfg
+// `systemd_v<version>` is set if the version is selected by a feature or, with
+// the feature `detect`, if the installed libsystemd is at least that version.
+// With the feature `static`, libsystemd is linked statically. With the
+// feature `codegen`, the tables of message IDs and partition types are
+// generated from the installed headers of systemd.
+use std::env;
+#[cfg(feature = "static")]
+use std::path::Path;
+#[cfg(feature = "codegen")]
+use std::{fs, path::PathBuf};
+
+const VERSIONS: [u32; 4] = [232, 233, 240, 255];
+
+/// Returns the major version of the installed libsystemd.
+#[cfg(feature = "detect")]
+fn detect() -> Option<u32> {
+    println!("cargo:rerun-if-env-changed=PKG_CONFIG_PATH");
+    match pkg_config::Config::new().cargo_metadata(false)
+                                   .env_metadata(false)
+                                   .probe("libsystemd")
+    {
+        Ok(library) => {
+            let major = library.version
+                               .chars()
+                               .take_while(char::is_ascii_digit)
+                               .collect::<String>();
+            major.parse().ok()
+        },
+        Err(error) => {
+            println!("cargo:warning=libsystemd not detected: {}", error);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "detect"))]
+fn detect() -> Option<u32> {
+    None
+}
+
+/// Links the static archive libsystemd.a, which is found in `SYSTEMD_LIB_DIR`
+/// or via pkg-config including the libraries it depends on.
+#[cfg(feature = "static")]
+fn link_static() {
+    println!("cargo:rerun-if-env-changed=SYSTEMD_LIB_DIR");
+    if let Some(directory) = env::var_os("SYSTEMD_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={}",
+                 Path::new(&directory).display());
+        println!("cargo:rustc-link-lib=static=systemd");
+        return;
+    }
+    match pkg_config::Config::new().cargo_metadata(false)
+                                   .env_metadata(false)
+                                   .statik(true)
+                                   .probe("libsystemd")
+    {
+        Ok(library) => {
+            for path in library.link_paths.iter() {
+                println!("cargo:rustc-link-search=native={}", path.display());
+            }
+            println!("cargo:rustc-link-lib=static=systemd");
+            for dependency in library.libs.iter().filter(|name| *name != "systemd") {
+                println!("cargo:rustc-link-lib={}", dependency);
+            }
+        },
+        Err(error) => {
+            println!("cargo:warning=libsystemd not found by pkg-config: {}",
+                     error);
+            println!("cargo:rustc-link-lib=static=systemd");
+        }
+    }
+}
+
+/// Returns the directory of the headers of systemd: `SYSTEMD_INCLUDE_DIR`, the
+/// include path of libsystemd reported by pkg-config or /usr/include.
+#[cfg(feature = "codegen")]
+fn include_dir() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=SYSTEMD_INCLUDE_DIR");
+    if let Some(directory) = env::var_os("SYSTEMD_INCLUDE_DIR") {
+        return PathBuf::from(directory);
+    }
+    #[cfg(feature = "pkg-config")]
+    if let Ok(library) = pkg_config::Config::new().cargo_metadata(false)
+                                                  .env_metadata(false)
+                                                  .probe("libsystemd")
+    {
+        if let Some(path) = library.include_paths.into_iter().next() {
+            return path;
+        }
+    }
+    PathBuf::from("/usr/include")
+}
+
+/// Returns the IDs defined by `#define <prefix>NAME SD_ID128_MAKE(..)` in a
+/// header, named like `NAME` in lower case with dashes. Aliases of other
+/// macros and the `_STR` variants are skipped.
+#[cfg(feature = "codegen")]
+fn parse_header(header: &str, prefix: &str) -> Vec<(String, String)> {
+    let mut ids = Vec::<(String, String)>::new();
+    for line in header.lines() {
+        let mut words = line.split_whitespace();
+        let (name, value) = match (words.next(), words.next(), words.next()) {
+            (Some("#define"), Some(name), Some(value)) => (name, value),
+            _ => continue
+        };
+        let name = match name.strip_prefix(prefix) {
+            Some(name) => name.to_ascii_lowercase().replace('_', "-"),
+            None => continue
+        };
+        let digits = match value.strip_prefix("SD_ID128_MAKE(")
+                                .and_then(|value| value.strip_suffix(')'))
+        {
+            Some(bytes) => bytes.split(',').map(str::trim).collect::<String>(),
+            None => continue
+        };
+        if digits.len() == 32
+           && digits.bytes().all(|byte| byte.is_ascii_hexdigit())
+           && !ids.iter().any(|(known, _)| *known == name)
+        {
+            ids.push((name, digits));
+        }
+    }
+    ids
+}
+
+/// Generates the table `constant` of the IDs of `header` into the file `target`
+/// of `OUT_DIR`. A missing header results in an empty table.
+#[cfg(feature = "codegen")]
+fn generate(header: &str, prefix: &str, constant: &str, target: &str) {
+    let path = include_dir().join("systemd").join(header);
+    println!("cargo:rerun-if-changed={}", path.display());
+    let ids = match fs::read_to_string(&path) {
+        Ok(header) => parse_header(&header, prefix),
+        Err(error) => {
+            println!("cargo:warning={} not read: {}", path.display(), error);
+            Vec::new()
+        }
+    };
+    let mut code = format!("/// IDs of `<systemd/{}>` by name, generated at build time (feature \
+                            `codegen`).\npub const {}: &[(&str, ID128)] = &[\n",
+                           header, constant);
+    for (name, digits) in ids {
+        code.push_str(&format!("    (\"{}\", ID128::from_u128(0x{})),\n", name, digits));
+    }
+    code.push_str("];\n");
+    let target = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join(target);
+    fs::write(&target, code).unwrap_or_else(|error| {
+                                panic!("{} not written: {}", target.display(), error)
+                            });
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "static")]
+    link_static();
+    #[cfg(feature = "codegen")]
+    {
+        generate("sd-messages.h",
+                 "SD_MESSAGE_",
+                 "SYSTEMD_MESSAGE_IDS",
+                 "messages.rs");
+        generate("sd-gpt.h", "SD_GPT_", "SYSTEMD_PARTITION_TYPES", "gpt.rs");
+    }
+    let selected =
+        VERSIONS.iter()
+                .copied()
+                .filter(|version| env::var_os(format!("CARGO_FEATURE_{}", version)).is_some())
+                .max()
+                .unwrap_or(0);
+    let supported = detect().unwrap_or(0).max(selected);
+    for version in VERSIONS.iter() {
+        println!("cargo:rustc-check-cfg=cfg(systemd_v{})", version);
+        if *version <= supported {
+            println!("cargo:rustc-cfg=systemd_v{}", version);
+        }
+    }
+}
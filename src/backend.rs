@@ -0,0 +1,133 @@
+// sd-id128: selection of the implementation behind the libsystemd API
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// The backend functions return the raw value of the requested ID. libsystemd
+// is called unless the feature `no-ffi` is selected or the feature `ffi` is
+// missing, in which case the native implementations are used. With the
+// feature `dynamic` (and without `ffi`), libsystemd is loaded at runtime and
+// the native implementations serve as fallback.
+#[cfg(all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))]
+pub(crate) use crate::dynamic::{get_app_specific, get_boot, get_boot_app_specific, get_invocation,
+                                get_machine, get_machine_app_specific, randomize};
+#[cfg(any(feature = "no-ffi",
+              all(not(feature = "ffi"), not(feature = "dynamic"))))]
+pub(crate) use crate::native::{get_boot, get_invocation, get_machine, randomize};
+#[cfg(all(feature = "ffi", any(not(feature = "no-ffi"), systemd_v233)))]
+use crate::{ffi, Error};
+
+// sd_id128_get_app_specific is not yet covered by sd-sys
+#[cfg(systemd_v255)]
+#[link(name = "systemd")]
+extern "C" {
+    fn sd_id128_get_app_specific(base: ffi::sd_id128,
+                                 app_id: ffi::sd_id128,
+                                 ret: *mut ffi::sd_id128)
+                                 -> libc::c_int;
+}
+
+/// Translates the return code of libsystemd.
+#[cfg(all(feature = "ffi", any(not(feature = "no-ffi"), systemd_v233)))]
+fn check(result: libc::c_int, id128: ffi::sd_id128) -> Result<[u8; 16], Error> {
+    if result < 0 {
+        return Err(Error::SDError(result));
+    }
+    Ok(id128.value)
+}
+
+#[cfg(all(feature = "ffi", not(feature = "no-ffi")))]
+pub(crate) fn randomize() -> Result<[u8; 16], Error> {
+    let mut id128 = ffi::sd_id128::default();
+    let result = unsafe { ffi::sd_id128_randomize(&mut id128) };
+    check(result, id128)
+}
+
+#[cfg(all(feature = "ffi", not(feature = "no-ffi")))]
+pub(crate) fn get_machine() -> Result<[u8; 16], Error> {
+    let mut id128 = ffi::sd_id128::default();
+    let result = unsafe { ffi::sd_id128_get_machine(&mut id128) };
+    check(result, id128)
+}
+
+#[cfg(all(feature = "ffi", not(feature = "no-ffi")))]
+pub(crate) fn get_boot() -> Result<[u8; 16], Error> {
+    let mut id128 = ffi::sd_id128::default();
+    let result = unsafe { ffi::sd_id128_get_boot(&mut id128) };
+    check(result, id128)
+}
+
+#[cfg(all(systemd_v232, not(feature = "no-ffi")))]
+pub(crate) fn get_invocation() -> Result<[u8; 16], Error> {
+    let mut id128 = ffi::sd_id128::default();
+    let result = unsafe { ffi::sd_id128_get_invocation(&mut id128) };
+    check(result, id128)
+}
+
+#[cfg(systemd_v233)]
+pub(crate) fn get_machine_app_specific(app: [u8; 16]) -> Result<[u8; 16], Error> {
+    let mut id128 = ffi::sd_id128::default();
+    let result =
+        unsafe { ffi::sd_id128_get_machine_app_specific(ffi::sd_id128 { value: app }, &mut id128) };
+    check(result, id128)
+}
+
+#[cfg(systemd_v240)]
+pub(crate) fn get_boot_app_specific(app: [u8; 16]) -> Result<[u8; 16], Error> {
+    let mut id128 = ffi::sd_id128::default();
+    let result =
+        unsafe { ffi::sd_id128_get_boot_app_specific(ffi::sd_id128 { value: app }, &mut id128) };
+    check(result, id128)
+}
+
+#[cfg(systemd_v255)]
+pub(crate) fn get_app_specific(base: [u8; 16], app: [u8; 16]) -> Result<[u8; 16], Error> {
+    let mut id128 = ffi::sd_id128::default();
+    let result = unsafe {
+        sd_id128_get_app_specific(ffi::sd_id128 { value: base },
+                                  ffi::sd_id128 { value: app },
+                                  &mut id128)
+    };
+    check(result, id128)
+}
+
+/// Returns true if the NUL terminated symbol is available in libsystemd.
+#[cfg(all(feature = "ffi", not(feature = "static"), not(feature = "no-ffi")))]
+pub(crate) fn has_symbol(name: &[u8]) -> bool {
+    !unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr() as *const libc::c_char) }.is_null()
+}
+
+/// Returns true if the NUL terminated symbol is available in libsystemd.
+///
+/// `dlsym` does not see the symbols of a statically linked libsystemd, so the
+/// availability follows from the systemd version selected at build time.
+#[cfg(all(feature = "static", not(feature = "no-ffi")))]
+pub(crate) fn has_symbol(name: &[u8]) -> bool {
+    match name {
+        b"sd_id128_get_invocation\0" => cfg!(systemd_v232),
+        b"sd_id128_get_machine_app_specific\0" => cfg!(systemd_v233),
+        b"sd_id128_get_boot_app_specific\0" => cfg!(systemd_v240),
+        b"sd_id128_get_app_specific\0" => cfg!(systemd_v255),
+        _ => true
+    }
+}
+
+#[cfg(all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))]
+pub(crate) use crate::dynamic::has_symbol;
+
+#[cfg(any(feature = "no-ffi",
+          all(not(feature = "ffi"), not(feature = "dynamic"))))]
+pub(crate) fn has_symbol(_name: &[u8]) -> bool {
+    false
+}
@@ -0,0 +1,279 @@
+// sd-id128: command line tool mirroring systemd-id128
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// The binary is built with the feature `cli`. Like systemd-id128, it prints
+// IDs in libsystemd format, one per line, and exits with status 1 on failure.
+use sd_id128::{gpt,
+               id_file::{self, Content},
+               messages, Case, Error, Format, Id128Reader, Id128Writer, SdErrno, ID128};
+use std::{env,
+          io::{self, BufWriter},
+          process};
+
+const HELP: &str = "\
+sd-id128 [OPTIONS...] COMMAND
+
+Generate and print 128-bit identifiers.
+
+Commands:
+  new                 Generate a new ID
+  machine-id          Print the ID of current machine
+  boot-id             Print the ID of current boot
+  invocation-id       Print the ID of current invocation
+  show [NAME|UUID...] Print one or more well-known GPT partition type or
+                      message IDs
+  convert             Read IDs in any format from standard input, one per
+                      line, and print them in the output format
+  init-file PATH      Initialize an ID file like /etc/machine-id with a new ID
+                      unless it contains one already, and print the ID
+  var-partition-uuid  Print the UUID of the /var partition of this machine
+  help                Show this help
+
+Options:
+  -h --help           Show this help
+     --version        Show package version
+  -a --app-specific=ID
+                      Generate app-specific machine or boot ID
+  -u --uuid           Output in UUID (RFC) format
+     --format=FORMAT  Output in FORMAT: simple, libsystemd, rfc, urn or braced
+     --upper          Output hexadecimal letters in upper case
+     --lower          Output hexadecimal letters in lower case (default)
+     --value          Only print the IDs of show, without names and header
+";
+
+/// Command line of the tool.
+struct Options {
+    command:   String,
+    arguments: Vec<String>,
+    format:    Format,
+    case:      Case,
+    app:       Option<ID128>,
+    value:     bool
+}
+
+/// Returns a message describing `error`.
+fn describe(error: Error) -> String {
+    error.to_string()
+}
+
+/// Parses the command line, not including the program name.
+fn parse(arguments: &[String]) -> Result<Options, String> {
+    let mut options = Options { command:   String::new(),
+                                arguments: Vec::new(),
+                                format:    Format::LibSystemD,
+                                case:      Case::Lower,
+                                app:       None,
+                                value:     false };
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let (name, inline) = match argument.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (argument.as_str(), None)
+        };
+        let mut value = || {
+            inline.or_else(|| arguments.next().map(String::as_str))
+                  .ok_or(format!("option requires an argument: {}", name))
+        };
+        match name {
+            "-h" | "--help" => options.command = String::from("help"),
+            "--version" => options.command = String::from("version"),
+            "-a" | "--app-specific" => {
+                let app = value()?;
+                options.app = Some(ID128::from_str(app).map_err(|_| {
+                                                           format!("invalid application ID: {}",
+                                                                   app)
+                                                       })?);
+            },
+            "-u" | "--uuid" => options.format = Format::RFC,
+            "--format" => {
+                let format = value()?;
+                options.format = format.parse()
+                                       .map_err(|_| format!("unknown format: {}", format))?;
+            },
+            "--upper" => options.case = Case::Upper,
+            "--lower" => options.case = Case::Lower,
+            "--value" => options.value = true,
+            option if option.starts_with('-') && option.len() > 1 => {
+                return Err(format!("unknown option: {}", option))
+            },
+            _ if options.command.is_empty() => options.command = argument.clone(),
+            _ => options.arguments.push(argument.clone())
+        }
+    }
+    if options.command.is_empty() {
+        options.command = String::from("new");
+    }
+    Ok(options)
+}
+
+/// Prints an ID in the format and case of `options`.
+fn print(id128: &ID128, options: &Options) {
+    println!("{}", id128.formatted(options.format, options.case));
+}
+
+/// Returns the well-known IDs by name.
+#[cfg(not(feature = "codegen"))]
+fn well_known() -> impl Iterator<Item = (&'static str, &'static ID128)> {
+    curated()
+}
+
+/// Returns the well-known IDs by name, followed by the IDs of the installed
+/// systemd whose names are missing in the tables of the library.
+#[cfg(feature = "codegen")]
+fn well_known() -> impl Iterator<Item = (&'static str, &'static ID128)> {
+    let generated =
+        gpt::SYSTEMD_PARTITION_TYPES.iter()
+                                    .chain(messages::SYSTEMD_MESSAGE_IDS.iter())
+                                    .map(|(name, id)| (*name, id))
+                                    .filter(|(name, _)| curated().all(|(known, _)| known != *name));
+    curated().chain(generated)
+}
+
+/// Returns the IDs of the tables of the library by name.
+fn curated() -> impl Iterator<Item = (&'static str, &'static ID128)> {
+    gpt::PARTITION_TYPES.iter()
+                        .map(|info| (info.name, &info.id))
+                        .chain(messages::MESSAGE_IDS.iter()
+                                                    .map(|(name, id, _)| (*name, id)))
+}
+
+/// Resolves a name or an ID to a well-known ID. Names are compared ignoring
+/// the case and treating underscores like dashes.
+fn resolve(name_or_id: &str) -> Result<(&'static str, ID128), String> {
+    let name = name_or_id.replace('_', "-");
+    if let Some((name, id128)) = well_known().find(|(known, _)| known.eq_ignore_ascii_case(&name)) {
+        return Ok((name, id128.clone()));
+    }
+    let id128 =
+        ID128::from_str(name_or_id).map_err(|_| format!("unknown name or ID: {}", name_or_id))?;
+    let name = well_known().find(|(_, known)| **known == id128)
+                           .map_or("-", |(name, _)| name);
+    Ok((name, id128))
+}
+
+/// Prints a table of well-known IDs, all of them if no names are given.
+fn show(options: &Options) -> Result<(), String> {
+    let rows = if options.arguments.is_empty() {
+        well_known().map(|(name, id128)| (name, id128.clone()))
+                    .collect::<Vec<_>>()
+    } else {
+        options.arguments
+               .iter()
+               .map(|name| resolve(name))
+               .collect::<Result<Vec<_>, _>>()?
+    };
+    if options.value {
+        rows.iter().for_each(|(_, id128)| print(id128, options));
+        return Ok(());
+    }
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    println!("{:<width$} ID", "NAME", width = width);
+    for (name, id128) in rows {
+        println!("{:<width$} {}",
+                 name,
+                 id128.formatted(options.format, options.case),
+                 width = width);
+    }
+    Ok(())
+}
+
+/// Converts the IDs of standard input into the format and case of `options`.
+/// Invalid lines are reported and skipped.
+fn convert(options: &Options) -> Result<(), String> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut writer = Id128Writer::new(BufWriter::new(stdout.lock())).format(options.format)
+                                                                    .case(options.case);
+    let mut invalid = 0;
+    for id128 in Id128Reader::new(stdin.lock()) {
+        match id128 {
+            Ok(id128) => writer.write_id(&id128).map_err(|error| error.to_string())?,
+            Err(error @ Error::ParseStringError(..)) => {
+                eprintln!("sd-id128: skipping invalid ID: {}", describe(error));
+                invalid += 1;
+            },
+            Err(error) => return Err(describe(error))
+        }
+    }
+    writer.flush().map_err(|error| error.to_string())?;
+    match invalid {
+        0 => Ok(()),
+        _ => Err(format!("{} invalid IDs skipped", invalid))
+    }
+}
+
+/// Initializes the ID file `path` if it is missing, empty or uninitialized.
+fn init_file(path: &str) -> Result<ID128, Error> {
+    match id_file::read(path) {
+        Ok(Content::Id(id128)) => return Ok(id128),
+        Ok(_) => {},
+        Err(error) if error.sd_errno() == Some(SdErrno::ENOENT) => {},
+        Err(error) => return Err(error)
+    }
+    let id128 = ID128::random_id()?;
+    id_file::write(path, &Content::Id(id128.clone()))?;
+    Ok(id128)
+}
+
+/// Executes the command given by `arguments`, not including the program name.
+fn run(arguments: &[String]) -> Result<(), String> {
+    let options = parse(arguments)?;
+    let expected = match options.command.as_str() {
+        "show" => options.arguments.len(),
+        "init-file" => 1,
+        _ => 0
+    };
+    if options.arguments.len() < expected {
+        return Err(format!("missing argument of {}", options.command));
+    }
+    if let Some(argument) = options.arguments.get(expected) {
+        return Err(format!("unexpected argument: {}", argument));
+    }
+    if options.app.is_some() && options.command != "machine-id" && options.command != "boot-id" {
+        return Err(format!("--app-specific is not supported by {}", options.command));
+    }
+    let id128 = match (options.command.as_str(), options.app.clone()) {
+        ("help", _) => {
+            print!("{}", HELP);
+            return Ok(());
+        },
+        ("version", _) => {
+            println!("sd-id128 {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        },
+        ("new", _) => ID128::random_id(),
+        ("machine-id", None) => ID128::machine_id(),
+        ("machine-id", Some(app)) => ID128::machine_id_app_specific_native(app),
+        ("boot-id", None) => ID128::boot_id(),
+        ("boot-id", Some(app)) => ID128::boot_id_app_specific_native(app),
+        ("invocation-id", _) => ID128::invocation_id_from_env(),
+        ("init-file", _) => init_file(&options.arguments[0]),
+        ("var-partition-uuid", _) => ID128::machine_id_app_specific_native(gpt::VAR),
+        ("show", _) => return show(&options),
+        ("convert", _) => return convert(&options),
+        (name, _) => return Err(format!("unknown command: {}", name))
+    };
+    print(&id128.map_err(describe)?, &options);
+    Ok(())
+}
+
+fn main() {
+    let arguments = env::args().skip(1).collect::<Vec<_>>();
+    if let Err(message) = run(&arguments) {
+        eprintln!("sd-id128: {}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,88 @@
+// sd-id128: identity of the current boot
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The boot ID paired with the time of the boot.
+//!
+//! Log shippers attach the boot context to every record. The boot ID
+//! identifies the boot, the time of the boot orders boots and makes them
+//! readable for humans.
+//!
+//! This module is only available on Linux.
+//!
+//! ```rust
+//! use sd_id128::boot::BootIdentity;
+//! let boot = BootIdentity::current().unwrap();
+//! println!("boot {} started {:?}", boot.boot_id, boot.boot_timestamp);
+//! ```
+use crate::{from_io, Error, ID128};
+use std::{fs, io,
+          time::{Duration, SystemTime, UNIX_EPOCH}};
+
+/// Boot ID and time of the boot.
+///
+/// Fields:
+/// - boot_id: the boot ID, see `ID128::boot_id`
+/// - boot_timestamp: the wall clock time the system booted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootIdentity {
+    pub boot_id:        ID128,
+    pub boot_timestamp: SystemTime
+}
+
+/// Reads the boot time in seconds since the epoch from `btime` in
+/// `/proc/stat`.
+fn btime() -> Option<SystemTime> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let seconds = stat.lines().find_map(|line| line.strip_prefix("btime "))?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds.trim().parse().ok()?))
+}
+
+/// Computes the boot time from the wall clock and `CLOCK_BOOTTIME`.
+fn boottime() -> Result<SystemTime, Error> {
+    let mut timespec = libc::timespec { tv_sec:  0,
+                                        tv_nsec: 0 };
+    let now = SystemTime::now();
+    if unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut timespec) } < 0 {
+        return Err(from_io(io::Error::last_os_error()));
+    }
+    let elapsed = Duration::new(timespec.tv_sec as u64, timespec.tv_nsec as u32);
+    now.checked_sub(elapsed)
+       .ok_or(Error::SDError(-libc::ERANGE))
+}
+
+impl BootIdentity {
+    /// Returns the identity of the current boot.
+    ///
+    /// The boot time is taken from `btime` of `/proc/stat`, which has a
+    /// resolution of one second but is the same for all processes of a boot.
+    /// If it is not available, it is computed from the wall clock and
+    /// `CLOCK_BOOTTIME`. Note that the boot time shifts if the wall clock is
+    /// set, e.g. by NTP after boot.
+    ///
+    /// # Return Values
+    /// - Ok(BootIdentity): the identity of the current boot
+    /// - Err(Error::SDError(i32)): the boot ID or time could not be retrieved,
+    ///   the code is the negative errno
+    pub fn current() -> Result<Self, Error> {
+        let boot_id = ID128::boot_id()?;
+        let boot_timestamp = match btime() {
+            Some(btime) => btime,
+            None => boottime()?
+        };
+        Ok(BootIdentity { boot_id,
+                          boot_timestamp })
+    }
+}
@@ -0,0 +1,102 @@
+// sd-id128: host identity on the BSDs
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{from_io, Error, ID128};
+use libc::{c_char, c_int, c_void, size_t};
+use std::{ffi::CStr, io};
+
+#[cfg(target_os = "freebsd")]
+extern "C" {
+    fn sysctlbyname(name: *const c_char,
+                    oldp: *mut c_void,
+                    oldlenp: *mut size_t,
+                    newp: *const c_void,
+                    newlen: size_t)
+                    -> c_int;
+}
+
+#[cfg(target_os = "openbsd")]
+extern "C" {
+    fn sysctl(name: *const c_int,
+              namelen: u32,
+              oldp: *mut c_void,
+              oldlenp: *mut size_t,
+              newp: *mut c_void,
+              newlen: size_t)
+              -> c_int;
+}
+
+#[cfg(target_os = "openbsd")]
+const CTL_HW: c_int = 6;
+#[cfg(target_os = "openbsd")]
+const HW_UUID: c_int = 18;
+
+/// Reads `kern.hostuuid` into `buffer`.
+#[cfg(target_os = "freebsd")]
+fn host_uuid(buffer: &mut [c_char]) -> c_int {
+    let mut length = buffer.len() as size_t;
+    unsafe {
+        sysctlbyname(b"kern.hostuuid\0".as_ptr() as *const c_char,
+                     buffer.as_mut_ptr() as *mut c_void,
+                     &mut length,
+                     std::ptr::null(),
+                     0)
+    }
+}
+
+/// Reads `hw.uuid` into `buffer`.
+#[cfg(target_os = "openbsd")]
+fn host_uuid(buffer: &mut [c_char]) -> c_int {
+    let name = [CTL_HW, HW_UUID];
+    let mut length = buffer.len() as size_t;
+    unsafe {
+        sysctl(name.as_ptr(),
+               name.len() as u32,
+               buffer.as_mut_ptr() as *mut c_void,
+               &mut length,
+               std::ptr::null_mut(),
+               0)
+    }
+}
+
+impl ID128 {
+    /// Returns the host UUID of FreeBSD or OpenBSD.
+    ///
+    /// On FreeBSD, `kern.hostuuid` is read. It is initialized from the SMBIOS
+    /// system UUID (or `/etc/hostid`) at boot and is virtualized per jail, so
+    /// each jail may report its own identity. On OpenBSD, `hw.uuid` is read,
+    /// which the kernel takes from SMBIOS.
+    ///
+    /// This function is only available on FreeBSD and OpenBSD with the feature
+    /// `portable`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the sysctl failed, the code is the negative
+    ///   errno; ENOENT indicates that no UUID is set (all bits 0 or 1)
+    /// - Err(Error::ParseStringError): the sysctl is no valid UUID
+    pub fn bsd_host_uuid() -> Result<Self, Error> {
+        let mut buffer = [0 as c_char; 64];
+        if host_uuid(&mut buffer[..63]) < 0 {
+            return Err(from_io(io::Error::last_os_error()));
+        }
+        let uuid = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        let id128 = ID128::from_str(uuid.to_string_lossy().trim_end())?;
+        if id128.as_raw_value() == &[0u8; 16] || id128.as_raw_value() == &[0xFFu8; 16] {
+            return Err(Error::SDError(-libc::ENOENT));
+        }
+        Ok(id128)
+    }
+}
@@ -0,0 +1,417 @@
+// sd-id128: IDs queried from systemd services via sd-bus
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//! Transport of IDs in sd-bus messages (feature `dbus`).
+//!
+//! The trait `BusMarshal` appends IDs to and reads them from messages of
+//! sd-bus, either as array of 16 bytes (`ay`), the encoding systemd uses for
+//! properties like `InvocationID`, or as string (`s`) in libsystemd format.
+//! Messages are passed as raw pointers, so bindings of sd-bus like the sd-bus
+//! crate of systemd.rs can transport IDs without depending on the types of
+//! each other.
+
+// Minimal bindings of sd-bus as part of libsystemd, only covering the calls
+// required to read ID properties of systemd services on the system bus and to
+// marshal IDs.
+use crate::{Error, ID128};
+use libc::{c_char, c_int, c_void};
+use std::{ffi::{CStr, CString},
+          ptr};
+
+#[allow(non_camel_case_types)]
+enum sd_bus {}
+
+#[allow(non_camel_case_types)]
+enum sd_bus_message {}
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct sd_bus_error {
+    name:      *const c_char,
+    message:   *const c_char,
+    need_free: c_int
+}
+
+#[link(name = "systemd")]
+extern "C" {
+    fn sd_bus_open_system(ret: *mut *mut sd_bus) -> c_int;
+    fn sd_bus_unref(bus: *mut sd_bus) -> *mut sd_bus;
+    fn sd_bus_message_unref(message: *mut sd_bus_message) -> *mut sd_bus_message;
+    fn sd_bus_error_free(error: *mut sd_bus_error);
+    fn sd_bus_call_method(bus: *mut sd_bus,
+                          destination: *const c_char,
+                          path: *const c_char,
+                          interface: *const c_char,
+                          member: *const c_char,
+                          ret_error: *mut sd_bus_error,
+                          reply: *mut *mut sd_bus_message,
+                          types: *const c_char,
+                          ...)
+                          -> c_int;
+    fn sd_bus_get_property(bus: *mut sd_bus,
+                           destination: *const c_char,
+                           path: *const c_char,
+                           interface: *const c_char,
+                           member: *const c_char,
+                           ret_error: *mut sd_bus_error,
+                           reply: *mut *mut sd_bus_message,
+                           type_: *const c_char)
+                           -> c_int;
+    fn sd_bus_message_read(message: *mut sd_bus_message, types: *const c_char, ...) -> c_int;
+    fn sd_bus_message_read_array(message: *mut sd_bus_message,
+                                 type_: c_char,
+                                 ptr: *mut *const c_void,
+                                 size: *mut usize)
+                                 -> c_int;
+    fn sd_bus_message_append(message: *mut sd_bus_message, types: *const c_char, ...) -> c_int;
+    fn sd_bus_message_append_array(message: *mut sd_bus_message,
+                                   type_: c_char,
+                                   ptr: *const c_void,
+                                   size: usize)
+                                   -> c_int;
+}
+
+/// Encodings of an ID in sd-bus messages.
+///
+/// Variants:
+/// - Bytes: array of 16 bytes, signature `ay`
+/// - String: string in libsystemd format, signature `s`; any format is accepted
+///   when reading
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Encoding {
+    Bytes,
+    String
+}
+
+impl Encoding {
+    /// Returns the D-Bus signature, i.e. "ay" or "s".
+    pub fn signature(self) -> &'static str {
+        match self {
+            Encoding::Bytes => "ay",
+            Encoding::String => "s"
+        }
+    }
+}
+
+/// Values which can be appended to and read from sd-bus messages.
+///
+/// `message` is a `sd_bus_message *` of libsystemd, e.g. taken from the
+/// bindings of sd-bus in use.
+///
+/// # Safety
+/// `message` must point to a valid message, which is open for appending or
+/// reading respectively and positioned at a value of the given encoding.
+pub trait BusMarshal: Sized {
+    /// Appends the value to the message.
+    ///
+    /// # Safety
+    /// See the trait.
+    ///
+    /// # Return Values
+    /// - Ok(()): the value was appended
+    /// - Err(Error::SDError(i32)): sd-bus failed, the code is the negative
+    ///   errno
+    unsafe fn append_to(&self, message: *mut c_void, encoding: Encoding) -> Result<(), Error>;
+
+    /// Reads a value from the message.
+    ///
+    /// # Safety
+    /// See the trait.
+    ///
+    /// # Return Values
+    /// - Ok(Self): the value read
+    /// - Err(Error::SDError(i32)): sd-bus failed, the message has no further
+    ///   value or the array does not hold 16 bytes (EBADMSG); the code is the
+    ///   negative errno
+    /// - Err(Error::ParseStringError(ParseError)): the string is no ID
+    unsafe fn read_from(message: *mut c_void, encoding: Encoding) -> Result<Self, Error>;
+}
+
+impl BusMarshal for ID128 {
+    unsafe fn append_to(&self, message: *mut c_void, encoding: Encoding) -> Result<(), Error> {
+        let message = message as *mut sd_bus_message;
+        let result = match encoding {
+            Encoding::Bytes => sd_bus_message_append_array(message,
+                                                           b'y' as c_char,
+                                                           self.ffi.value.as_ptr()
+                                                           as *const c_void,
+                                                           self.ffi.value.len()),
+            Encoding::String => {
+                let string = c_string(&format!("{:x}", self))?;
+                sd_bus_message_append(message, b"s\0".as_ptr() as *const c_char, string.as_ptr())
+            }
+        };
+        if result < 0 {
+            return Err(Error::SDError(result));
+        }
+        Ok(())
+    }
+
+    unsafe fn read_from(message: *mut c_void, encoding: Encoding) -> Result<Self, Error> {
+        let message = message as *mut sd_bus_message;
+        match encoding {
+            Encoding::Bytes => {
+                let mut data: *const c_void = ptr::null();
+                let mut size = 0usize;
+                let result =
+                    sd_bus_message_read_array(message, b'y' as c_char, &mut data, &mut size);
+                if result < 0 {
+                    return Err(Error::SDError(result));
+                }
+                if result == 0 || size != 16 {
+                    return Err(Error::SDError(-libc::EBADMSG));
+                }
+                let mut value = [0u8; 16];
+                value.copy_from_slice(std::slice::from_raw_parts(data as *const u8, size));
+                Ok(ID128::from_raw_value(value))
+            },
+            Encoding::String => {
+                let mut string: *const c_char = ptr::null();
+                let result =
+                    sd_bus_message_read(message, b"s\0".as_ptr() as *const c_char, &mut string);
+                if result < 0 {
+                    return Err(Error::SDError(result));
+                }
+                if result == 0 {
+                    return Err(Error::SDError(-libc::EBADMSG));
+                }
+                let string = CStr::from_ptr(string).to_str()
+                                                   .map_err(|_| Error::SDError(-libc::EBADMSG))?;
+                ID128::from_str(string)
+            }
+        }
+    }
+}
+
+/// Translates a name into a C string.
+fn c_string(name: &str) -> Result<CString, Error> {
+    CString::new(name).map_err(Error::NullError)
+}
+
+/// Error buffer of a single call, released on drop.
+struct BusError(sd_bus_error);
+
+impl BusError {
+    fn new() -> Self {
+        BusError(sd_bus_error { name:      ptr::null(),
+                                message:   ptr::null(),
+                                need_free: 0 })
+    }
+}
+
+impl Drop for BusError {
+    fn drop(&mut self) {
+        unsafe { sd_bus_error_free(&mut self.0) };
+    }
+}
+
+/// Reply of a method call, released on drop.
+struct Message(*mut sd_bus_message);
+
+impl Drop for Message {
+    fn drop(&mut self) {
+        unsafe { sd_bus_message_unref(self.0) };
+    }
+}
+
+/// Connection to the system bus, released on drop.
+pub(crate) struct Bus(*mut sd_bus);
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        unsafe { sd_bus_unref(self.0) };
+    }
+}
+
+impl Bus {
+    /// Connects to the system bus.
+    pub(crate) fn system() -> Result<Self, Error> {
+        let mut bus = ptr::null_mut();
+        let result = unsafe { sd_bus_open_system(&mut bus) };
+        if result < 0 {
+            return Err(Error::SDError(result));
+        }
+        Ok(Bus(bus))
+    }
+
+    /// Calls a method taking a single string and returning an object path.
+    pub(crate) fn call_object_path(&self,
+                                   destination: &str,
+                                   path: &str,
+                                   interface: &str,
+                                   method: &str,
+                                   argument: &str)
+                                   -> Result<String, Error> {
+        let (destination, path) = (c_string(destination)?, c_string(path)?);
+        let (interface, method) = (c_string(interface)?, c_string(method)?);
+        let argument = c_string(argument)?;
+        let mut error = BusError::new();
+        let mut reply = Message(ptr::null_mut());
+        let result = unsafe {
+            sd_bus_call_method(self.0,
+                               destination.as_ptr(),
+                               path.as_ptr(),
+                               interface.as_ptr(),
+                               method.as_ptr(),
+                               &mut error.0,
+                               &mut reply.0,
+                               b"s\0".as_ptr() as *const c_char,
+                               argument.as_ptr())
+        };
+        if result < 0 {
+            return Err(Error::SDError(result));
+        }
+        let mut object: *const c_char = ptr::null();
+        let result =
+            unsafe { sd_bus_message_read(reply.0, b"o\0".as_ptr() as *const c_char, &mut object) };
+        if result < 0 {
+            return Err(Error::SDError(result));
+        }
+        let object = unsafe { CStr::from_ptr(object) };
+        object.to_str()
+              .map(String::from)
+              .map_err(|_| Error::SDError(-libc::EBADMSG))
+    }
+
+    /// Reads a property of type `ay` holding an ID.
+    ///
+    /// An empty or null ID is reported as ENXIO.
+    pub(crate) fn get_id_property(&self,
+                                  destination: &str,
+                                  path: &str,
+                                  interface: &str,
+                                  property: &str)
+                                  -> Result<[u8; 16], Error> {
+        let (destination, path) = (c_string(destination)?, c_string(path)?);
+        let (interface, property) = (c_string(interface)?, c_string(property)?);
+        let mut error = BusError::new();
+        let mut reply = Message(ptr::null_mut());
+        let result = unsafe {
+            sd_bus_get_property(self.0,
+                                destination.as_ptr(),
+                                path.as_ptr(),
+                                interface.as_ptr(),
+                                property.as_ptr(),
+                                &mut error.0,
+                                &mut reply.0,
+                                b"ay\0".as_ptr() as *const c_char)
+        };
+        if result < 0 {
+            return Err(Error::SDError(result));
+        }
+        let mut data: *const c_void = ptr::null();
+        let mut size = 0usize;
+        let result =
+            unsafe { sd_bus_message_read_array(reply.0, b'y' as c_char, &mut data, &mut size) };
+        if result < 0 {
+            return Err(Error::SDError(result));
+        }
+        if size == 0 {
+            return Err(Error::SDError(-libc::ENXIO));
+        }
+        if size != 16 {
+            return Err(Error::SDError(-libc::EBADMSG));
+        }
+        let mut value = [0u8; 16];
+        value.copy_from_slice(unsafe { std::slice::from_raw_parts(data as *const u8, size) });
+        if value == [0u8; 16] {
+            return Err(Error::SDError(-libc::ENXIO));
+        }
+        Ok(value)
+    }
+}
+
+impl ID128 {
+    /// Returns the machine ID of a container registered with
+    /// systemd-machined.
+    ///
+    /// The machine is looked up by name via `GetMachine()` of
+    /// `org.freedesktop.machine1.Manager` on the system bus and its `Id`
+    /// property is returned. This is the ID shown by `machinectl status`,
+    /// which allows correlating the identities of containers without
+    /// shelling out to machinectl.
+    ///
+    /// This function is only available with the feature `dbus`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::NullError): the name contains a 0-byte
+    /// - Err(Error::SDError(i32)): the query failed, e.g. the machine is not
+    ///   registered, or the machine has no ID (ENXIO); the code is the negative
+    ///   errno
+    pub fn machine_id_of_container(name: &str) -> Result<Self, Error> {
+        const MACHINED: &str = "org.freedesktop.machine1";
+        let bus = Bus::system()?;
+        let path = bus.call_object_path(MACHINED,
+                                        "/org/freedesktop/machine1",
+                                        "org.freedesktop.machine1.Manager",
+                                        "GetMachine",
+                                        name)?;
+        bus.get_id_property(MACHINED, &path, "org.freedesktop.machine1.Machine", "Id")
+           .map(ID128::from_raw_value)
+    }
+
+    /// Returns the current invocation ID of a unit of the service manager.
+    ///
+    /// The unit is looked up by name via `GetUnit()` of
+    /// `org.freedesktop.systemd1.Manager` on the system bus and its
+    /// `InvocationID` property is returned. This allows monitoring tools to
+    /// correlate journal entries of other services (field
+    /// `_SYSTEMD_INVOCATION_ID`) with their current invocation.
+    ///
+    /// This function is only available with the feature `dbus`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::NullError): the name contains a 0-byte
+    /// - Err(Error::SDError(i32)): the query failed, e.g. the unit is not
+    ///   loaded, or the unit is not running (ENXIO); the code is the negative
+    ///   errno
+    pub fn invocation_id_of_unit(unit: &str) -> Result<Self, Error> {
+        const SYSTEMD: &str = "org.freedesktop.systemd1";
+        let bus = Bus::system()?;
+        let path = bus.call_object_path(SYSTEMD,
+                                        "/org/freedesktop/systemd1",
+                                        "org.freedesktop.systemd1.Manager",
+                                        "GetUnit",
+                                        unit)?;
+        bus.get_id_property(SYSTEMD,
+                            &path,
+                            "org.freedesktop.systemd1.Unit",
+                            "InvocationID")
+           .map(ID128::from_raw_value)
+    }
+
+    /// Returns the machine ID of the host as reported by systemd-hostnamed.
+    ///
+    /// The `MachineID` property of `org.freedesktop.hostname1` is read from
+    /// the system bus. This is useful for sandboxed processes which cannot
+    /// read `/etc/machine-id` but may talk to the system bus. The property is
+    /// available since systemd 248.
+    ///
+    /// This function is only available with the feature `dbus`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the query failed, the code is the negative
+    ///   errno
+    pub fn machine_id_via_hostnamed() -> Result<Self, Error> {
+        Bus::system()?.get_id_property("org.freedesktop.hostname1",
+                                       "/org/freedesktop/hostname1",
+                                       "org.freedesktop.hostname1",
+                                       "MachineID")
+                      .map(ID128::from_raw_value)
+    }
+}
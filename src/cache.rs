@@ -0,0 +1,122 @@
+// sd-id128: process-wide cache of the system IDs
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{native::{read_id_file, MACHINE_ID_PATH},
+            Error, ID128};
+use std::{path::Path,
+          sync::{OnceLock, RwLock}};
+
+// The machine ID may be replaced by `refresh`, previous results are leaked so
+// references handed out by `machine_id_cached` remain valid. An unchanged
+// result is kept, so only changes of the machine ID leak memory.
+static MACHINE_ID: RwLock<Option<&'static Result<ID128, Error>>> = RwLock::new(None);
+static BOOT_ID: OnceLock<Result<ID128, Error>> = OnceLock::new();
+#[cfg(any(systemd_v232, feature = "no-ffi", not(feature = "ffi")))]
+static INVOCATION_ID: OnceLock<Result<ID128, Error>> = OnceLock::new();
+
+impl ID128 {
+    /// Returns the machine ID, retrieved once per process.
+    ///
+    /// The first call retrieves the ID via `machine_id`, every later call
+    /// returns a reference to the same result without any FFI call or
+    /// allocation, e.g. for tagging every record on hot logging paths. A
+    /// failure is cached as well, until the cache is updated by `refresh`.
+    ///
+    /// # Return Values
+    /// - Ok(&ID128): the machine ID
+    /// - Err(&Error): the error of the first retrieval
+    pub fn machine_id_cached() -> Result<&'static ID128, &'static Error> {
+        if let Some(cached) = *MACHINE_ID.read().unwrap_or_else(|error| error.into_inner()) {
+            return cached.as_ref();
+        }
+        let mut cache = MACHINE_ID.write()
+                                  .unwrap_or_else(|error| error.into_inner());
+        let cached = *cache.get_or_insert_with(|| Box::leak(Box::new(ID128::machine_id())));
+        cached.as_ref()
+    }
+
+    /// Returns the machine ID by reading `/etc/machine-id` on every call.
+    ///
+    /// libsystemd reads the machine ID once and caches it for the lifetime of
+    /// the process, and so does `machine_id_cached`. Provisioning daemons
+    /// running while `/etc/machine-id` is initialized during first boot need
+    /// the current content of the file instead. The file is validated like
+    /// `machine_id_of` does.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the machine ID could not be read, the code
+    ///   is the negative errno
+    pub fn machine_id_uncached() -> Result<Self, Error> {
+        read_id_file(Path::new(MACHINE_ID_PATH)).map(ID128::from_raw_value)
+    }
+
+    /// Returns the boot ID, retrieved once per process.
+    ///
+    /// The first call retrieves the ID via `boot_id`, every later call returns
+    /// a reference to the same result. A failure is cached as well.
+    ///
+    /// # Return Values
+    /// - Ok(&ID128): the boot ID
+    /// - Err(&Error): the error of the first retrieval
+    pub fn boot_id_cached() -> Result<&'static ID128, &'static Error> {
+        BOOT_ID.get_or_init(ID128::boot_id).as_ref()
+    }
+
+    /// Returns the invocation ID of the service, retrieved once per process.
+    ///
+    /// The first call retrieves the ID via `invocation_id`, every later call
+    /// returns a reference to the same result. A failure, e.g. for a process
+    /// not started by systemd, is cached as well.
+    ///
+    /// # Return Values
+    /// - Ok(&ID128): the invocation ID
+    /// - Err(&Error): the error of the first retrieval
+    #[cfg(any(systemd_v232, feature = "no-ffi", not(feature = "ffi")))]
+    pub fn invocation_id_cached() -> Result<&'static ID128, &'static Error> {
+        INVOCATION_ID.get_or_init(ID128::invocation_id).as_ref()
+    }
+}
+
+/// Re-reads `/etc/machine-id` and updates the machine ID returned by
+/// `ID128::machine_id_cached`.
+///
+/// Long-running daemons call this after the machine ID was committed, e.g.
+/// when `systemd-machine-id-commit.service` finished. References returned by
+/// `machine_id_cached` before remain valid and keep the previous ID. If the
+/// result did not change, the cache entry is kept; otherwise the previous
+/// entry of a few bytes is leaked, so polling only costs memory when the
+/// machine ID actually changes.
+///
+/// Note that libsystemd keeps its own cache: `ID128::machine_id` continues to
+/// return the ID it read first.
+///
+/// # Return Values
+/// - Ok(&ID128): the current machine ID
+/// - Err(&Error::SDError(i32)): the machine ID could not be read, the code is
+///   the negative errno
+pub fn refresh() -> Result<&'static ID128, &'static Error> {
+    let current = ID128::machine_id_uncached();
+    let mut cache = MACHINE_ID.write()
+                              .unwrap_or_else(|error| error.into_inner());
+    match *cache {
+        Some(cached) if *cached == current => cached.as_ref(),
+        _ => {
+            let current = &*Box::leak(Box::new(current));
+            *cache = Some(current);
+            current.as_ref()
+        }
+    }
+}
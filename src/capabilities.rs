@@ -0,0 +1,60 @@
+// sd-id128: runtime probing of libsystemd
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::backend;
+
+/// Functionality of libsystemd available at runtime.
+///
+/// Compile-time features only state which functions a program expects. The
+/// libsystemd actually in use may be older (feature `dynamic`) or not in use
+/// at all (feature `no-ffi`). Each field reports whether the corresponding
+/// symbol is available in the libsystemd used by this crate:
+/// - libsystemd: libsystemd is used at all
+/// - invocation_id: `sd_id128_get_invocation()`, systemd 232
+/// - machine_id_app_specific: `sd_id128_get_machine_app_specific()`, systemd
+///   233
+/// - boot_id_app_specific: `sd_id128_get_boot_app_specific()`, systemd 240
+/// - app_specific: `sd_id128_get_app_specific()`, systemd 255
+///
+/// Note that `machine_id`, `boot_id`, `invocation_id` and `random_id` are
+/// implemented natively if libsystemd is not in use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub libsystemd:              bool,
+    pub invocation_id:           bool,
+    pub machine_id_app_specific: bool,
+    pub boot_id_app_specific:    bool,
+    pub app_specific:            bool
+}
+
+/// Probes the libsystemd in use for its capabilities.
+///
+/// The symbols are looked up in the libsystemd linked into the process or
+/// loaded at runtime. This allows callers to degrade gracefully, e.g. to fall
+/// back from `boot_id_app_specific` to `boot_id_app_specific_native` on hosts
+/// with an old libsystemd. A statically linked libsystemd is reported to
+/// provide the functions of the systemd version selected at build time.
+pub fn capabilities() -> Capabilities {
+    if !backend::has_symbol(b"sd_id128_get_machine\0") {
+        return Capabilities::default();
+    }
+    Capabilities { libsystemd:              true,
+                   invocation_id:           backend::has_symbol(b"sd_id128_get_invocation\0"),
+                   machine_id_app_specific:
+                       backend::has_symbol(b"sd_id128_get_machine_app_specific\0"),
+                   boot_id_app_specific:
+                       backend::has_symbol(b"sd_id128_get_boot_app_specific\0"),
+                   app_specific:            backend::has_symbol(b"sd_id128_get_app_specific\0") }
+}
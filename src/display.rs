@@ -0,0 +1,285 @@
+// sd-id128: adapters displaying IDs in a given format
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{Case, Error, Format, ID128};
+#[cfg(feature = "arrayvec")]
+use arrayvec::ArrayString;
+use std::{fmt, io, str,
+          sync::{atomic::{AtomicU8, Ordering},
+                 RwLock}};
+
+// The format and case of `Display`, encoded as twice the format plus the
+// case. A custom format is stored in `CUSTOM`.
+static DISPLAY: AtomicU8 = AtomicU8::new(4);
+static CUSTOM: RwLock<Format> = RwLock::new(Format::RFC);
+
+/// Sets the process-wide format and case of the plain `Display` of `ID128`,
+/// e.g. `{}` in `format!` and logging macros. The default is RFC in lower
+/// case. The alternate form `{:#}` always displays the libsystemd format.
+pub fn set_display_format(format: Format, case: Case) {
+    let encoded = match format {
+        Format::Simple => 0,
+        Format::LibSystemD => 1,
+        Format::RFC => 2,
+        Format::Urn => 3,
+        Format::Braced => 4,
+        Format::Custom { .. } => {
+            *CUSTOM.write().unwrap_or_else(|error| error.into_inner()) = format;
+            5
+        }
+    };
+    let case = match case {
+        Case::Lower => 0,
+        Case::Upper => 1
+    };
+    DISPLAY.store(encoded * 2 + case, Ordering::Relaxed);
+}
+
+/// Returns the process-wide format and case of the plain `Display`.
+pub fn display_format() -> (Format, Case) {
+    let encoded = DISPLAY.load(Ordering::Relaxed);
+    let format = match encoded / 2 {
+        0 => Format::Simple,
+        1 => Format::LibSystemD,
+        3 => Format::Urn,
+        4 => Format::Braced,
+        5 => *CUSTOM.read().unwrap_or_else(|error| error.into_inner()),
+        _ => Format::RFC
+    };
+    let case = match encoded % 2 {
+        0 => Case::Lower,
+        _ => Case::Upper
+    };
+    (format, case)
+}
+
+/// An ID displayed in a given format and case.
+///
+/// The adapter borrows the ID and writes it directly into the formatter, so
+/// `format!("{}", id128.simple())` or logging macros do not build an
+/// intermediate String. Adapters are lower case unless `upper` is applied.
+///
+/// ```rust
+/// use sd_id128::ID128;
+/// let id128 = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+/// assert_eq!(format!("{}", id128.libsystemd().upper()),
+///            "0123456789ABCDEF0123456789ABCDEF");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Formatted<'a> {
+    id128:  &'a ID128,
+    format: Format,
+    case:   Case
+}
+
+impl Formatted<'_> {
+    /// Displays hexadecimal letters in upper case.
+    pub fn upper(mut self) -> Self {
+        self.case = Case::Upper;
+        self
+    }
+
+    /// Displays hexadecimal letters in lower case.
+    pub fn lower(mut self) -> Self {
+        self.case = Case::Lower;
+        self
+    }
+}
+
+/// Writer filling a fixed buffer, fails if the buffer is too small.
+pub(crate) struct Buffer<'a> {
+    bytes:  &'a mut [u8],
+    length: usize
+}
+
+impl<'a> Buffer<'a> {
+    pub(crate) fn new(bytes: &'a mut [u8]) -> Self {
+        Buffer { bytes,
+                 length: 0 }
+    }
+
+    /// Returns the text written so far.
+    pub(crate) fn into_str(self) -> &'a str {
+        let bytes: &'a [u8] = self.bytes;
+        str::from_utf8(&bytes[..self.length]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for Buffer<'_> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        let end = self.length + string.len();
+        if end > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.length..end].copy_from_slice(string.as_bytes());
+        self.length = end;
+        Ok(())
+    }
+}
+
+/// The ID is written directly into the formatter without any allocation.
+/// Width, fill, alignment and precision are honored like for `ID128`; custom
+/// formats longer than 64 bytes are then formatted into a String first.
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if formatter.width().is_none() && formatter.precision().is_none() {
+            return self.id128
+                       .write_formatted(formatter, self.format, self.case);
+        }
+        let mut bytes = [0u8; 64];
+        let mut buffer = Buffer::new(&mut bytes);
+        if self.id128
+               .write_formatted(&mut buffer, self.format, self.case)
+               .is_ok()
+        {
+            return formatter.pad(buffer.into_str());
+        }
+        formatter.pad(&self.id128.to_string_formatted(self.format, self.case))
+    }
+}
+
+/// Writes the 32 hexadecimal digits like an integer, i.e. `{:#x}` prepends
+/// `0x` and width, fill, alignment and zero padding are honored.
+fn pad_hex(id128: &ID128, case: Case, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let hex = id128.hex_digits(case);
+    formatter.pad_integral(true, "0x", str::from_utf8(&hex).map_err(|_| fmt::Error)?)
+}
+
+/// Displays the ID as 32 lower case hexadecimal digits, e.g. via `{:x}`.
+impl fmt::LowerHex for ID128 {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        pad_hex(self, Case::Lower, formatter)
+    }
+}
+
+/// Displays the ID as 32 upper case hexadecimal digits, e.g. via `{:X}`.
+impl fmt::UpperHex for ID128 {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        pad_hex(self, Case::Upper, formatter)
+    }
+}
+
+impl ID128 {
+    /// Formats the ID into a caller supplied buffer without any allocation.
+    ///
+    /// The buffer must hold at least `format.encoded_len()` bytes.
+    ///
+    /// # Return Values
+    /// - Ok(&str): the formatted ID at the start of `buffer`
+    /// - Err(Error::SDError(-ERANGE)): the buffer is too small
+    pub fn encode_into<'a>(&self,
+                           buffer: &'a mut [u8],
+                           format: Format,
+                           case: Case)
+                           -> Result<&'a str, Error> {
+        let mut writer = Buffer::new(buffer);
+        self.write_formatted(&mut writer, format, case)
+            .map_err(|_| Error::SDError(-libc::ERANGE))?;
+        Ok(writer.into_str())
+    }
+
+    /// Appends the ID in `format` and `case` to a `fmt::Write`, e.g. a String
+    /// or a Formatter, without intermediate Strings.
+    pub fn write_to<W: fmt::Write + ?Sized>(&self,
+                                            writer: &mut W,
+                                            format: Format,
+                                            case: Case)
+                                            -> fmt::Result {
+        self.write_formatted(writer, format, case)
+    }
+
+    /// Writes the ID in `format` and `case` to an `io::Write`, e.g. a file or
+    /// a socket, with a single call of `write_all`.
+    pub fn write_io<W: io::Write + ?Sized>(&self,
+                                           writer: &mut W,
+                                           format: Format,
+                                           case: Case)
+                                           -> io::Result<()> {
+        let mut bytes = [0u8; 64];
+        match self.encode_into(&mut bytes, format, case) {
+            Ok(string) => writer.write_all(string.as_bytes()),
+            Err(_) => writer.write_all(self.to_string_formatted(format, case).as_bytes())
+        }
+    }
+
+    /// Formats the ID into a stack allocated string of capacity `N`, e.g.
+    /// `ArrayString<36>` for the RFC format.
+    ///
+    /// This function is only available with the feature `arrayvec`.
+    ///
+    /// # Return Values
+    /// - Ok(ArrayString<N>): the formatted ID
+    /// - Err(Error::SDError(-ERANGE)): the capacity is too small for `format`
+    #[cfg(feature = "arrayvec")]
+    pub fn to_array_string<const N: usize>(&self,
+                                           format: Format,
+                                           case: Case)
+                                           -> Result<ArrayString<N>, Error> {
+        let mut string = ArrayString::new();
+        self.write_formatted(&mut string, format, case)
+            .map_err(|_| Error::SDError(-libc::ERANGE))?;
+        Ok(string)
+    }
+
+    /// Returns the ID in libsystemd format as fixed size array, e.g. for
+    /// embedded code or binary protocols.
+    pub fn to_libsystemd_bytes(&self, case: Case) -> [u8; 32] {
+        self.hex_digits(case)
+    }
+
+    /// Returns the ID in RFC format as fixed size array.
+    pub fn to_rfc_bytes(&self, case: Case) -> [u8; 36] {
+        let mut bytes = [0u8; 36];
+        let _ = self.encode_into(&mut bytes, Format::RFC, case);
+        bytes
+    }
+
+    /// Returns an adapter displaying the ID in `format` and `case`.
+    pub fn formatted(&self, format: Format, case: Case) -> Formatted<'_> {
+        Formatted { id128: self,
+                    format,
+                    case }
+    }
+
+    /// Returns an adapter displaying the ID in RFC format:
+    /// 01234567-89ab-cdef-0123-456789abcdef.
+    pub fn rfc(&self) -> Formatted<'_> {
+        self.formatted(Format::RFC, Case::Lower)
+    }
+
+    /// Returns an adapter displaying the ID in simple format:
+    /// 0123-4567-89ab-cdef-0123-4567-89ab-cdef.
+    pub fn simple(&self) -> Formatted<'_> {
+        self.formatted(Format::Simple, Case::Lower)
+    }
+
+    /// Returns an adapter displaying the ID in libsystemd format:
+    /// 0123456789abcdef0123456789abcdef.
+    pub fn libsystemd(&self) -> Formatted<'_> {
+        self.formatted(Format::LibSystemD, Case::Lower)
+    }
+
+    /// Returns an adapter displaying the ID as URN:
+    /// urn:uuid:01234567-89ab-cdef-0123-456789abcdef.
+    pub fn urn(&self) -> Formatted<'_> {
+        self.formatted(Format::Urn, Case::Lower)
+    }
+
+    /// Returns an adapter displaying the ID in braces:
+    /// {01234567-89ab-cdef-0123-456789abcdef}.
+    pub fn braced(&self) -> Formatted<'_> {
+        self.formatted(Format::Braced, Case::Lower)
+    }
+}
@@ -0,0 +1,150 @@
+// sd-id128: libsystemd loaded at runtime
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// libsystemd is opened on first use. If the library or a single symbol is not
+// available, the native implementation is used instead; the app specific IDs
+// are derived natively with the feature `crypto` and fail with ENOSYS
+// otherwise. Errors reported by libsystemd itself are passed on as they are.
+#[cfg(feature = "crypto")]
+use crate::ID128;
+use crate::{ffi, native, Error};
+use libloading::Library;
+use std::sync::OnceLock;
+
+const LIBRARY_NAME: &str = "libsystemd.so.0";
+
+type GetFunction = unsafe extern "C" fn(*mut ffi::sd_id128) -> libc::c_int;
+type AppFunction = unsafe extern "C" fn(ffi::sd_id128, *mut ffi::sd_id128) -> libc::c_int;
+type BaseFunction =
+    unsafe extern "C" fn(ffi::sd_id128, ffi::sd_id128, *mut ffi::sd_id128) -> libc::c_int;
+
+/// Symbols resolved from libsystemd; the library stays loaded as long as the
+/// function pointers are in use, i.e. for the lifetime of the process.
+struct LibSystemD {
+    library:                  Library,
+    randomize:                Option<GetFunction>,
+    get_machine:              Option<GetFunction>,
+    get_boot:                 Option<GetFunction>,
+    get_invocation:           Option<GetFunction>,
+    get_machine_app_specific: Option<AppFunction>,
+    get_boot_app_specific:    Option<AppFunction>,
+    get_app_specific:         Option<BaseFunction>
+}
+
+static LIBSYSTEMD: OnceLock<Option<LibSystemD>> = OnceLock::new();
+
+/// Returns the function of the NUL terminated symbol, None if it is missing.
+fn resolve<T: Copy>(library: &Library, name: &[u8]) -> Option<T> {
+    unsafe { library.get::<T>(name).ok().map(|symbol| *symbol) }
+}
+
+fn load() -> Option<LibSystemD> {
+    let library = unsafe { Library::new(LIBRARY_NAME) }.ok()?;
+    Some(LibSystemD { randomize: resolve(&library, b"sd_id128_randomize\0"),
+                      get_machine: resolve(&library, b"sd_id128_get_machine\0"),
+                      get_boot: resolve(&library, b"sd_id128_get_boot\0"),
+                      get_invocation: resolve(&library, b"sd_id128_get_invocation\0"),
+                      get_machine_app_specific: resolve(&library,
+                                                        b"sd_id128_get_machine_app_specific\0"),
+                      get_boot_app_specific: resolve(&library,
+                                                     b"sd_id128_get_boot_app_specific\0"),
+                      get_app_specific: resolve(&library, b"sd_id128_get_app_specific\0"),
+                      library })
+}
+
+/// Returns true if the NUL terminated symbol is available in libsystemd.
+pub(crate) fn has_symbol(name: &[u8]) -> bool {
+    match LIBSYSTEMD.get_or_init(load) {
+        Some(libsystemd) => unsafe { libsystemd.library.get::<GetFunction>(name).is_ok() },
+        None => false
+    }
+}
+
+/// Calls a function of libsystemd via `invoke`, None if it is not available.
+fn call<F>(invoke: F) -> Option<Result<[u8; 16], Error>>
+    where F: FnOnce(&LibSystemD, &mut ffi::sd_id128) -> Option<libc::c_int> {
+    let libsystemd = LIBSYSTEMD.get_or_init(load).as_ref()?;
+    let mut id128 = ffi::sd_id128::default();
+    let result = invoke(libsystemd, &mut id128)?;
+    if result < 0 {
+        return Some(Err(Error::SDError(result)));
+    }
+    Some(Ok(id128.value))
+}
+
+/// Derives an app specific ID natively, see `ID128::app_specific_native`.
+#[cfg(feature = "crypto")]
+fn app_specific_native(base: Result<[u8; 16], Error>, app: [u8; 16]) -> Result<[u8; 16], Error> {
+    Ok(ID128::app_specific_native(ID128::from_raw_value(base?),
+                                  ID128::from_raw_value(app)).into_raw_value())
+}
+
+#[cfg(not(feature = "crypto"))]
+fn app_specific_native(_base: Result<[u8; 16], Error>, _app: [u8; 16]) -> Result<[u8; 16], Error> {
+    Err(Error::SDError(-libc::ENOSYS))
+}
+
+pub(crate) fn randomize() -> Result<[u8; 16], Error> {
+    call(|libsystemd, id128| {
+        libsystemd.randomize
+                  .map(|function| unsafe { function(id128) })
+    }).unwrap_or_else(native::randomize)
+}
+
+pub(crate) fn get_machine() -> Result<[u8; 16], Error> {
+    call(|libsystemd, id128| {
+        libsystemd.get_machine
+                  .map(|function| unsafe { function(id128) })
+    }).unwrap_or_else(native::get_machine)
+}
+
+pub(crate) fn get_boot() -> Result<[u8; 16], Error> {
+    call(|libsystemd, id128| {
+        libsystemd.get_boot
+                  .map(|function| unsafe { function(id128) })
+    }).unwrap_or_else(native::get_boot)
+}
+
+pub(crate) fn get_invocation() -> Result<[u8; 16], Error> {
+    call(|libsystemd, id128| {
+        libsystemd.get_invocation
+                  .map(|function| unsafe { function(id128) })
+    }).unwrap_or_else(native::get_invocation)
+}
+
+pub(crate) fn get_machine_app_specific(app: [u8; 16]) -> Result<[u8; 16], Error> {
+    call(|libsystemd, id128| {
+        libsystemd.get_machine_app_specific
+                  .map(|function| unsafe { function(ffi::sd_id128 { value: app }, id128) })
+    }).unwrap_or_else(|| app_specific_native(get_machine(), app))
+}
+
+pub(crate) fn get_boot_app_specific(app: [u8; 16]) -> Result<[u8; 16], Error> {
+    call(|libsystemd, id128| {
+        libsystemd.get_boot_app_specific
+                  .map(|function| unsafe { function(ffi::sd_id128 { value: app }, id128) })
+    }).unwrap_or_else(|| app_specific_native(get_boot(), app))
+}
+
+pub(crate) fn get_app_specific(base: [u8; 16], app: [u8; 16]) -> Result<[u8; 16], Error> {
+    call(|libsystemd, id128| {
+        libsystemd.get_app_specific.map(|function| unsafe {
+                                       function(ffi::sd_id128 { value: base },
+                                                ffi::sd_id128 { value: app },
+                                                id128)
+                                   })
+    }).unwrap_or_else(|| app_specific_native(Ok(base), app))
+}
@@ -0,0 +1,162 @@
+// sd-id128: entropy sources for random IDs
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Process-wide configuration of the entropy source of random IDs.
+//!
+//! All random IDs of this crate, i.e. `ID128::random_id`,
+//! `ID128::random_batch`, `ID128::fast_random_id` and the generators built on
+//! top of them, honor the configured policy. Only explicit constructors like
+//! `ID128::random_id_getrandom` or `ID128::random_with` bypass it.
+//!
+//! ```rust
+//! use sd_id128::{entropy::{self, Blocking, EntropyPolicy},
+//!                ID128};
+//! entropy::set_policy(EntropyPolicy::GetRandom(Blocking::NonBlock));
+//! let id128 = ID128::random_id();
+//! entropy::set_policy(EntropyPolicy::LibSystemD);
+//! ```
+use crate::{from_io, Error};
+use std::{fs::File,
+          io::{self, Read},
+          sync::atomic::{AtomicU8, Ordering}};
+
+/// Source of the random bits of random IDs.
+///
+/// Variants:
+/// - LibSystemD: `random_id` calls `sd_id128_randomize()`, bulk requests read
+///   from `getrandom(2)` like libsystemd does internally; this is the default.
+///   With the feature `no-ffi`, `random_id` reads from `getrandom(2)` as well.
+/// - GetRandom: the kernel random number generator is read via `getrandom(2)`
+///   with the given blocking behavior; other systems than Linux read
+///   `getentropy(3)` or `RtlGenRandom()` on Windows regardless of the blocking
+///   behavior
+/// - DevURandom: `/dev/urandom` is read, which never blocks, e.g. for kernels
+///   without `getrandom(2)`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EntropyPolicy {
+    #[default]
+    LibSystemD,
+    GetRandom(Blocking),
+    DevURandom
+}
+
+/// Behavior of `getrandom(2)` while the kernel random number generator is not
+/// yet initialized, which matters during early boot.
+///
+/// Variants:
+/// - Block: wait until the random number generator is initialized
+/// - NonBlock: fail with `EAGAIN` instead of waiting (`GRND_NONBLOCK`)
+/// - Insecure: return bits even if the random number generator is not yet
+///   initialized (`GRND_INSECURE`, Linux 5.6+); such IDs are unique but not
+///   necessarily unpredictable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocking {
+    Block,
+    NonBlock,
+    Insecure
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the process-wide entropy policy.
+pub fn set_policy(policy: EntropyPolicy) {
+    let encoded = match policy {
+        EntropyPolicy::LibSystemD => 0,
+        EntropyPolicy::GetRandom(Blocking::Block) => 1,
+        EntropyPolicy::GetRandom(Blocking::NonBlock) => 2,
+        EntropyPolicy::GetRandom(Blocking::Insecure) => 3,
+        EntropyPolicy::DevURandom => 4
+    };
+    POLICY.store(encoded, Ordering::Relaxed);
+}
+
+/// Returns the process-wide entropy policy.
+pub fn policy() -> EntropyPolicy {
+    match POLICY.load(Ordering::Relaxed) {
+        1 => EntropyPolicy::GetRandom(Blocking::Block),
+        2 => EntropyPolicy::GetRandom(Blocking::NonBlock),
+        3 => EntropyPolicy::GetRandom(Blocking::Insecure),
+        4 => EntropyPolicy::DevURandom,
+        _ => EntropyPolicy::LibSystemD
+    }
+}
+
+/// Reads random bytes via `getrandom(2)`.
+#[cfg(target_os = "linux")]
+fn getrandom(buffer: &mut [u8], blocking: Blocking) -> Result<(), Error> {
+    let flags = match blocking {
+        Blocking::Block => 0,
+        Blocking::NonBlock => libc::GRND_NONBLOCK,
+        Blocking::Insecure => libc::GRND_INSECURE
+    };
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let remaining = &mut buffer[filled..];
+        let result =
+            unsafe { libc::getrandom(remaining.as_mut_ptr().cast(), remaining.len(), flags) };
+        if result < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(from_io(error));
+        }
+        filled += result as usize;
+    }
+    Ok(())
+}
+
+/// Reads random bytes via `getentropy(3)`, which is limited to 256 bytes per
+/// call and never blocks once the system is up.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn getrandom(buffer: &mut [u8], _blocking: Blocking) -> Result<(), Error> {
+    for chunk in buffer.chunks_mut(256) {
+        if unsafe { libc::getentropy(chunk.as_mut_ptr().cast(), chunk.len()) } < 0 {
+            return Err(from_io(io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+#[link(name = "advapi32")]
+extern "system" {
+    #[link_name = "SystemFunction036"]
+    fn RtlGenRandom(buffer: *mut std::ffi::c_void, length: u32) -> u8;
+}
+
+/// Reads random bytes via `RtlGenRandom()`, which never blocks.
+#[cfg(windows)]
+fn getrandom(buffer: &mut [u8], _blocking: Blocking) -> Result<(), Error> {
+    for chunk in buffer.chunks_mut(65536) {
+        if unsafe { RtlGenRandom(chunk.as_mut_ptr().cast(), chunk.len() as u32) } == 0 {
+            return Err(from_io(io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+/// Fills `buffer` with random bytes according to the entropy policy.
+pub(crate) fn fill(buffer: &mut [u8]) -> Result<(), Error> {
+    match policy() {
+        EntropyPolicy::LibSystemD => getrandom(buffer, Blocking::Block),
+        EntropyPolicy::GetRandom(blocking) => getrandom(buffer, blocking),
+        EntropyPolicy::DevURandom => {
+            File::open("/dev/urandom").and_then(|mut file| file.read_exact(buffer))
+                                      .map_err(from_io)
+        },
+    }
+}
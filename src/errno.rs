@@ -0,0 +1,133 @@
+// sd-id128: typed error codes of libsystemd
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use std::{fmt, io};
+
+/// Error codes returned by libsystemd and this library, see `Error::sd_errno`.
+///
+/// Variants:
+/// - ENOENT: a file, e.g. the ID file /etc/machine-id, does not exist
+/// - ENOMEDIUM: the ID file is empty or contains the null ID
+/// - ENOPKG: the ID file is uninitialized
+/// - EUCLEAN: the ID file has an invalid format
+/// - ENXIO: a device or address does not exist, e.g. no invocation ID or no
+///   credentials directory is set
+/// - EOPNOTSUPP: the operation is not supported, e.g. by the host
+/// - ENOSYS: the functionality is not available on this system
+/// - EINVAL: an argument is invalid
+/// - ERANGE: a buffer is too small
+/// - EIO: an input/output error occurred
+/// - EPERM: the operation is not permitted
+/// - EACCES: the access was denied
+/// - EBADMSG: a message, e.g. of D-Bus, is malformed
+/// - Other: any other errno value; 0 if libsystemd did not report one
+///
+/// The variants exist on all systems. ENOMEDIUM, ENOPKG and EUCLEAN are codes
+/// of Linux; other systems report the closest portable code instead, i.e.
+/// `errno` returns ENOENT or EBADMSG for them and `from_errno` never returns
+/// them.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum SdErrno {
+    ENOENT,
+    ENOMEDIUM,
+    ENOPKG,
+    EUCLEAN,
+    ENXIO,
+    EOPNOTSUPP,
+    ENOSYS,
+    EINVAL,
+    ERANGE,
+    EIO,
+    EPERM,
+    EACCES,
+    EBADMSG,
+    Other(i32)
+}
+
+/// Code of an empty ID file or the null ID, ENOENT on other systems than
+/// Linux.
+#[cfg(target_os = "linux")]
+pub(crate) const ENOMEDIUM: i32 = libc::ENOMEDIUM;
+#[cfg(not(target_os = "linux"))]
+pub(crate) const ENOMEDIUM: i32 = libc::ENOENT;
+
+/// Code of an uninitialized ID file, ENOENT on other systems than Linux.
+#[cfg(target_os = "linux")]
+pub(crate) const ENOPKG: i32 = libc::ENOPKG;
+#[cfg(not(target_os = "linux"))]
+pub(crate) const ENOPKG: i32 = libc::ENOENT;
+
+/// Code of an ID file of invalid format, EBADMSG on other systems than Linux.
+#[cfg(target_os = "linux")]
+pub(crate) const EUCLEAN: i32 = libc::EUCLEAN;
+#[cfg(not(target_os = "linux"))]
+pub(crate) const EUCLEAN: i32 = libc::EBADMSG;
+
+/// Codes by variant. The codes of ID files follow the portable codes, so that
+/// their fallbacks on other systems than Linux resolve to the portable variant.
+const ERRNOS: [(SdErrno, i32, &str, &str); 13] =
+    [(SdErrno::ENOENT, libc::ENOENT, "ENOENT", "no such file or directory"),
+     (SdErrno::ENXIO, libc::ENXIO, "ENXIO", "no such device or address"),
+     (SdErrno::EOPNOTSUPP, libc::EOPNOTSUPP, "EOPNOTSUPP", "operation not supported"),
+     (SdErrno::ENOSYS, libc::ENOSYS, "ENOSYS", "not supported on this system"),
+     (SdErrno::EINVAL, libc::EINVAL, "EINVAL", "invalid argument"),
+     (SdErrno::ERANGE, libc::ERANGE, "ERANGE", "buffer too small"),
+     (SdErrno::EIO, libc::EIO, "EIO", "input/output error"),
+     (SdErrno::EPERM, libc::EPERM, "EPERM", "operation not permitted"),
+     (SdErrno::EACCES, libc::EACCES, "EACCES", "permission denied"),
+     (SdErrno::EBADMSG, libc::EBADMSG, "EBADMSG", "bad message"),
+     (SdErrno::ENOMEDIUM, ENOMEDIUM, "ENOMEDIUM", "ID file is empty or contains the null ID"),
+     (SdErrno::ENOPKG, ENOPKG, "ENOPKG", "ID file is uninitialized"),
+     (SdErrno::EUCLEAN, EUCLEAN, "EUCLEAN", "ID file has an invalid format")];
+
+impl SdErrno {
+    /// Returns the error code of a positive errno value, e.g. `libc::ENOENT`.
+    pub fn from_errno(errno: i32) -> SdErrno {
+        ERRNOS.iter()
+              .find(|(_, known, ..)| *known == errno)
+              .map_or(SdErrno::Other(errno), |(sd_errno, ..)| *sd_errno)
+    }
+
+    /// Returns the positive errno value, e.g. `libc::ENOENT`.
+    pub fn errno(self) -> i32 {
+        match self {
+            SdErrno::Other(errno) => errno,
+            sd_errno => sd_errno.entry().map_or(0, |(_, errno, ..)| *errno)
+        }
+    }
+
+    /// Returns the symbolic name, e.g. "ENOENT", None for `Other`.
+    pub fn name(self) -> Option<&'static str> {
+        self.entry().map(|(_, _, name, _)| *name)
+    }
+
+    fn entry(self) -> Option<&'static (SdErrno, i32, &'static str, &'static str)> {
+        ERRNOS.iter().find(|(sd_errno, ..)| *sd_errno == self)
+    }
+}
+
+/// Displays the name and a description phrased for the context the code is
+/// raised in, e.g. "ENOPKG: ID file is uninitialized". Other codes are
+/// described by the operating system.
+impl fmt::Display for SdErrno {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.entry(), self) {
+            (Some((_, _, name, description)), _) => write!(formatter, "{}: {}", name, description),
+            (None, SdErrno::Other(0)) => write!(formatter, "unknown error in libsystemd"),
+            (None, sd_errno) => io::Error::from_raw_os_error(sd_errno.errno()).fmt(formatter)
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// sd-id128: fault injection into the libsystemd API
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//! Fault injection for testing the error handling of applications (feature
+//! `failpoints`).
+//!
+//! A failpoint makes a function of the libsystemd API fail with the given
+//! errno instead of retrieving the ID, no matter which backend is in use. It
+//! applies to the current thread until its guard is dropped, so tests running
+//! in parallel do not interfere. The cached accessors keep the result of their
+//! first call, failpoints only affect them before.
+//!
+//! The feature is meant for dev-dependencies only, it must not be enabled in
+//! production builds.
+//!
+//! ```rust
+//! use sd_id128::{failpoints::{self, Call},
+//!                Error, ID128};
+//! {
+//!     let _guard = failpoints::fail(Call::Machine, libc::ENOMEDIUM);
+//!     assert_eq!(ID128::machine_id(), Err(Error::SDError(-libc::ENOMEDIUM)));
+//! }
+//! ```
+use crate::Error;
+use std::cell::Cell;
+
+/// Functions of the libsystemd API a failpoint can be set on.
+///
+/// Variants:
+/// - Randomize: `ID128::random_id`
+/// - Machine: `ID128::machine_id` and functions derived from it
+/// - Boot: `ID128::boot_id` and functions derived from it
+/// - Invocation: `ID128::invocation_id`; ENXIO and ENOENT are reported as
+///   `Error::NotUnderSystemd` like the real errors
+/// - MachineAppSpecific: `ID128::machine_id_app_specific`
+/// - BootAppSpecific: `ID128::boot_id_app_specific`
+/// - AppSpecific: `ID128::app_specific`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Call {
+    Randomize,
+    Machine,
+    Boot,
+    Invocation,
+    MachineAppSpecific,
+    BootAppSpecific,
+    AppSpecific
+}
+
+thread_local! {
+    // positive errno per call, 0 if the call is not failing
+    static FAILPOINTS: Cell<[i32; 7]> = const { Cell::new([0; 7]) };
+}
+
+/// Restores the previous state of a failpoint when dropped.
+#[derive(Debug)]
+#[must_use = "the failpoint is removed when the guard is dropped"]
+pub struct FailGuard {
+    call:     Call,
+    previous: i32
+}
+
+impl Drop for FailGuard {
+    fn drop(&mut self) {
+        set(self.call, self.previous);
+    }
+}
+
+/// Sets the errno of a call, returning the previous one.
+fn set(call: Call, errno: i32) -> i32 {
+    FAILPOINTS.with(|failpoints| {
+                  let mut errnos = failpoints.get();
+                  let previous = errnos[call as usize];
+                  errnos[call as usize] = errno;
+                  failpoints.set(errnos);
+                  previous
+              })
+}
+
+/// Makes `call` fail with `Error::SDError(-errno)` on the current thread until
+/// the returned guard is dropped.
+///
+/// `errno` is the positive error code, e.g. `libc::ENOENT`; 0 removes the
+/// failpoint while the guard is alive.
+pub fn fail(call: Call, errno: i32) -> FailGuard {
+    FailGuard { call,
+                previous: set(call, errno.abs()) }
+}
+
+/// Returns the injected error of `call`, if any.
+pub(crate) fn check(call: Call) -> Result<(), Error> {
+    match FAILPOINTS.with(|failpoints| failpoints.get()[call as usize]) {
+        0 => Ok(()),
+        errno => Err(Error::SDError(-errno))
+    }
+}
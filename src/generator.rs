@@ -0,0 +1,238 @@
+// sd-id128: ID generation strategies
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Generation strategies behind a common trait.
+//!
+//! Application code may be written against `IdGenerator` and switch between
+//! strategies at runtime, e.g. based on configuration:
+//!
+//! ```rust
+//! use sd_id128::generator::{IdGenerator, Seeded, SystemdRandom};
+//! let testing = true;
+//! let mut generator: Box<dyn IdGenerator> = if testing {
+//!     Box::new(Seeded::new(42))
+//! } else {
+//!     Box::new(SystemdRandom)
+//! };
+//! let id128 = generator.generate().unwrap();
+//! ```
+use crate::{uuid::set_version, Error, ID128};
+use std::{process,
+          time::{SystemTime, UNIX_EPOCH}};
+
+/// Common interface of all ID generation strategies.
+///
+/// Provided implementations:
+/// - SystemdRandom: random IDs from libsystemd (`ID128::random_id`)
+/// - FastRandom: random IDs from a thread-local generator
+///   (`ID128::fast_random_id`)
+/// - GetRandom: random IDs from the getrandom crate
+///   (`ID128::random_id_getrandom`), requires the feature `getrandom`
+/// - V7: time-ordered UUID v7
+/// - Snowflake: machine-scoped, roughly sortable IDs
+/// - Seeded: deterministic IDs for tests (`ID128::from_seed`)
+pub trait IdGenerator {
+    /// Generates the next ID.
+    fn generate(&mut self) -> Result<ID128, Error>;
+}
+
+impl<G: IdGenerator + ?Sized> IdGenerator for Box<G> {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        (**self).generate()
+    }
+}
+
+impl<G: IdGenerator + ?Sized> IdGenerator for &mut G {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        (**self).generate()
+    }
+}
+
+/// Generates random IDs using libsystemd.
+#[derive(Debug, Default, Clone)]
+pub struct SystemdRandom;
+
+impl IdGenerator for SystemdRandom {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        ID128::random_id()
+    }
+}
+
+/// Generates random IDs using a thread-local generator.
+#[derive(Debug, Default, Clone)]
+pub struct FastRandom;
+
+impl IdGenerator for FastRandom {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        ID128::fast_random_id()
+    }
+}
+
+/// Generates random IDs using the getrandom crate.
+///
+/// This generator is only available with the feature `getrandom`.
+#[cfg(feature = "getrandom")]
+#[derive(Debug, Default, Clone)]
+pub struct GetRandom;
+
+#[cfg(feature = "getrandom")]
+impl IdGenerator for GetRandom {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        ID128::random_id_getrandom()
+    }
+}
+
+/// Returns the current Unix time in milliseconds.
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+                     .unwrap_or_default()
+                     .as_millis() as u64
+}
+
+/// Generates time-ordered UUIDs version 7 (RFC 9562).
+///
+/// The first 48 bits hold the Unix timestamp in milliseconds, followed by a
+/// 12-bit counter and 62 random bits. The counter starts at a random value
+/// within each millisecond and is incremented for every ID, so IDs generated
+/// by one generator are strictly ascending even within the same millisecond.
+#[derive(Debug, Default, Clone)]
+pub struct V7 {
+    last: u64
+}
+
+impl V7 {
+    /// Creates a new generator.
+    pub fn new() -> Self {
+        V7::default()
+    }
+}
+
+impl IdGenerator for V7 {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        let mut value = ID128::fast_random_id()?.into_raw_value();
+        let millis = unix_millis();
+        let counter = u64::from(value[6] & 0x07) << 8 | u64::from(value[7]);
+        let mut stamp = (millis & 0xFFFF_FFFF_FFFF) << 12 | counter;
+        if stamp <= self.last {
+            stamp = self.last + 1;
+        }
+        self.last = stamp;
+        value[..8].copy_from_slice(&((stamp >> 12) << 16 | stamp & 0x0FFF).to_be_bytes());
+        set_version(&mut value, 7);
+        Ok(ID128::from_raw_value(value))
+    }
+}
+
+/// Generates a deterministic sequence of IDs.
+///
+/// The n-th generated ID equals `ID128::from_seed(seed + n)`. IDs of this
+/// generator are predictable and must only be used in tests and fixtures.
+#[derive(Debug, Default, Clone)]
+pub struct Seeded {
+    next: u64
+}
+
+impl Seeded {
+    /// Creates a new generator starting at `seed`.
+    pub fn new(seed: u64) -> Self {
+        Seeded { next: seed }
+    }
+}
+
+impl IdGenerator for Seeded {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        let id128 = ID128::from_seed(self.next);
+        self.next = self.next.wrapping_add(1);
+        Ok(id128)
+    }
+}
+
+/// Generates cluster-unique, roughly sortable IDs without coordination.
+///
+/// Similar to Twitter's snowflake IDs, every ID is composed of a timestamp, a
+/// sequence counter and bits identifying the machine and the worker. The IDs
+/// are UUID v8-compatible with the following layout:
+/// - 48 bits: Unix timestamp in milliseconds
+/// - 4 bits: version 8
+/// - 12 bits: sequence counter within the millisecond
+/// - 2 bits: RFC variant
+/// - 46 bits: machine bits taken from the machine ID
+/// - 16 bits: worker, by default the lower bits of the process ID
+///
+/// IDs sort by their creation time. Should more than 4096 IDs be generated
+/// within one millisecond, the generator borrows from the next millisecond.
+///
+/// Generating IDs based on the plain machine ID discloses parts of it. Use
+/// `app_specific` when IDs are passed to untrusted environments.
+#[derive(Debug, Clone)]
+pub struct Snowflake {
+    node: [u8; 8],
+    last: u64
+}
+
+impl Snowflake {
+    /// Creates a new generator for the machine ID of the executing host.
+    ///
+    /// # Return Values
+    /// - Ok(Snowflake): initialized generator
+    /// - Err(Error::SDError(i32)): sd-id128 returned an error code
+    pub fn new() -> Result<Self, Error> {
+        Ok(Snowflake::with_machine(&ID128::machine_id()?))
+    }
+
+    /// Creates a new generator for the app specific machine ID of the
+    /// executing host.
+    ///
+    /// # Return Values
+    /// - Ok(Snowflake): initialized generator
+    /// - Err(Error::SDError(i32)): sd-id128 returned an error code
+    #[cfg(any(systemd_v233,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+    pub fn app_specific(app: ID128) -> Result<Self, Error> {
+        Ok(Snowflake::with_machine(&ID128::machine_id_app_specific(app)?))
+    }
+
+    /// Creates a new generator for an arbitrary machine ID.
+    pub fn with_machine(machine: &ID128) -> Self {
+        let mut node = [0u8; 8];
+        node[..6].copy_from_slice(&machine.as_raw_value()[..6]);
+        node[6..].copy_from_slice(&(process::id() as u16).to_be_bytes());
+        Snowflake { node,
+                    last: 0 }
+    }
+
+    /// Sets the worker bits, e.g. to distinguish multiple generators on the
+    /// same machine.
+    pub fn worker(mut self, worker: u16) -> Self {
+        self.node[6..].copy_from_slice(&worker.to_be_bytes());
+        self
+    }
+}
+
+impl IdGenerator for Snowflake {
+    fn generate(&mut self) -> Result<ID128, Error> {
+        let mut stamp = (unix_millis() & 0xFFFF_FFFF_FFFF) << 12;
+        if stamp <= self.last {
+            stamp = self.last + 1;
+        }
+        self.last = stamp;
+        let mut value = [0u8; 16];
+        value[..8].copy_from_slice(&((stamp >> 12) << 16 | stamp & 0x0FFF).to_be_bytes());
+        value[8..].copy_from_slice(&self.node);
+        set_version(&mut value, 8);
+        Ok(ID128::from_raw_value(value))
+    }
+}
@@ -0,0 +1,343 @@
+// sd-id128: well-known GPT partition type IDs
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//! GPT partition type IDs of the
+//! [Discoverable Partitions Specification](https://uapi-group.org/specifications/specs/discoverable_partitions_specification/).
+//!
+//! systemd uses these IDs to find the partitions of a disk image, e.g. in
+//! systemd-gpt-auto-generator and systemd-dissect. The names of
+//! `PARTITION_TYPES` are the ones shown by `systemd-id128 show`.
+//!
+//! `lookup` resolves an ID to its partition type, `root_for` and `usr_for`
+//! return the IDs for an architecture, e.g. when building disk images for
+//! another target. With the feature `codegen`, `lookup` and `usr_for` fall back
+//! to the root and `/usr` partition types of the installed systemd.
+//!
+//! ```rust
+//! use sd_id128::gpt::{self, Architecture, Designator};
+//! assert_eq!(gpt::ESP.to_string(), "c12a7328-f81f-11d2-ba4b-00a0c93ec93b");
+//! let info = gpt::lookup(&gpt::ROOT_ARM64).unwrap();
+//! assert_eq!(info.designator, Designator::Root);
+//! assert_eq!(info.architecture, Some(Architecture::Arm64));
+//! assert_eq!(gpt::root_for(Architecture::Arm64), gpt::ROOT_ARM64);
+//! ```
+use crate::ID128;
+
+/// EFI System Partition.
+pub const ESP: ID128 = ID128::from_u128(0xc12a7328_f81f_11d2_ba4b_00a0c93ec93b);
+/// Extended Boot Loader Partition.
+pub const XBOOTLDR: ID128 = ID128::from_u128(0xbc13c2ff_59e6_4262_a352_b275fd6f7172);
+/// Swap partition.
+pub const SWAP: ID128 = ID128::from_u128(0x0657fd6d_a4ab_43c4_84e5_0933c84b4f4f);
+/// Home partition, mounted to `/home`.
+pub const HOME: ID128 = ID128::from_u128(0x933ac7e1_2eb4_4f13_b844_0e14e2aef915);
+/// Server data partition, mounted to `/srv`.
+pub const SRV: ID128 = ID128::from_u128(0x3b8f8425_20e0_4f3b_907f_1a25a76f98e8);
+/// Variable data partition, mounted to `/var`.
+pub const VAR: ID128 = ID128::from_u128(0x4d21b016_b534_45c2_a9fb_5c16e091fd2d);
+/// Temporary data partition, mounted to `/var/tmp`.
+pub const TMP: ID128 = ID128::from_u128(0x7ec6f557_3bc5_4aca_b293_16ef5df639d1);
+/// Per-user home partition of systemd-homed.
+pub const USER_HOME: ID128 = ID128::from_u128(0x773f91ef_66d4_49b5_bd83_d683bf40ad16);
+/// Generic Linux data partition.
+pub const LINUX_GENERIC: ID128 = ID128::from_u128(0x0fc63daf_8483_4772_8e79_3d69d8477de4);
+
+/// Root partition of x86.
+pub const ROOT_X86: ID128 = ID128::from_u128(0x44479540_f297_41b2_9af7_d131d5f0458a);
+/// Root partition of x86-64.
+pub const ROOT_X86_64: ID128 = ID128::from_u128(0x4f68bce3_e8cd_4db1_96e7_fbcaf984b709);
+/// Root partition of 32-bit ARM.
+pub const ROOT_ARM: ID128 = ID128::from_u128(0x69dad710_2ce4_4e3c_b16c_21a1d49abed3);
+/// Root partition of 64-bit ARM.
+pub const ROOT_ARM64: ID128 = ID128::from_u128(0xb921b045_1df0_41c3_af44_4c6f280d3fae);
+/// Root partition of Itanium.
+pub const ROOT_IA64: ID128 = ID128::from_u128(0x993d8d3d_f80e_4225_855a_9daf8ed7ea97);
+/// Root partition of 64-bit LoongArch.
+pub const ROOT_LOONGARCH64: ID128 = ID128::from_u128(0x77055800_792c_4f94_b39a_98c91b762bb6);
+/// Root partition of 64-bit PowerPC, little endian.
+pub const ROOT_PPC64_LE: ID128 = ID128::from_u128(0xc31c45e6_3f39_412e_80fb_4809c4980599);
+/// Root partition of 32-bit RISC-V.
+pub const ROOT_RISCV32: ID128 = ID128::from_u128(0x60d5a7fe_8e7d_435c_b714_3dd8162144e1);
+/// Root partition of 64-bit RISC-V.
+pub const ROOT_RISCV64: ID128 = ID128::from_u128(0x72ec70a6_cf74_40e6_bd49_4bda08e8f224);
+/// Root partition of s390x.
+pub const ROOT_S390X: ID128 = ID128::from_u128(0x5eead9a9_fe09_4a1e_a1d7_520d00531306);
+
+/// `/usr` partition of x86.
+pub const USR_X86: ID128 = ID128::from_u128(0x75250d76_8cc6_458e_bd66_bd47cc81a812);
+/// `/usr` partition of x86-64.
+pub const USR_X86_64: ID128 = ID128::from_u128(0x8484680c_9521_48c6_9c11_b0720656f69e);
+/// `/usr` partition of 32-bit ARM.
+pub const USR_ARM: ID128 = ID128::from_u128(0x7d0359a3_02b3_4f0a_865c_654403e70625);
+/// `/usr` partition of 64-bit ARM.
+pub const USR_ARM64: ID128 = ID128::from_u128(0xb0e01050_ee5f_4390_949a_9101b17104e9);
+
+/// All partition types of this module by name, with their designator and
+/// architecture.
+pub const PARTITION_TYPES: &[PartitionTypeInfo] =
+    &[PartitionTypeInfo { name:         "esp",
+                          id:           ESP,
+                          designator:   Designator::Esp,
+                          architecture: None },
+      PartitionTypeInfo { name:         "xbootldr",
+                          id:           XBOOTLDR,
+                          designator:   Designator::Xbootldr,
+                          architecture: None },
+      PartitionTypeInfo { name:         "swap",
+                          id:           SWAP,
+                          designator:   Designator::Swap,
+                          architecture: None },
+      PartitionTypeInfo { name:         "home",
+                          id:           HOME,
+                          designator:   Designator::Home,
+                          architecture: None },
+      PartitionTypeInfo { name:         "srv",
+                          id:           SRV,
+                          designator:   Designator::Srv,
+                          architecture: None },
+      PartitionTypeInfo { name:         "var",
+                          id:           VAR,
+                          designator:   Designator::Var,
+                          architecture: None },
+      PartitionTypeInfo { name:         "tmp",
+                          id:           TMP,
+                          designator:   Designator::Tmp,
+                          architecture: None },
+      PartitionTypeInfo { name:         "user-home",
+                          id:           USER_HOME,
+                          designator:   Designator::UserHome,
+                          architecture: None },
+      PartitionTypeInfo { name:         "linux-generic",
+                          id:           LINUX_GENERIC,
+                          designator:   Designator::LinuxGeneric,
+                          architecture: None },
+      PartitionTypeInfo { name:         "root-x86",
+                          id:           ROOT_X86,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::X86) },
+      PartitionTypeInfo { name:         "root-x86-64",
+                          id:           ROOT_X86_64,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::X86_64) },
+      PartitionTypeInfo { name:         "root-arm",
+                          id:           ROOT_ARM,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::Arm) },
+      PartitionTypeInfo { name:         "root-arm64",
+                          id:           ROOT_ARM64,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::Arm64) },
+      PartitionTypeInfo { name:         "root-ia64",
+                          id:           ROOT_IA64,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::Ia64) },
+      PartitionTypeInfo { name:         "root-loongarch64",
+                          id:           ROOT_LOONGARCH64,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::LoongArch64) },
+      PartitionTypeInfo { name:         "root-ppc64-le",
+                          id:           ROOT_PPC64_LE,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::Ppc64Le) },
+      PartitionTypeInfo { name:         "root-riscv32",
+                          id:           ROOT_RISCV32,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::RiscV32) },
+      PartitionTypeInfo { name:         "root-riscv64",
+                          id:           ROOT_RISCV64,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::RiscV64) },
+      PartitionTypeInfo { name:         "root-s390x",
+                          id:           ROOT_S390X,
+                          designator:   Designator::Root,
+                          architecture: Some(Architecture::S390x) },
+      PartitionTypeInfo { name:         "usr-x86",
+                          id:           USR_X86,
+                          designator:   Designator::Usr,
+                          architecture: Some(Architecture::X86) },
+      PartitionTypeInfo { name:         "usr-x86-64",
+                          id:           USR_X86_64,
+                          designator:   Designator::Usr,
+                          architecture: Some(Architecture::X86_64) },
+      PartitionTypeInfo { name:         "usr-arm",
+                          id:           USR_ARM,
+                          designator:   Designator::Usr,
+                          architecture: Some(Architecture::Arm) },
+      PartitionTypeInfo { name:         "usr-arm64",
+                          id:           USR_ARM64,
+                          designator:   Designator::Usr,
+                          architecture: Some(Architecture::Arm64) }];
+
+#[cfg(feature = "codegen")]
+include!(concat!(env!("OUT_DIR"), "/gpt.rs"));
+
+/// CPU architectures of root and `/usr` partitions.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Architecture {
+    X86,
+    X86_64,
+    Arm,
+    Arm64,
+    Ia64,
+    LoongArch64,
+    Ppc64Le,
+    RiscV32,
+    RiscV64,
+    S390x
+}
+
+impl Architecture {
+    /// Returns the architecture this program was compiled for, None if it has
+    /// no partition types.
+    pub fn native() -> Option<Self> {
+        let targets = [(cfg!(target_arch = "x86"), Architecture::X86),
+                       (cfg!(target_arch = "x86_64"), Architecture::X86_64),
+                       (cfg!(target_arch = "arm"), Architecture::Arm),
+                       (cfg!(target_arch = "aarch64"), Architecture::Arm64),
+                       (cfg!(target_arch = "loongarch64"), Architecture::LoongArch64),
+                       (cfg!(all(target_arch = "powerpc64", target_endian = "little")),
+                        Architecture::Ppc64Le),
+                       (cfg!(target_arch = "riscv32"), Architecture::RiscV32),
+                       (cfg!(target_arch = "riscv64"), Architecture::RiscV64),
+                       (cfg!(target_arch = "s390x"), Architecture::S390x)];
+        targets.iter()
+               .find(|(native, _)| *native)
+               .map(|(_, architecture)| *architecture)
+    }
+}
+
+/// Purpose of a partition, i.e. where systemd mounts or uses it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Designator {
+    Esp,
+    Xbootldr,
+    Swap,
+    Home,
+    Srv,
+    Var,
+    Tmp,
+    UserHome,
+    LinuxGeneric,
+    Root,
+    Usr
+}
+
+/// Description of a partition type, see `lookup`.
+///
+/// Fields:
+/// - name: name of the type, e.g. "root-x86-64"
+/// - id: the partition type ID
+/// - designator: purpose of the partition
+/// - architecture: architecture of root and `/usr` partitions, None for all
+///   other partitions
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PartitionTypeInfo {
+    pub name:         &'static str,
+    pub id:           ID128,
+    pub designator:   Designator,
+    pub architecture: Option<Architecture>
+}
+
+/// Names of the architectures within the names of partition types.
+#[cfg(feature = "codegen")]
+const ARCHITECTURES: &[(&str, Architecture)] = &[("x86", Architecture::X86),
+                                                 ("x86-64", Architecture::X86_64),
+                                                 ("arm", Architecture::Arm),
+                                                 ("arm64", Architecture::Arm64),
+                                                 ("ia64", Architecture::Ia64),
+                                                 ("loongarch64", Architecture::LoongArch64),
+                                                 ("ppc64-le", Architecture::Ppc64Le),
+                                                 ("riscv32", Architecture::RiscV32),
+                                                 ("riscv64", Architecture::RiscV64),
+                                                 ("s390x", Architecture::S390x)];
+
+/// Resolves a partition type ID to its description, None if the ID is no
+/// partition type of this module. With the feature `codegen`, root and `/usr`
+/// partition types of `SYSTEMD_PARTITION_TYPES` are resolved as well.
+pub fn lookup(id: &ID128) -> Option<PartitionTypeInfo> {
+    PARTITION_TYPES.iter()
+                   .find(|info| info.id == *id)
+                   .cloned()
+                   .or_else(|| lookup_generated(id))
+}
+
+/// Resolves a root or `/usr` partition type generated from the installed
+/// headers, deriving designator and architecture from its name.
+#[cfg(feature = "codegen")]
+fn lookup_generated(id: &ID128) -> Option<PartitionTypeInfo> {
+    let (name, id) = SYSTEMD_PARTITION_TYPES.iter()
+                                            .find(|(_, known)| known == id)?;
+    let (designator, architecture) = match name.split_once('-')? {
+        ("root", architecture) => (Designator::Root, architecture),
+        ("usr", architecture) => (Designator::Usr, architecture),
+        _ => return None
+    };
+    let (_, architecture) = ARCHITECTURES.iter()
+                                         .find(|(known, _)| *known == architecture)?;
+    Some(PartitionTypeInfo { name,
+                             id: id.clone(),
+                             designator,
+                             architecture: Some(*architecture) })
+}
+
+#[cfg(not(feature = "codegen"))]
+fn lookup_generated(_id: &ID128) -> Option<PartitionTypeInfo> {
+    None
+}
+
+/// Returns the generated partition type `<prefix>-<architecture>`.
+#[cfg(feature = "codegen")]
+fn generated_for(prefix: &str, architecture: Architecture) -> Option<ID128> {
+    let (name, _) = ARCHITECTURES.iter()
+                                 .find(|(_, known)| *known == architecture)?;
+    let name = format!("{}-{}", prefix, name);
+    SYSTEMD_PARTITION_TYPES.iter()
+                           .find(|(known, _)| *known == name)
+                           .map(|(_, id)| id.clone())
+}
+
+#[cfg(not(feature = "codegen"))]
+fn generated_for(_prefix: &str, _architecture: Architecture) -> Option<ID128> {
+    None
+}
+
+/// Returns the root partition type of an architecture.
+pub fn root_for(architecture: Architecture) -> ID128 {
+    match architecture {
+        Architecture::X86 => ROOT_X86,
+        Architecture::X86_64 => ROOT_X86_64,
+        Architecture::Arm => ROOT_ARM,
+        Architecture::Arm64 => ROOT_ARM64,
+        Architecture::Ia64 => ROOT_IA64,
+        Architecture::LoongArch64 => ROOT_LOONGARCH64,
+        Architecture::Ppc64Le => ROOT_PPC64_LE,
+        Architecture::RiscV32 => ROOT_RISCV32,
+        Architecture::RiscV64 => ROOT_RISCV64,
+        Architecture::S390x => ROOT_S390X
+    }
+}
+
+/// Returns the `/usr` partition type of an architecture, None if this module
+/// has none for it. With the feature `codegen`, missing types are taken from
+/// `SYSTEMD_PARTITION_TYPES`.
+pub fn usr_for(architecture: Architecture) -> Option<ID128> {
+    match architecture {
+        Architecture::X86 => Some(USR_X86),
+        Architecture::X86_64 => Some(USR_X86_64),
+        Architecture::Arm => Some(USR_ARM),
+        Architecture::Arm64 => Some(USR_ARM64),
+        _ => generated_for("usr", architecture)
+    }
+}
@@ -0,0 +1,343 @@
+// sd-id128: native HMAC-SHA256 for ID derivation
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{uuid::set_version, Error, ID128};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const K: [u32; 64] = [0x428A_2F98,
+                      0x7137_4491,
+                      0xB5C0_FBCF,
+                      0xE9B5_DBA5,
+                      0x3956_C25B,
+                      0x59F1_11F1,
+                      0x923F_82A4,
+                      0xAB1C_5ED5,
+                      0xD807_AA98,
+                      0x1283_5B01,
+                      0x2431_85BE,
+                      0x550C_7DC3,
+                      0x72BE_5D74,
+                      0x80DE_B1FE,
+                      0x9BDC_06A7,
+                      0xC19B_F174,
+                      0xE49B_69C1,
+                      0xEFBE_4786,
+                      0x0FC1_9DC6,
+                      0x240C_A1CC,
+                      0x2DE9_2C6F,
+                      0x4A74_84AA,
+                      0x5CB0_A9DC,
+                      0x76F9_88DA,
+                      0x983E_5152,
+                      0xA831_C66D,
+                      0xB003_27C8,
+                      0xBF59_7FC7,
+                      0xC6E0_0BF3,
+                      0xD5A7_9147,
+                      0x06CA_6351,
+                      0x1429_2967,
+                      0x27B7_0A85,
+                      0x2E1B_2138,
+                      0x4D2C_6DFC,
+                      0x5338_0D13,
+                      0x650A_7354,
+                      0x766A_0ABB,
+                      0x81C2_C92E,
+                      0x9272_2C85,
+                      0xA2BF_E8A1,
+                      0xA81A_664B,
+                      0xC24B_8B70,
+                      0xC76C_51A3,
+                      0xD192_E819,
+                      0xD699_0624,
+                      0xF40E_3585,
+                      0x106A_A070,
+                      0x19A4_C116,
+                      0x1E37_6C08,
+                      0x2748_774C,
+                      0x34B0_BCB5,
+                      0x391C_0CB3,
+                      0x4ED8_AA4A,
+                      0x5B9C_CA4F,
+                      0x682E_6FF3,
+                      0x748F_82EE,
+                      0x78A5_636F,
+                      0x84C8_7814,
+                      0x8CC7_0208,
+                      0x90BE_FFFA,
+                      0xA450_6CEB,
+                      0xBEF9_A3F7,
+                      0xC671_78F2];
+
+/// Incremental SHA-256 (FIPS 180-4).
+#[derive(Clone)]
+pub(crate) struct Sha256 {
+    state:  [u32; 8],
+    block:  [u8; 64],
+    filled: usize,
+    length: u64
+}
+
+impl Sha256 {
+    pub(crate) fn new() -> Self {
+        Sha256 { state:  [0x6A09_E667,
+                          0xBB67_AE85,
+                          0x3C6E_F372,
+                          0xA54F_F53A,
+                          0x510E_527F,
+                          0x9B05_688C,
+                          0x1F83_D9AB,
+                          0x5BE0_CD19],
+                 block:  [0u8; 64],
+                 filled: 0,
+                 length: 0 }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (index, bytes) in self.block.chunks_exact(4).enumerate() {
+            w[index] = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for index in 16..64 {
+            let s0 = w[index - 15].rotate_right(7)
+                     ^ w[index - 15].rotate_right(18)
+                     ^ (w[index - 15] >> 3);
+            let s1 = w[index - 2].rotate_right(17)
+                     ^ w[index - 2].rotate_right(19)
+                     ^ (w[index - 2] >> 10);
+            w[index] = w[index - 16].wrapping_add(s0)
+                                    .wrapping_add(w[index - 7])
+                                    .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for index in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1)
+                      .wrapping_add(ch)
+                      .wrapping_add(K[index])
+                      .wrapping_add(w[index]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+            *state = state.wrapping_add(*value);
+        }
+    }
+
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        while !data.is_empty() {
+            let take = (64 - self.filled).min(data.len());
+            self.block[self.filled..self.filled + take].copy_from_slice(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+            if self.filled == 64 {
+                self.compress();
+                self.filled = 0;
+            }
+        }
+    }
+
+    pub(crate) fn finalize(mut self) -> [u8; 32] {
+        let bits = self.length * 8;
+        self.update(&[0x80]);
+        while self.filled != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_be_bytes());
+        let mut digest = [0u8; 32];
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(self.state.iter()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+}
+
+/// Computes HMAC-SHA256 (RFC 2104) over the concatenation of `message`.
+pub(crate) fn hmac_sha256(key: &[u8], message: &[&[u8]]) -> [u8; 32] {
+    let mut padded = [0u8; 64];
+    if key.len() > 64 {
+        let mut hash = Sha256::new();
+        hash.update(key);
+        padded[..32].copy_from_slice(&hash.finalize());
+    } else {
+        padded[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(&padded.iter().map(|byte| byte ^ 0x36).collect::<Vec<u8>>());
+    for part in message {
+        inner.update(part);
+    }
+    let mut outer = Sha256::new();
+    outer.update(&padded.iter().map(|byte| byte ^ 0x5C).collect::<Vec<u8>>());
+    outer.update(&inner.finalize());
+    outer.finalize()
+}
+
+/// Name space ID for DNS names as defined in RFC 4122 Appendix C.
+const NAMESPACE_DNS: [u8; 16] = [0x6B, 0xA7, 0xB8, 0x10, 0x9D, 0xAD, 0x11, 0xD1, 0x80, 0xB4, 0x00,
+                                 0xC0, 0x4F, 0xD4, 0x30, 0xC8];
+
+impl ID128 {
+    /// Derives an application ID from a name, e.g. a reverse-DNS name like
+    /// "com.example.service".
+    ///
+    /// Instead of minting and circulating a random application ID, it may be
+    /// derived from the name of the application. The algorithm is the
+    /// name-based UUID v8 of RFC 9562 (Appendix B.2): SHA-256 over the DNS name
+    /// space ID (6ba7b810-9dad-11d1-80b4-00c04fd430c8) followed by the UTF-8
+    /// bytes of `name`, the first 16 bytes of the digest with the UUID v8
+    /// version and variant bits applied. The algorithm is part of the API and
+    /// will not change.
+    ///
+    /// Application IDs derived from names are public knowledge: everybody
+    /// knowing the name can compute the ID. This is fine for the purpose of
+    /// `machine_id_app_specific` which keeps the machine ID secret.
+    ///
+    /// This function is only available with the feature `crypto`.
+    pub fn app_id_from_name(name: &str) -> Self {
+        let mut hash = Sha256::new();
+        hash.update(&NAMESPACE_DNS);
+        hash.update(name.as_bytes());
+        let mut value = [0u8; 16];
+        value.copy_from_slice(&hash.finalize()[..16]);
+        set_version(&mut value, 8);
+        ID128::from_raw_value(value)
+    }
+
+    /// Derives an app specific id from an arbitrary base id using native Rust
+    /// functionality (i.e. without any FFI call).
+    ///
+    /// This implements the same algorithm as libsystemd: HMAC-SHA256 keyed
+    /// with `base` over `app`, the first 16 bytes of the digest with the UUID
+    /// v4 version and variant bits applied. The result is bit for bit
+    /// identical to `app_specific`, `machine_id_app_specific` and
+    /// `boot_id_app_specific` and may be used to compute or verify those IDs on
+    /// hosts without a new enough libsystemd.
+    ///
+    /// This function is only available with the feature `crypto`.
+    pub fn app_specific_native(base: ID128, app: ID128) -> Self {
+        base.derive(app)
+    }
+
+    /// Derives an app specific id from this id, allowing hierarchical
+    /// derivations.
+    ///
+    /// `id.derive(app)` is the same as `ID128::app_specific_native(id, app)`.
+    /// Since the result is an ID again, derivations may be chained. Large
+    /// applications may hand out stable identifiers per subsystem, all derived
+    /// from one registered application ID:
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "crypto")]
+    /// # {
+    /// use sd_id128::ID128;
+    /// let app = ID128::from_str("4e7290a0a9a14f3fb7bbf5a05b46f1ad").unwrap();
+    /// let storage = ID128::from_str("2d4b1c8e0e3f4a6c9a1b5e7d3c2f1a0b").unwrap();
+    /// let machine = ID128::machine_id().unwrap();
+    /// let storage_id = machine.derive(&app).derive(&storage);
+    /// # }
+    /// ```
+    ///
+    /// Every level of the hierarchy is a one-way function of its parent, so a
+    /// subsystem ID discloses neither the application ID nor the machine ID.
+    ///
+    /// This function is only available with the feature `crypto`.
+    pub fn derive(&self, component: impl AsRef<[u8; 16]>) -> Self {
+        let digest = hmac_sha256(self.as_raw_value(), &[component.as_ref()]);
+        let mut value = [0u8; 16];
+        value.copy_from_slice(&digest[..16]);
+        set_version(&mut value, 4);
+        ID128::from_raw_value(value)
+    }
+
+    /// Returns an app specific machine id computed with native Rust
+    /// functionality.
+    ///
+    /// The machine ID is retrieved from libsystemd, the derivation is
+    /// performed by `app_specific_native`. The result is identical to
+    /// `machine_id_app_specific`.
+    ///
+    /// This function is only available with the feature `crypto`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): sd-id128 returned an error code
+    pub fn machine_id_app_specific_native(app: ID128) -> Result<Self, Error> {
+        Ok(ID128::app_specific_native(ID128::machine_id()?, app))
+    }
+
+    /// Returns an app specific boot id computed with native Rust
+    /// functionality.
+    ///
+    /// The boot ID is retrieved from libsystemd, the derivation is performed
+    /// by `app_specific_native`. The result is identical to
+    /// `boot_id_app_specific`.
+    ///
+    /// This function is only available with the feature `crypto`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): sd-id128 returned an error code
+    pub fn boot_id_app_specific_native(app: ID128) -> Result<Self, Error> {
+        Ok(ID128::app_specific_native(ID128::boot_id()?, app))
+    }
+
+    /// Derives an identifier which is stable within a time window but cannot
+    /// be linked across windows.
+    ///
+    /// Telemetry often needs to correlate reports of the same host over a short
+    /// period of time without exposing a permanent identity like the machine
+    /// ID. This function splits the time since the Unix epoch into windows of
+    /// length `period` and derives an ID from `base` (e.g. the machine ID),
+    /// the application ID `app` and the index of the current window:
+    /// HMAC-SHA256 keyed with `base` over `app` followed by the window index as
+    /// 64-bit big-endian number. The first 16 bytes of the digest form the ID,
+    /// with the UUID v4 version and variant bits applied like the app specific
+    /// IDs of libsystemd.
+    ///
+    /// Periods are counted in whole seconds, shorter periods are treated as one
+    /// second. The result is only as secret as `base`.
+    ///
+    /// This function is only available with the feature `crypto`.
+    pub fn rotating_id(base: &ID128, app: &ID128, period: Duration) -> Self {
+        ID128::rotating_id_at(base, app, period, SystemTime::now())
+    }
+
+    /// Derives the rotating identifier of `rotating_id` for the window
+    /// containing the point in time `at`.
+    ///
+    /// This function is only available with the feature `crypto`.
+    pub fn rotating_id_at(base: &ID128, app: &ID128, period: Duration, at: SystemTime) -> Self {
+        let seconds = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let window = seconds / period.as_secs().max(1);
+        let digest = hmac_sha256(base.as_raw_value(), &[app.as_raw_value(),
+                                                        &window.to_be_bytes()]);
+        let mut value = [0u8; 16];
+        value.copy_from_slice(&digest[..16]);
+        set_version(&mut value, 4);
+        ID128::from_raw_value(value)
+    }
+}
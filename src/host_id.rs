@@ -0,0 +1,134 @@
+// sd-id128: stable host identity across operating systems
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A stable 128-bit host identity on every platform.
+//!
+//! Tools managing fleets of mixed hosts need one identity per host, no matter
+//! which operating system it runs. The backend is selected at compile time by
+//! the target operating system:
+//! - Linux: the machine ID of systemd, `ID128::machine_id`
+//! - Windows: the MachineGuid of the registry, `ID128::windows_machine_guid`
+//! - macOS: the IOPlatformUUID of IOKit, `ID128::macos_platform_uuid`
+//! - FreeBSD and OpenBSD: the host UUID, `ID128::bsd_host_uuid`
+//!
+//! All backends but Linux require the feature `portable`. On any other
+//! platform, `host_id` fails with ENOSYS.
+//!
+//! The host identities of the other operating systems are neither secret nor
+//! guaranteed to be random. Like the machine ID, they should not be exposed
+//! to untrusted parties; use `app_specific` instead.
+//!
+//! ```rust
+//! use sd_id128::host_id;
+//! let host = host_id::host_id().unwrap();
+//! ```
+use crate::{Error, ID128};
+
+/// Backend providing the host identity.
+///
+/// Variants:
+/// - MachineId: the machine ID of systemd (Linux)
+/// - MachineGuid: the MachineGuid of the registry (Windows)
+/// - PlatformUuid: the IOPlatformUUID of IOKit (macOS)
+/// - HostUuid: `kern.hostuuid` or `hw.uuid` (FreeBSD, OpenBSD)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostIdBackend {
+    MachineId,
+    MachineGuid,
+    PlatformUuid,
+    HostUuid
+}
+
+#[cfg(target_os = "linux")]
+const BACKEND: Option<HostIdBackend> = Some(HostIdBackend::MachineId);
+#[cfg(all(feature = "portable", windows))]
+const BACKEND: Option<HostIdBackend> = Some(HostIdBackend::MachineGuid);
+#[cfg(all(feature = "portable", target_os = "macos"))]
+const BACKEND: Option<HostIdBackend> = Some(HostIdBackend::PlatformUuid);
+#[cfg(all(feature = "portable",
+          any(target_os = "freebsd", target_os = "openbsd")))]
+const BACKEND: Option<HostIdBackend> = Some(HostIdBackend::HostUuid);
+#[cfg(not(any(target_os = "linux",
+              all(feature = "portable",
+                  any(windows,
+                      target_os = "macos",
+                      target_os = "freebsd",
+                      target_os = "openbsd")))))]
+const BACKEND: Option<HostIdBackend> = None;
+
+/// Returns the backend selected for the target, if any.
+pub fn backend() -> Option<HostIdBackend> {
+    BACKEND
+}
+
+#[cfg(target_os = "linux")]
+fn query() -> Result<ID128, Error> {
+    ID128::machine_id()
+}
+
+#[cfg(all(feature = "portable", windows))]
+fn query() -> Result<ID128, Error> {
+    ID128::windows_machine_guid()
+}
+
+#[cfg(all(feature = "portable", target_os = "macos"))]
+fn query() -> Result<ID128, Error> {
+    ID128::macos_platform_uuid()
+}
+
+#[cfg(all(feature = "portable",
+          any(target_os = "freebsd", target_os = "openbsd")))]
+fn query() -> Result<ID128, Error> {
+    ID128::bsd_host_uuid()
+}
+
+#[cfg(not(any(target_os = "linux",
+              all(feature = "portable",
+                  any(windows,
+                      target_os = "macos",
+                      target_os = "freebsd",
+                      target_os = "openbsd")))))]
+fn query() -> Result<ID128, Error> {
+    Err(Error::SDError(-libc::ENOSYS))
+}
+
+/// Returns the identity of the host from the backend of the target.
+///
+/// # Return Values
+/// - Ok(ID128): initialized ID128 struct
+/// - Err(Error::SDError(i32)): the identity could not be retrieved, the code is
+///   the negative errno; ENOSYS indicates that the platform has no backend
+/// - Err(Error::ParseStringError): the identity is no valid ID
+pub fn host_id() -> Result<ID128, Error> {
+    query()
+}
+
+/// Returns an app specific host identity.
+///
+/// The host identity is derived with `ID128::app_specific_native`, i.e. the
+/// algorithm of libsystemd. On Linux, the result is identical to
+/// `ID128::machine_id_app_specific`, so services report the same ID whether
+/// they use this module or libsystemd.
+///
+/// This function is only available with the feature `crypto`.
+///
+/// # Return Values
+/// - Ok(ID128): initialized ID128 struct
+/// - Err(Error): see `host_id`
+#[cfg(feature = "crypto")]
+pub fn app_specific(app: ID128) -> Result<ID128, Error> {
+    Ok(ID128::app_specific_native(host_id()?, app))
+}
@@ -0,0 +1,119 @@
+// sd-id128: files in the format of /etc/machine-id
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Reading and writing files in the format of `/etc/machine-id`.
+//!
+//! As specified in
+//! [machine-id(5)](https://www.freedesktop.org/software/systemd/man/machine-id.html),
+//! such a file contains a single ID as 32 lower case hexadecimal characters
+//! followed by a newline. Beside that, systemd knows two special states:
+//! - an empty file: no ID is assigned yet, systemd generates a transient one on
+//!   boot, e.g. in images shared by many machines
+//! - the marker "uninitialized": the ID is generated on boot and committed once
+//!   the first boot completed successfully
+//!
+//! Files are written atomically with the permissions systemd applies to
+//! `/etc/machine-id`, i.e. read-only for everybody (0444).
+//!
+//! ```rust
+//! use sd_id128::{id_file::{self, Content},
+//!                ID128};
+//! let path = std::env::temp_dir().join(format!("sd-id128-doc-{}", std::process::id()));
+//! id_file::write(&path, &Content::Uninitialized).unwrap();
+//! assert_eq!(id_file::read(&path).unwrap(), Content::Uninitialized);
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+use crate::{errno, from_io, native::parse_plain_or_rfc, persistent::replace_atomic, Case, Error,
+            Format, ID128};
+use std::{fs, path::Path, str};
+
+/// Permissions of a file written by `write`.
+pub const MODE: u32 = 0o444;
+
+/// State of a file in the format of `/etc/machine-id`.
+///
+/// Variants:
+/// - Id: the file contains an ID
+/// - Uninitialized: the file contains the marker "uninitialized"
+/// - Empty: the file is empty; a file containing the null ID is treated alike
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Content {
+    Id(ID128),
+    Uninitialized,
+    Empty
+}
+
+/// Parses the content of a file in the format of `/etc/machine-id`.
+///
+/// A single trailing newline is accepted but not required. Like systemd,
+/// the ID is also accepted in RFC format.
+///
+/// # Return Values
+/// - Ok(Content): the state of the file
+/// - Err(Error::SDError(-EUCLEAN)): the file contains anything else
+pub fn parse(content: &[u8]) -> Result<Content, Error> {
+    let content = content.strip_suffix(b"\n").unwrap_or(content);
+    if content.is_empty() {
+        return Ok(Content::Empty);
+    }
+    if content == b"uninitialized" {
+        return Ok(Content::Uninitialized);
+    }
+    let value = str::from_utf8(content).ok()
+                                       .and_then(parse_plain_or_rfc)
+                                       .ok_or(Error::SDError(-errno::EUCLEAN))?;
+    if value == [0u8; 16] {
+        return Ok(Content::Empty);
+    }
+    Ok(Content::Id(ID128::from_raw_value(value)))
+}
+
+/// Reads a file in the format of `/etc/machine-id`, see `parse`.
+///
+/// # Return Values
+/// - Ok(Content): the state of the file
+/// - Err(Error::SDError(i32)): the file could not be read or contains anything
+///   else (EUCLEAN), the code is the negative errno
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Content, Error> {
+    let content = fs::read(path).map_err(from_io)?;
+    parse(&content)
+}
+
+/// Returns the content of a file in the format of `/etc/machine-id`.
+pub fn format(content: &Content) -> String {
+    match content {
+        Content::Id(id128) => {
+            format!("{}\n",
+                    id128.to_string_formatted(Format::LibSystemD, Case::Lower))
+        },
+        Content::Uninitialized => String::from("uninitialized\n"),
+        Content::Empty => String::new()
+    }
+}
+
+/// Writes a file in the format of `/etc/machine-id`.
+///
+/// The content is written to a temporary file in the same directory which
+/// replaces `path` only when complete, so readers never see a partial file.
+/// The file is created with permissions `MODE`.
+///
+/// # Return Values
+/// - Ok(()): the file was written
+/// - Err(Error::SDError(i32)): the file could not be written, the code is the
+///   negative errno
+pub fn write<P: AsRef<Path>>(path: P, content: &Content) -> Result<(), Error> {
+    replace_atomic(path.as_ref(), format(content).as_bytes(), MODE).map_err(from_io)
+}
@@ -0,0 +1,49 @@
+// sd-id128: match strings of the journal
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::ID128;
+
+impl ID128 {
+    /// Returns the match `FIELD=ID` of the journal with the ID in libsystemd
+    /// format, ready to pass to `sd_journal_add_match()` or `journalctl`.
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// let id128 = ID128::from_str("01234567-89ab-cdef-0123-456789abcdef").unwrap();
+    /// assert_eq!(id128.journal_match("_BOOT_ID"),
+    ///            "_BOOT_ID=0123456789abcdef0123456789abcdef");
+    /// ```
+    pub fn journal_match(&self, field: &str) -> String {
+        format!("{}={:x}", field, self)
+    }
+
+    /// Returns the match of the journal field `_BOOT_ID`, selecting the
+    /// entries logged during the boot with this ID.
+    pub fn boot_match(&self) -> String {
+        self.journal_match("_BOOT_ID")
+    }
+
+    /// Returns the match of the journal field `MESSAGE_ID`, selecting the
+    /// entries of a well-known event like those of the module `messages`.
+    pub fn message_id_match(&self) -> String {
+        self.journal_match("MESSAGE_ID")
+    }
+
+    /// Returns the match of the journal field `_SYSTEMD_INVOCATION_ID`,
+    /// selecting the entries logged by the invocation of a unit with this ID.
+    pub fn invocation_match(&self) -> String {
+        self.journal_match("_SYSTEMD_INVOCATION_ID")
+    }
+}
@@ -0,0 +1,360 @@
+// sd-id128: IDs in the header of journal files
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//! Reading the IDs stored in the header of journal files.
+//!
+//! As specified in the
+//! [journal file format](https://systemd.io/JOURNAL_FILE_FORMAT/), each
+//! `.journal` file starts with a header carrying the IDs of the file, of the
+//! machine and boot it was written on and of its sequence number space. Tools
+//! archiving logs can index journal files by these IDs without linking the
+//! journal API, only the header is read.
+//!
+//! ```rust,no_run
+//! use sd_id128::journal_file;
+//! let header = journal_file::read("/var/log/journal/system.journal").unwrap();
+//! println!("file {} of machine {}", header.file_id, header.machine_id);
+//! ```
+//!
+//! With the feature `journal`, `list_boots` scans the entries of the journal
+//! files for the boots they were logged in, like `journalctl --list-boots`.
+use crate::{from_io, Error, ID128};
+#[cfg(feature = "journal")]
+use std::{collections::HashMap,
+          convert::TryFrom,
+          fs,
+          io::{BufReader, Seek, SeekFrom},
+          time::{Duration, SystemTime, UNIX_EPOCH}};
+use std::{fs::File,
+          io::{self, Read},
+          path::Path};
+
+/// Signature at the start of every journal file.
+pub const SIGNATURE: [u8; 8] = *b"LPKSHHRH";
+
+/// Number of bytes of the header covering the IDs.
+pub const HEADER_SIZE: usize = 88;
+
+/// IDs in the header of a journal file.
+///
+/// Fields:
+/// - file_id: the ID of the file, generated when it is created
+/// - machine_id: the machine ID of the system writing the file
+/// - boot_id: the boot ID of the last entry written, called
+///   `tail_entry_boot_id` by newer versions of systemd
+/// - seqnum_id: the ID of the sequence number space, shared by the files
+///   rotated from each other
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub file_id:    ID128,
+    pub machine_id: ID128,
+    pub boot_id:    ID128,
+    pub seqnum_id:  ID128
+}
+
+/// Returns the ID at `offset` of the header.
+fn id_at(header: &[u8], offset: usize) -> ID128 {
+    let mut value = [0u8; 16];
+    value.copy_from_slice(&header[offset..offset + 16]);
+    ID128::from_raw_value(value)
+}
+
+/// Parses the header at the start of a journal file.
+///
+/// At least `HEADER_SIZE` bytes are required, further bytes are ignored.
+///
+/// # Return Values
+/// - Ok(Header): the IDs of the file
+/// - Err(Error::SDError(-EBADMSG)): the data is too short or does not start
+///   with `SIGNATURE`
+pub fn parse(data: &[u8]) -> Result<Header, Error> {
+    if data.len() < HEADER_SIZE || data[..8] != SIGNATURE {
+        return Err(Error::SDError(-libc::EBADMSG));
+    }
+    Ok(Header { file_id:    id_at(data, 24),
+                machine_id: id_at(data, 40),
+                boot_id:    id_at(data, 56),
+                seqnum_id:  id_at(data, 72) })
+}
+
+/// Translates an I/O error reading a journal file, a file ending too early is
+/// damaged (EBADMSG).
+fn from_read(error: io::Error) -> Error {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => Error::SDError(-libc::EBADMSG),
+        _ => from_io(error)
+    }
+}
+
+/// Reads the header of a journal file, see `parse`.
+///
+/// # Return Values
+/// - Ok(Header): the IDs of the file
+/// - Err(Error::SDError(i32)): the file could not be read or is no journal file
+///   (EBADMSG), the code is the negative errno
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Header, Error> {
+    let mut header = [0u8; HEADER_SIZE];
+    File::open(path).and_then(|mut file| file.read_exact(&mut header))
+                    .map_err(from_read)?;
+    parse(&header)
+}
+
+/// Directories holding the system journal of the local machine, each followed
+/// by the machine ID in libsystemd format.
+#[cfg(feature = "journal")]
+const JOURNAL_DIRECTORIES: [&str; 2] = ["/var/log/journal", "/run/log/journal"];
+
+/// Flag of the header marking files with 32 bit entry array items.
+#[cfg(feature = "journal")]
+const HEADER_INCOMPATIBLE_COMPACT: u32 = 1 << 4;
+
+/// Number of bytes of the header up to the offset of the first entry array.
+#[cfg(feature = "journal")]
+const ENTRIES_HEADER_SIZE: usize = 184;
+
+/// Number of bytes of the entry array items read at once.
+#[cfg(feature = "journal")]
+const ITEMS_CHUNK_SIZE: usize = 4096;
+
+/// A boot found in the journal.
+///
+/// Fields:
+/// - index: the position relative to the last boot, i.e. 0 for the last boot,
+///   -1 for the one before, as accepted by `journalctl --boot`
+/// - boot_id: the boot ID
+/// - first: the wall clock time of the first entry of the boot
+/// - last: the wall clock time of the last entry of the boot
+#[cfg(feature = "journal")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Boot {
+    pub index:   i32,
+    pub boot_id: ID128,
+    pub first:   SystemTime,
+    pub last:    SystemTime
+}
+
+/// Returns the little endian integer of `N` bytes at `offset`, EBADMSG if the
+/// data is too short.
+#[cfg(feature = "journal")]
+fn le_at<const N: usize>(data: &[u8], offset: usize) -> Result<u64, Error> {
+    let bytes = offset.checked_add(N)
+                      .and_then(|end| data.get(offset..end))
+                      .ok_or(Error::SDError(-libc::EBADMSG))?;
+    Ok(bytes.iter()
+            .rev()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+}
+
+/// Buffered reader of a journal file tracking its position, so the entries
+/// and arrays written close to each other are mostly read from the buffer
+/// instead of by a system call each.
+#[cfg(feature = "journal")]
+struct JournalReader<R> {
+    reader:   BufReader<R>,
+    position: Option<u64>
+}
+
+#[cfg(feature = "journal")]
+impl<R: Read + Seek> JournalReader<R> {
+    fn new(file: R) -> Self {
+        JournalReader { reader:   BufReader::new(file),
+                        position: None }
+    }
+
+    /// Reads `buffer.len()` bytes at `offset`, EBADMSG if the file ends
+    /// before. Seeking relative to the known position keeps the buffer.
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        let distance = self.position
+                           .and_then(|position| i64::try_from(position).ok())
+                           .and_then(|position| Some(i64::try_from(offset).ok()? - position));
+        let sought = match distance {
+            Some(distance) => self.reader.seek_relative(distance),
+            None => self.reader.seek(SeekFrom::Start(offset)).map(|_| ())
+        };
+        let result = sought.and_then(|_| self.reader.read_exact(buffer));
+        self.position = result.as_ref().ok().map(|_| offset + buffer.len() as u64);
+        result.map_err(from_read)
+    }
+}
+
+/// Collects the boot ID and wall clock time of the entry object at `entry`
+/// into `boots`.
+#[cfg(feature = "journal")]
+fn scan_entry<R: Read + Seek>(file: &mut JournalReader<R>,
+                              entry: u64,
+                              boots: &mut HashMap<[u8; 16], (u64, u64)>)
+                              -> Result<(), Error> {
+    let mut object = [0u8; 56];
+    file.read_at(entry, &mut object)?;
+    let realtime = le_at::<8>(&object, 24)?;
+    let mut boot_id = [0u8; 16];
+    boot_id.copy_from_slice(&object[40..56]);
+    let times = boots.entry(boot_id).or_insert((realtime, realtime));
+    times.0 = times.0.min(realtime);
+    times.1 = times.1.max(realtime);
+    Ok(())
+}
+
+/// Collects the boot ID and wall clock time of each entry of a journal file
+/// into `boots`, keeping the first and last time of each boot.
+///
+/// The entries are found by walking the chain of entry arrays, the entry
+/// objects themselves are never compressed. Only the header, the entry arrays
+/// and the entries are read, the data objects making up most of a journal file
+/// are skipped. Arrays are appended to the file, so a chain not moving forward
+/// is damaged.
+#[cfg(feature = "journal")]
+fn scan_entries<R: Read + Seek>(file: &mut JournalReader<R>,
+                                boots: &mut HashMap<[u8; 16], (u64, u64)>)
+                                -> Result<(), Error> {
+    let mut header = [0u8; ENTRIES_HEADER_SIZE];
+    file.read_at(0, &mut header)?;
+    parse(&header)?;
+    let compact = le_at::<4>(&header, 12)? as u32 & HEADER_INCOMPATIBLE_COMPACT != 0;
+    let item_size = if compact { 4 } else { 8 };
+    let mut remaining = le_at::<8>(&header, 152)?;
+    let mut array = le_at::<8>(&header, 176)?;
+    let mut chunk = [0u8; ITEMS_CHUNK_SIZE];
+    while array != 0 && remaining > 0 {
+        let mut object = [0u8; 24];
+        file.read_at(array, &mut object)?;
+        let mut items = le_at::<8>(&object, 8)?.saturating_sub(24) / item_size as u64;
+        let mut position = array.checked_add(24)
+                                .ok_or(Error::SDError(-libc::EBADMSG))?;
+        while items > 0 && remaining > 0 {
+            let count = items.min((ITEMS_CHUNK_SIZE / item_size) as u64) as usize;
+            let bytes = &mut chunk[..count * item_size];
+            file.read_at(position, bytes)?;
+            for item in bytes.chunks_exact(item_size) {
+                let entry = match item_size {
+                    4 => le_at::<4>(item, 0)?,
+                    _ => le_at::<8>(item, 0)?
+                };
+                if entry == 0 || remaining == 0 {
+                    items = count as u64;
+                    break;
+                }
+                scan_entry(file, entry, boots)?;
+                remaining -= 1;
+            }
+            items -= count as u64;
+            position += bytes.len() as u64;
+        }
+        let next = le_at::<8>(&object, 16)?;
+        if next != 0 && next <= array {
+            return Err(Error::SDError(-libc::EBADMSG));
+        }
+        array = next;
+    }
+    Ok(())
+}
+
+/// Returns the time of a timestamp in microseconds since the epoch.
+#[cfg(feature = "journal")]
+fn realtime(microseconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_micros(microseconds)
+}
+
+/// Lists the boots of the journal files in `directory`, ordered by the time
+/// of their first entry.
+///
+/// All files ending in `.journal` are scanned, files which are not journal
+/// files, damaged, not readable by the caller or removed while scanning are
+/// skipped. This function is
+/// only available with the feature `journal`.
+///
+/// # Return Values
+/// - Ok(Vec<Boot>): the boots found, the last one with index 0
+/// - Err(Error::SDError(i32)): the directory or a file could not be read, the
+///   code is the negative errno
+#[cfg(feature = "journal")]
+pub fn list_boots_in<P: AsRef<Path>>(directory: P) -> Result<Vec<Boot>, Error> {
+    let mut boots = HashMap::new();
+    scan_directory(directory.as_ref(), &mut boots)?;
+    Ok(sort_boots(boots))
+}
+
+/// Lists the boots of the system journal of the local machine, i.e. the
+/// journal files in `/var/log/journal/<machine-id>` and
+/// `/run/log/journal/<machine-id>`, see `list_boots_in`.
+///
+/// The index of the boots allows offering a selection like `--boot=-1` with
+/// proper boot IDs. This function is only available with the feature
+/// `journal`.
+///
+/// # Return Values
+/// - Ok(Vec<Boot>): the boots found, the last one with index 0
+/// - Err(Error::SDError(i32)): the machine ID or a journal file could not be
+///   read, the code is the negative errno
+#[cfg(feature = "journal")]
+pub fn list_boots() -> Result<Vec<Boot>, Error> {
+    let machine_id = format!("{:x}", ID128::machine_id()?);
+    let mut boots = HashMap::new();
+    for directory in JOURNAL_DIRECTORIES.iter() {
+        let directory = Path::new(directory).join(&machine_id);
+        if directory.is_dir() {
+            scan_directory(&directory, &mut boots)?;
+        }
+    }
+    Ok(sort_boots(boots))
+}
+
+/// Scans the journal files in `directory`.
+#[cfg(feature = "journal")]
+fn scan_directory(directory: &Path,
+                  boots: &mut HashMap<[u8; 16], (u64, u64)>)
+                  -> Result<(), Error> {
+    for entry in fs::read_dir(directory).map_err(from_io)? {
+        let path = entry.map_err(from_io)?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("journal") {
+            continue;
+        }
+        let mut file_boots = HashMap::new();
+        match File::open(&path).map_err(from_io)
+                               .and_then(|file| {
+                                   scan_entries(&mut JournalReader::new(file), &mut file_boots)
+                               }) {
+            // files may be rotated or vacuumed by journald while scanning
+            Err(Error::SDError(errno))
+                if errno == -libc::EBADMSG || errno == -libc::EACCES || errno == -libc::ENOENT =>
+            {
+                continue
+            },
+            result => result?
+        }
+        for (boot_id, (first, last)) in file_boots {
+            let times = boots.entry(boot_id).or_insert((first, last));
+            times.0 = times.0.min(first);
+            times.1 = times.1.max(last);
+        }
+    }
+    Ok(())
+}
+
+/// Orders the boots by the time of their first entry and numbers them
+/// relative to the last one.
+#[cfg(feature = "journal")]
+fn sort_boots(boots: HashMap<[u8; 16], (u64, u64)>) -> Vec<Boot> {
+    let mut boots = boots.into_iter().collect::<Vec<_>>();
+    boots.sort_by_key(|(_, (first, last))| (*first, *last));
+    let count = boots.len() as i32;
+    boots.into_iter()
+         .enumerate()
+         .map(|(position, (boot_id, (first, last)))| Boot { index:   position as i32 + 1 - count,
+                                                            boot_id: ID128::from_raw_value(boot_id),
+                                                            first:   realtime(first),
+                                                            last:    realtime(last) })
+         .collect()
+}
@@ -27,12 +27,66 @@
 //! can be mastered using features. Each feature is named after the
 //! corresponding systemd version. The following features exist currently:
 //!
+//! - 255: Dec 6, 2023
 //! - 240: Jan 12, 2019
 //! - 233: Mar 12, 2017
 //!
-//! All features are in the default feature set. If required, default-features
-//! must be turned off. Features are stacking: if you select feature 240, you
-//! will get 233 included.
+//! All features except 255 are in the default feature set. If required,
+//! default-features must be turned off. Features are stacking: if you select
+//! feature 240, you will get 233 included.
+//!
+//! Alternatively, the feature detect determines the version of the installed
+//! libsystemd via pkg-config at build time and enables all functions it
+//! provides. Features selected explicitly are enabled in any case.
+//!
+//! Further optional features extend the functionality:
+//!
+//! - rand_core: generate random IDs from any random number generator
+//! - rand: sample random IDs via `rng.gen::<ID128>()`
+//! - getrandom: generate random IDs without libsystemd, `random_id` falls back
+//!   to it if libsystemd fails
+//! - crypto: derive IDs natively using HMAC-SHA256, including the app specific
+//!   IDs of libsystemd
+//! - no-ffi: read machine, boot and invocation ID natively instead of calling
+//!   libsystemd, e.g. for static musl builds or containers without libsystemd
+//! - dynamic: load libsystemd at runtime instead of linking it and fall back to
+//!   the native implementations of no-ffi if it is absent, so one binary runs
+//!   on hosts with and without systemd; the app specific IDs are available if
+//!   libsystemd provides them, otherwise they are derived natively with the
+//!   feature crypto; requires default-features=false
+//! - static: link libsystemd.a statically, e.g. for fully static binaries on
+//!   appliances; the archive is found via pkg-config or in the directory given
+//!   by the environment variable `SYSTEMD_LIB_DIR` at build time
+//! - dbus: query IDs of containers and services from systemd via sd-bus and
+//!   transport IDs in sd-bus messages
+//! - portable: host identities of other operating systems, i.e. the MachineGuid
+//!   of Windows, the IOPlatformUUID of macOS and the host UUID of FreeBSD and
+//!   OpenBSD, selected per platform by the module `host_id`
+//! - serde: (de)serialize `Format` and `Case` by the names parsed via `FromStr`
+//! - arrayvec: format IDs into a stack allocated `ArrayString`
+//! - watch: get notified via inotify when `/etc/machine-id` is written; Linux
+//!   only, the feature is ignored on other operating systems
+//! - simd: format and parse the libsystemd format using SSE2 on x86_64 and NEON
+//!   on aarch64, e.g. for log pipelines; other targets use scalar code
+//! - cli: build the command line tool `sd-id128` mirroring `systemd-id128`,
+//!   e.g. `sd-id128 machine-id` for scripts on minimal systems
+//! - diagnostics: implement `miette::Diagnostic` for parse errors, labeling the
+//!   offending characters of malformed IDs
+//! - failpoints: make functions of the libsystemd API fail with a given errno
+//!   to test the error handling of applications, see module `failpoints`; meant
+//!   for dev-dependencies only
+//! - codegen: generate the tables `messages::SYSTEMD_MESSAGE_IDS` and
+//!   `gpt::SYSTEMD_PARTITION_TYPES` at build time from the installed headers
+//!   `<systemd/sd-messages.h>` and `<systemd/sd-gpt.h>`, found in
+//!   `SYSTEMD_INCLUDE_DIR`, via pkg-config or in /usr/include; `MessageId`,
+//!   `gpt::lookup`, `gpt::usr_for` and `sd-id128 show` merge them in
+//! - journal: list the boots recorded in the journal files, see
+//!   `journal_file::list_boots`
+//!
+//! The version features enable the feature ffi which links libsystemd.
+//! Without ffi, the crate does not depend on libsystemd at all and the FFI
+//! specific functions are not available. The functionality of the libsystemd
+//! in use at runtime is reported by `capabilities()`.
 //!
 //! ### cargo.toml
 //!
@@ -54,7 +108,21 @@
 //!
 //! ```toml
 //! [dependencies]
-//! sd-id128 = {default-features=false, version="1.0"}
+//! sd-id128 = {default-features=false, version="1.0", features=["ffi"]}
+//! ```
+//!
+//! no libsystemd at all:
+//!
+//! ```toml
+//! [dependencies]
+//! sd-id128 = {default-features=false, version="1.0", features=["no-ffi"]}
+//! ```
+//!
+//! libsystemd if available at runtime:
+//!
+//! ```toml
+//! [dependencies]
+//! sd-id128 = {default-features=false, version="1.0", features=["dynamic"]}
 //! ```
 //!
 //! ### Example
@@ -66,11 +134,82 @@
 //! println!("The boot id in libsystemd format is: {}",
 //!          id128.to_string_formatted(Format::LibSystemD, Case::Lower));
 //! ```
+#[cfg(feature = "ffi")]
 use sd_sys::id128 as ffi;
+#[cfg(feature = "ffi")]
+use std::ffi::CString;
 use std::{convert::TryFrom,
           error,
-          ffi::{CString, IntoStringError, NulError},
-          fmt};
+          ffi::{IntoStringError, NulError},
+          fmt, io,
+          str::{self, FromStr}};
+
+pub use cache::refresh;
+pub use capabilities::{capabilities, Capabilities};
+pub use display::{display_format, set_display_format, Formatted};
+pub use errno::SdErrno;
+pub use parse::ParseMany;
+pub use parse_error::{ParseError, ParseProblem};
+pub use stream::{Id128Reader, Id128Writer};
+
+// mirror of the FFI binding if libsystemd is not linked at all
+#[cfg(not(feature = "ffi"))]
+mod ffi {
+    #[allow(non_camel_case_types)]
+    #[repr(C)]
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct sd_id128 {
+        pub value: [u8; 16]
+    }
+}
+
+mod backend;
+#[cfg(target_os = "linux")]
+pub mod boot;
+#[cfg(all(feature = "portable",
+          any(target_os = "freebsd", target_os = "openbsd")))]
+mod bsd;
+#[cfg(feature = "dbus")]
+pub mod bus;
+mod cache;
+mod capabilities;
+mod display;
+#[cfg(all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))]
+mod dynamic;
+#[cfg(all(feature = "dynamic", feature = "ffi", not(feature = "no-ffi")))]
+compile_error!("the feature `dynamic` cannot be combined with `ffi`, which is enabled by the \
+                default features and the systemd version features; use default-features = false");
+pub mod entropy;
+mod errno;
+#[cfg(feature = "failpoints")]
+pub mod failpoints;
+pub mod generator;
+pub mod gpt;
+#[cfg(feature = "crypto")]
+mod hmac;
+pub mod host_id;
+pub mod id_file;
+mod journal;
+pub mod journal_file;
+#[cfg(all(feature = "portable", target_os = "macos"))]
+mod macos;
+mod macros;
+pub mod marker;
+pub mod messages;
+mod native;
+mod parse;
+mod parse_error;
+mod persistent;
+mod random;
+#[cfg(feature = "simd")]
+mod simd;
+pub mod source;
+mod stream;
+mod uuid;
+#[cfg(all(feature = "watch", target_os = "linux"))]
+pub mod watch;
+#[cfg(all(feature = "portable", windows))]
+mod windows;
 
 /// Wrapper for sd-id128 as offered in libsystemd.
 ///
@@ -82,13 +221,72 @@ use std::{convert::TryFrom,
 /// - boot_id_app_specific: get hashed boot id
 /// - machine_id: get machine id
 /// - machine_id_app_specific: get hashed machine id
+/// - app_specific: get hashed arbitrary id
 /// - invocation_id: get service invocation id
 /// - random_id: get a random id
 /// - from_string_sd: parse string into id using libsystemd
+/// - machine_id_of_container: get machine id of a container from machined
+/// - machine_id_via_hostnamed: get machine id from hostnamed
+/// - invocation_id_of_unit: get invocation id of another unit
 ///
 /// Native Constructors -> Result<ID128, Error>
 /// - from_string: parse string into id using native Rust
+/// - from_ascii: parse ASCII bytes into id using native Rust
 /// - from_string_lax: parse string into id using native Rust with lax rules
+/// - from_str_lax_with: parse string into id tolerating what LaxOptions allow
+/// - parse_detailed: parse string into id, format and case
+/// - from_str_format: parse string into id accepting only the given format
+/// - new_v6: generate a time-ordered UUID v6
+/// - machine_id_app_specific_native: get hashed machine id without libsystemd
+///   hashing
+/// - boot_id_app_specific_native: get hashed boot id without libsystemd hashing
+/// - random_batch: generate many random ids with a single entropy read
+/// - persistent: load an id from a state file or create it on first run
+/// - machine_id_of: get machine id of an alternative root directory
+/// - machine_id_uncached: get machine id from /etc/machine-id on every call
+/// - invocation_id_from_env: get service invocation id without libsystemd
+/// - boot_id_from_procfs: get boot id from procfs without libsystemd
+/// - from_credential: get an id passed as service credential
+/// - container_uuid: get the uuid of the container the process runs in
+/// - dmi_product_uuid: get the uuid of the hardware from the firmware
+/// - windows_machine_guid: get the machine guid of Windows
+/// - macos_platform_uuid: get the platform uuid of macOS
+/// - bsd_host_uuid: get the host uuid of FreeBSD or OpenBSD
+/// - machine_id_from: get machine id from a chain of sources
+/// - machine_id_default_chain: get machine id from libsystemd, /etc/machine-id
+///   or /var/lib/dbus/machine-id
+/// - fast_random_id: get a random id from a thread-local generator
+/// - random_id_getrandom: get a random id using the getrandom crate
+///
+/// Native Constructors -> ID128
+/// - app_id_from_name: derive an application id from its name
+/// - new_v1: construct a time-based UUID v1
+/// - new_v8: construct a custom UUID v8
+/// - random_with: generate a random id from a caller supplied RNG
+/// - from_seed: construct a deterministic id from a numeric seed
+/// - from_seed_bytes: construct a deterministic id from a byte seed
+/// - app_specific_native: hash an arbitrary id like libsystemd does
+/// - derive: hash this id like libsystemd does, may be chained
+/// - rotating_id: derive an id which changes every time window
+/// - rotating_id_at: derive the rotating id of a given point in time
+/// - from_smbios_uuid: decode the raw uuid of SMBIOS
+/// - parse_many: parse a list of ids separated by newlines, commas or
+///   whitespace into an iterator
+///
+/// Const Constructors -> ID128
+/// - NULL: the null id
+/// - from_raw_value: construct an id from its raw bytes
+/// - from_str_const: parse an id in const contexts, e.g. to declare well-known
+///   ids as const items usable in match patterns
+///
+/// Native Validators -> bool
+/// - is_valid: check a string like from_string without constructing an id
+/// - is_valid_format: check a string like from_str_format
+///
+/// Cached Accessors -> Result<&'static ID128, &'static Error>
+/// - machine_id_cached: get machine id retrieved once per process
+/// - boot_id_cached: get boot id retrieved once per process
+/// - invocation_id_cached: get service invocation id retrieved once per process
 ///
 /// FFI Methods -> Result<T, Error>
 /// - to_string_sd: format an id as String using libsystemd
@@ -97,17 +295,36 @@ use std::{convert::TryFrom,
 /// Native Method -> T
 /// - to_string: format an id as String in default format using native Rust
 /// - to_string_formatted: format an id as String using native Rust
+/// - to_string_with: format an id as String according to FormatOptions
+/// - encode_into: format an id into a caller supplied buffer
+/// - write_to, write_io: append an id to a fmt::Write or io::Write
+/// - to_libsystemd_bytes, to_rfc_bytes: format an id into a fixed size array
+/// - to_array_string: format an id into an ArrayString (feature `arrayvec`)
+/// - rfc, simple, libsystemd, urn, braced, formatted: adapters implementing
+///   Display without building a String
+/// - timestamp: extract the timestamp of a time-based id
+/// - equal_str: compare an id with a string in any format without parsing
+/// - matches_prefix: check whether an id starts with abbreviated hex digits
+/// - validate_rfc4122: check that an id is a random UUID v4
+/// - journal_match, boot_match, message_id_match, invocation_match: build a
+///   `FIELD=ID` match of the journal
 ///
 /// Implemented Traits
 /// - Display: provides `to_string(&ID128) -> String` and `format!(..., &ID128)`
+/// - LowerHex, UpperHex: provide `format!("{:x}", &ID128)` and `format!("{:X}",
+///   &ID128)` in libsystemd format
 /// - From<ID128> -> String: provides `into(ID128) -> String`
 /// - TryFrom<String> -> ID128: provides `try_into(String) -> ID128`
 /// - From<<ffi::sd_id128>> -> ID128: provides `into(ffi::sd_id128) -> ID128`
+///   (feature `ffi`)
 /// - From<ID128> -> ffi::sd_id128: provides `into(ID128) -> ffi::sd_id128`
+///   (feature `ffi`)
 /// - AsRef<[u8; 16]>: provides `as_ref(&ID128) -> &[u8; 16]`
 /// - Clone: provides `clone(&ID128) -> ID128`
 /// - From<ID128> -> [u8; 16]: provides `into(ID128) -> [u8; 16]`
 /// - From<[u8; 16]> -> ID128: provides `into([u8; 16]) -> ID128`
+/// - AsId128: provides `as_id128(&ID128) -> ID128`
+/// - Distribution<ID128> for rand's Standard: provides `gen(&mut Rng) -> ID128`
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ID128 {
     ffi: ffi::sd_id128
@@ -120,17 +337,33 @@ pub struct ID128 {
 ///   C compatible CString. The error is caused by contained 0x00 bytes in
 ///   String which are not compatible with C & CString.
 /// - SDError: This error is raised after libsystemd returned a negative return
-///   code, i.e. an error code.
+///   code, i.e. an error code. The code is the negative errno value, see
+///   `errno`, or use `sd_errno` to match on the code.
 /// - StringError: This error is raised during translation of C compatible
 ///   CString back into native String. The error is caused by non-UTF8 symbols.
+/// - ParseStringError: This error is raised while parsing a malformed ID, it
+///   carries a `ParseError` describing the problem and its position.
+/// - NotUnderSystemd: This error is raised if the invocation ID is requested,
+///   but the process was not started by systemd, i.e. `$INVOCATION_ID` is not
+///   set. Services may fall back to a random ID then.
+///
+/// The enum is non-exhaustive: further kinds of errors, e.g. of backends or
+/// D-Bus, may be added in minor releases, so matches require a wildcard arm.
+/// Use the accessors like `errno` to inspect errors independently of their
+/// variant.
 #[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
     NullError(NulError),
     SDError(i32),
     IntoStringError(IntoStringError),
-    ParseStringError(&'static str, usize)
+    ParseStringError(ParseError),
+    NotUnderSystemd
 }
 
+/// Result of the operations of sd-id128, the error defaults to `Error`.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
 /// String formats available during transformation from an ID into text
 ///
 /// Variants:
@@ -139,11 +372,29 @@ pub enum Error {
 ///   all formatting performed by calling FFI functionality
 /// - RFC: 00000000-0000-0000-0000-000000000000, this format is applied by
 ///   default to all native formatting
-#[derive(Debug, Eq, PartialEq)]
+/// - Urn: urn:uuid:00000000-0000-0000-0000-000000000000, the URN of RFC 4122
+///   required by registries and XML based protocols; the prefix is always lower
+///   case
+/// - Braced: {00000000-0000-0000-0000-000000000000}, the registry format of
+///   Windows, also used by GPT tooling
+/// - Custom: groups of hexadecimal digits of the given sizes joined by
+///   `separator`, e.g. `group_sizes: &[4; 8], separator: ':'` formats
+///   0000:0000:0000:0000:0000:0000:0000:0000; digits beyond the sum of the
+///   sizes form a last group. This format is only available for formatting.
+///
+/// The default is RFC.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
 pub enum Format {
     Simple,
     LibSystemD,
-    RFC
+    #[default]
+    RFC,
+    Urn,
+    Braced,
+    Custom {
+        group_sizes: &'static [usize],
+        separator:   char
+    }
 }
 
 /// Format of hexadecimal letters during transformation from an ID into text
@@ -151,36 +402,580 @@ pub enum Format {
 /// Variants:
 /// - Upper
 /// - Lower: lower case is applied as default to all formatting
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
 pub enum Case {
     Upper,
+    #[default]
     Lower
 }
 
+/// Formatting configuration bundling format, case and an optional prefix,
+/// e.g. to store it in the configuration of an application.
+///
+/// ```rust
+/// use sd_id128::{Case, Format, FormatOptions, ID128};
+/// let options = FormatOptions::new().format(Format::LibSystemD)
+///                                   .case(Case::Upper)
+///                                   .prefix("machine-");
+/// let id128 = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+/// assert_eq!(id128.to_string_with(&options),
+///            "machine-0123456789ABCDEF0123456789ABCDEF");
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    format: Format,
+    case:   Case,
+    prefix: Option<String>
+}
+
+/// Leniency of `ID128::from_str_lax_with`.
+///
+/// Leading and trailing whitespace is always ignored. Each field tolerates:
+/// - braces: enclosing braces, i.e. `Format::Braced`
+/// - urn_prefix: the prefix `urn:uuid:` in any case, i.e. `Format::Urn`
+/// - hex_prefix: the prefix `0x` or `0X`
+/// - whitespace: whitespace anywhere, e.g. in wrapped hex dumps
+/// - dashes: dashes anywhere
+/// - underscores: underscores anywhere, e.g. in identifiers
+/// - colons: colons anywhere, e.g. in MAC address like notations
+///
+/// The default tolerates what `from_str_lax` tolerates: braces, the URN
+/// prefix and dashes. Other options are built from the default or from
+/// `permissive`, e.g. `LaxOptions::default().hex_prefix(true)`, as fields may
+/// be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LaxOptions {
+    pub braces:      bool,
+    pub urn_prefix:  bool,
+    pub hex_prefix:  bool,
+    pub whitespace:  bool,
+    pub dashes:      bool,
+    pub underscores: bool,
+    pub colons:      bool
+}
+
+impl Default for LaxOptions {
+    fn default() -> Self {
+        LaxOptions { braces:      true,
+                     urn_prefix:  true,
+                     hex_prefix:  false,
+                     whitespace:  false,
+                     dashes:      true,
+                     underscores: false,
+                     colons:      false }
+    }
+}
+
+impl LaxOptions {
+    /// Returns options tolerating everything.
+    pub fn permissive() -> Self {
+        LaxOptions { braces:      true,
+                     urn_prefix:  true,
+                     hex_prefix:  true,
+                     whitespace:  true,
+                     dashes:      true,
+                     underscores: true,
+                     colons:      true }
+    }
+
+    /// Sets whether enclosing braces are tolerated.
+    pub fn braces(mut self, tolerate: bool) -> Self {
+        self.braces = tolerate;
+        self
+    }
+
+    /// Sets whether the prefix `urn:uuid:` is tolerated.
+    pub fn urn_prefix(mut self, tolerate: bool) -> Self {
+        self.urn_prefix = tolerate;
+        self
+    }
+
+    /// Sets whether the prefix `0x` is tolerated.
+    pub fn hex_prefix(mut self, tolerate: bool) -> Self {
+        self.hex_prefix = tolerate;
+        self
+    }
+
+    /// Sets whether whitespace anywhere is tolerated.
+    pub fn whitespace(mut self, tolerate: bool) -> Self {
+        self.whitespace = tolerate;
+        self
+    }
+
+    /// Sets whether dashes anywhere are tolerated.
+    pub fn dashes(mut self, tolerate: bool) -> Self {
+        self.dashes = tolerate;
+        self
+    }
+
+    /// Sets whether underscores anywhere are tolerated.
+    pub fn underscores(mut self, tolerate: bool) -> Self {
+        self.underscores = tolerate;
+        self
+    }
+
+    /// Sets whether colons anywhere are tolerated.
+    pub fn colons(mut self, tolerate: bool) -> Self {
+        self.colons = tolerate;
+        self
+    }
+}
+
+impl FormatOptions {
+    /// Creates options for the default format: RFC, lower case, no prefix.
+    pub fn new() -> Self {
+        FormatOptions::default()
+    }
+
+    /// Sets the format.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the case of hexadecimal letters.
+    pub fn case(mut self, case: Case) -> Self {
+        self.case = case;
+        self
+    }
+
+    /// Sets a prefix written in front of the ID, e.g. a type tag.
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+}
+
+impl Format {
+    /// Detects format and case of an ID, e.g. to report or preserve the
+    /// representation of IDs ingested from various sources.
+    ///
+    /// An ID without any letters is reported as lower case. `Format::Custom` is
+    /// never detected.
+    ///
+    /// # Return Values
+    /// - Some((Format, Case)): the string is a valid ID, see `ID128::from_str`
+    /// - None: the string is no valid ID or mixes upper and lower case letters
+    pub fn detect(string: &str) -> Option<(Format, Case)> {
+        ID128::from_str(string).ok()?;
+        let (format, digits) = match unwrap_rfc(string) {
+            Some((uuid, 1)) => (Format::Braced, uuid),
+            Some((uuid, _)) => (Format::Urn, uuid),
+            None => {
+                let format = match string.len() {
+                    39 => Format::Simple,
+                    32 => Format::LibSystemD,
+                    _ => Format::RFC
+                };
+                (format, string)
+            }
+        };
+        let upper = digits.bytes().any(|byte| byte.is_ascii_uppercase());
+        let lower = digits.bytes().any(|byte| byte.is_ascii_lowercase());
+        match (upper, lower) {
+            (true, true) => None,
+            (true, false) => Some((format, Case::Upper)),
+            (false, _) => Some((format, Case::Lower))
+        }
+    }
+
+    /// Returns the length in bytes of an ID formatted in this format.
+    pub fn encoded_len(&self) -> usize {
+        let (prefix, groups, separator, suffix) = self.layout();
+        let separators = groups.iter()
+                               .scan(0, |end, size| {
+                                   *end += size;
+                                   Some(*end)
+                               })
+                               .take_while(|end| *end < 32)
+                               .count();
+        prefix.len() + 32 + separators * separator.len_utf8() + suffix.len()
+    }
+
+    /// Returns prefix, sizes of the groups of digits, separator and suffix.
+    fn layout(&self) -> (&'static str, &'static [usize], char, &'static str) {
+        const SIMPLE: &[usize] = &[4, 4, 4, 4, 4, 4, 4, 4];
+        const RFC: &[usize] = &[8, 4, 4, 4, 12];
+        match *self {
+            Format::Simple => ("", SIMPLE, '-', ""),
+            Format::LibSystemD => ("", &[], '-', ""),
+            Format::RFC => ("", RFC, '-', ""),
+            Format::Urn => (URN_PREFIX, RFC, '-', ""),
+            Format::Braced => ("{", RFC, '-', "}"),
+            Format::Custom { group_sizes,
+                             separator } => ("", group_sizes, separator, "")
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    /// Parses the name of a format, ignoring the case: "simple", "libsystemd",
+    /// "rfc", "urn" or "braced", e.g. from configuration files or command line
+    /// flags. `Format::Custom` has no name.
+    fn from_str(name: &str) -> Result<Self, Error> {
+        match name.to_ascii_lowercase().as_str() {
+            "simple" => Ok(Format::Simple),
+            "libsystemd" => Ok(Format::LibSystemD),
+            "rfc" => Ok(Format::RFC),
+            "urn" => Ok(Format::Urn),
+            "braced" => Ok(Format::Braced),
+            _ => Err(named_parse_error(name, "format name"))
+        }
+    }
+}
+
+impl FromStr for Case {
+    type Err = Error;
+
+    /// Parses the name of a case, ignoring the case: "upper" or "lower".
+    fn from_str(name: &str) -> Result<Self, Error> {
+        match name.to_ascii_lowercase().as_str() {
+            "upper" => Ok(Case::Upper),
+            "lower" => Ok(Case::Lower),
+            _ => Err(named_parse_error(name, "case name"))
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+const FORMAT_NAMES: &[&str] = &["simple", "libsystemd", "rfc", "urn", "braced"];
+#[cfg(feature = "serde")]
+const CASE_NAMES: &[&str] = &["upper", "lower"];
+
+/// Visitor deserializing a name parsed via `FromStr`.
+#[cfg(feature = "serde")]
+struct NameVisitor<T>(&'static [&'static str], std::marker::PhantomData<T>);
+
+#[cfg(feature = "serde")]
+impl<'de, T: FromStr> serde::de::Visitor<'de> for NameVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "one of {:?}", self.0)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<T, E> {
+        name.parse().map_err(|_| E::unknown_variant(name, self.0))
+    }
+}
+
+/// Serializes the name parsed by `FromStr`, e.g. "rfc". `Format::Custom` has
+/// no name and fails to serialize.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Format {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = match self {
+            Format::Simple => FORMAT_NAMES[0],
+            Format::LibSystemD => FORMAT_NAMES[1],
+            Format::RFC => FORMAT_NAMES[2],
+            Format::Urn => FORMAT_NAMES[3],
+            Format::Braced => FORMAT_NAMES[4],
+            Format::Custom { .. } => {
+                return Err(serde::ser::Error::custom("custom formats cannot be serialized"))
+            },
+        };
+        serializer.serialize_str(name)
+    }
+}
+
+/// Deserializes the names parsed by `FromStr`.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Format {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(NameVisitor(FORMAT_NAMES, std::marker::PhantomData))
+    }
+}
+
+/// Serializes the name parsed by `FromStr`, i.e. "upper" or "lower".
+#[cfg(feature = "serde")]
+impl serde::Serialize for Case {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match self {
+                      Case::Upper => CASE_NAMES[0],
+                      Case::Lower => CASE_NAMES[1]
+                  })
+    }
+}
+
+/// Deserializes the names parsed by `FromStr`.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Case {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(NameVisitor(CASE_NAMES, std::marker::PhantomData))
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::NullError(ref error) => error.fmt(formatter),
-            Error::SDError(_) => todo!(),
+            Error::SDError(code) => SdErrno::from_errno(-code).fmt(formatter),
             Error::IntoStringError(ref error) => error.fmt(formatter),
-            Error::ParseStringError(ref message, ref pos) => {
-                write!(formatter, "{}{}", message, pos)
+            Error::ParseStringError(ref error) => error.fmt(formatter),
+            Error::NotUnderSystemd => {
+                write!(formatter,
+                       "not running under systemd: no invocation ID is set")
             }
         }
     }
 }
 
+impl Error {
+    /// Returns the error code of `SDError` as positive errno value, e.g.
+    /// `libc::ENOENT`, ENXIO for `NotUnderSystemd` like libsystemd reports it,
+    /// or None for any other error.
+    ///
+    /// ```rust
+    /// use sd_id128::{Error, ID128};
+    /// assert_eq!(Error::SDError(-libc::ENOENT).errno(), Some(libc::ENOENT));
+    /// assert_eq!(ID128::from_str("0123").unwrap_err().errno(), None);
+    /// ```
+    pub fn errno(&self) -> Option<i32> {
+        match *self {
+            Error::SDError(code) => Some(-code),
+            Error::NotUnderSystemd => Some(libc::ENXIO),
+            _ => None
+        }
+    }
+
+    /// Returns the error code of `SDError` as `SdErrno` to match on, or None
+    /// for any other error.
+    ///
+    /// ```rust
+    /// use sd_id128::{Error, SdErrno};
+    /// match Error::SDError(-libc::ENOENT).sd_errno() {
+    ///     Some(SdErrno::ENOENT) => println!("not initialized yet"),
+    ///     _ => panic!()
+    /// }
+    /// ```
+    pub fn sd_errno(&self) -> Option<SdErrno> {
+        self.errno().map(SdErrno::from_errno)
+    }
+
+    /// Returns true if an ID file or ID does not exist: ENOENT, or ENOMEDIUM
+    /// and ENOPKG of empty or uninitialized ID files.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.sd_errno(),
+                 Some(SdErrno::ENOENT) | Some(SdErrno::ENOMEDIUM) | Some(SdErrno::ENOPKG))
+    }
+
+    /// Returns true if the access to an ID was denied: EPERM or EACCES.
+    pub fn is_permission_denied(&self) -> bool {
+        matches!(self.sd_errno(),
+                 Some(SdErrno::EPERM) | Some(SdErrno::EACCES))
+    }
+
+    /// Returns true if the process is not running as a systemd unit, i.e. it
+    /// has no invocation ID: `NotUnderSystemd`.
+    ///
+    /// Other sources of ENXIO, e.g. a missing credentials directory, are not
+    /// matched; the invocation ID functions translate ENXIO of libsystemd into
+    /// `NotUnderSystemd`.
+    pub fn is_not_under_systemd(&self) -> bool {
+        matches!(self, Error::NotUnderSystemd)
+    }
+
+    /// Returns true if a string could not be parsed as ID.
+    pub fn is_parse_error(&self) -> bool {
+        matches!(self, Error::ParseStringError(..))
+    }
+
+    /// Returns the OS error code like `io::Error::raw_os_error`, i.e. the
+    /// positive errno value of `SDError` unless libsystemd did not report one.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.errno().filter(|errno| *errno > 0)
+    }
+}
+
+/// Converts `SDError` into an `io::Error` of the same OS error code, so it is
+/// reported like errors of the standard library. All other errors become
+/// `InvalidInput` or `InvalidData` errors wrapping the original error.
+impl From<Error> for io::Error {
+    fn from(error: Error) -> io::Error {
+        if let Some(errno) = error.raw_os_error() {
+            return io::Error::from_raw_os_error(errno);
+        }
+        let kind = match error {
+            Error::NullError(_) => io::ErrorKind::InvalidInput,
+            Error::SDError(_) | Error::NotUnderSystemd => io::ErrorKind::Other,
+            Error::IntoStringError(_) | Error::ParseStringError(..) => io::ErrorKind::InvalidData
+        };
+        io::Error::new(kind, error)
+    }
+}
+
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Error::NullError(ref error) => Some(error),
             Error::SDError(_) => None,
             Error::IntoStringError(ref error) => Some(error),
-            Error::ParseStringError(_, _) => None
+            Error::ParseStringError(_) => None,
+            Error::NotUnderSystemd => None
+        }
+    }
+}
+
+const URN_PREFIX: &str = "urn:uuid:";
+
+/// Strips the prefix `urn:uuid:` of `Format::Urn`, ignoring the case, or the
+/// braces of `Format::Braced`. Returns the enclosed ID and its position.
+fn unwrap_rfc(string: &str) -> Option<(&str, usize)> {
+    let (uuid, offset) = unwrap_rfc_ascii(string.as_bytes())?;
+    Some((&string[offset..offset + uuid.len()], offset))
+}
+
+/// Like `unwrap_rfc` for ASCII bytes.
+fn unwrap_rfc_ascii(bytes: &[u8]) -> Option<(&[u8], usize)> {
+    match bytes.get(..URN_PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(URN_PREFIX.as_bytes()) => {
+            return Some((&bytes[URN_PREFIX.len()..], URN_PREFIX.len()))
+        },
+        _ => {}
+    }
+    let uuid = bytes.strip_prefix(b"{")?.strip_suffix(b"}")?;
+    Some((uuid, 1))
+}
+
+/// Values of the hexadecimal digits, 0xFF for any other byte.
+static HEX_VALUES: [u8; 256] = hex_values();
+
+const fn hex_values() -> [u8; 256] {
+    let mut values = [0xFF; 256];
+    let mut digit = 0;
+    while digit < 16 {
+        values[b"0123456789abcdef"[digit] as usize] = digit as u8;
+        values[b"0123456789ABCDEF"[digit] as usize] = digit as u8;
+        digit += 1;
+    }
+    values
+}
+
+/// Returns true if all bytes are hexadecimal digits.
+fn is_hex(bytes: &[u8]) -> bool {
+    bytes.iter().all(|byte| HEX_VALUES[*byte as usize] < 16)
+}
+
+/// Positions and lengths of the groups of digits of an ID.
+type DigitGroups = &'static [(usize, usize)];
+
+/// Returns the groups of digits of an unwrapped ID by its length and number
+/// of dashes.
+fn digit_groups(bytes: &[u8]) -> Option<DigitGroups> {
+    match (bytes.len(), bytes.iter().filter(|byte| **byte == b'-').count()) {
+        (39, 7) => Some(&[(0, 4),
+                          (5, 4),
+                          (10, 4),
+                          (15, 4),
+                          (20, 4),
+                          (25, 4),
+                          (30, 4),
+                          (35, 4)]),
+        (32, 0) => Some(&[(0, 32)]),
+        (36, 4) => Some(&[(0, 8), (9, 4), (14, 4), (19, 4), (24, 12)]),
+        _ => None
+    }
+}
+
+/// Strips the prefix of `Format::Urn` or the braces of `Format::Braced` like
+/// `from_ascii` and returns the ID and its groups of digits.
+fn unwrapped_digit_groups(bytes: &[u8]) -> Option<(&[u8], DigitGroups)> {
+    let bytes = match unwrap_rfc_ascii(bytes) {
+        Some((uuid, _)) if uuid.len() != 36 => return None,
+        Some((uuid, _)) => uuid,
+        None => bytes
+    };
+    Some((bytes, digit_groups(bytes)?))
+}
+
+/// Returns the error of the first invalid byte of an ID, given the positions
+/// and lengths of its groups of digits. A digit in place of a dash is not
+/// reported, as the dash then appears in a group.
+fn locate_parse_error(bytes: &[u8], groups: &[(usize, usize)]) -> Error {
+    for (position, byte) in bytes.iter().enumerate() {
+        let digit = groups.iter()
+                          .any(|&(start, length)| (start..start + length).contains(&position));
+        match (HEX_VALUES[*byte as usize] < 16, *byte == b'-') {
+            (true, _) => {},
+            (false, true) if !digit => {},
+            (false, true) => return parse_error(ParseProblem::UnexpectedDash, position),
+            (false, false) => return parse_error(ParseProblem::InvalidCharacter, position)
+        }
+    }
+    parse_error(ParseProblem::InvalidCharacter, 0)
+}
+
+/// The formats accepted by `from_str`.
+const ANY_FORMAT: &[Format] = &[Format::Simple,
+                                Format::LibSystemD,
+                                Format::RFC,
+                                Format::Urn,
+                                Format::Braced];
+
+/// Returns the error of parsing an ID at `position`.
+fn parse_error(problem: ParseProblem, position: usize) -> Error {
+    Error::ParseStringError(ParseError::new(problem, position))
+}
+
+/// Returns the error of parsing an unknown name, e.g. of a format.
+fn named_parse_error(name: &str, subject: &'static str) -> Error {
+    Error::ParseStringError(ParseError::new(ParseProblem::UnknownName, 0).subject(subject)
+                                                                         .within(name.as_bytes(),
+                                                                                 &[]))
+}
+
+/// Shifts the position reported by a parse error by `offset` characters.
+fn shift(error: Error, offset: usize) -> Error {
+    match error {
+        Error::ParseStringError(error) => Error::ParseStringError(error.shift(offset)),
+        error => error
+    }
+}
+
+/// Attaches the input and the formats expected while parsing it to a parse
+/// error.
+fn within(error: Error, input: &[u8], expected: &[Format]) -> Error {
+    match error {
+        Error::ParseStringError(error) => Error::ParseStringError(error.within(input, expected)),
+        error => error
+    }
+}
+
+/// Translates ENXIO and ENOENT of `sd_id128_get_invocation` into
+/// `Error::NotUnderSystemd`.
+#[cfg(any(systemd_v232, feature = "no-ffi", not(feature = "ffi")))]
+fn not_under_systemd(error: Error) -> Error {
+    match error {
+        Error::SDError(code) if code == -libc::ENXIO || code == -libc::ENOENT => {
+            Error::NotUnderSystemd
+        },
+        error => error
+    }
+}
+
+/// Returns the formats `from_str` expects for the length of `bytes`.
+fn expected_formats(bytes: &[u8]) -> &'static [Format] {
+    match (unwrap_rfc_ascii(bytes), bytes.first()) {
+        (Some(_), Some(b'{')) => &[Format::Braced],
+        (Some(_), _) => &[Format::Urn],
+        (None, _) => match bytes.len() {
+            32 => &[Format::LibSystemD],
+            36 => &[Format::RFC],
+            39 => &[Format::Simple],
+            _ => ANY_FORMAT
         }
     }
 }
 
+/// Translates an I/O error of native functionality into the negative errno
+/// convention used by libsystemd.
+pub(crate) fn from_io(error: io::Error) -> Error {
+    Error::SDError(-error.raw_os_error().unwrap_or(libc::EIO))
+}
+
 impl From<ID128> for String {
     fn from(id128: ID128) -> String {
         id128.to_string()
@@ -195,20 +990,30 @@ impl TryFrom<&str> for ID128 {
     }
 }
 
+/// Displays the ID in RFC format or the format configured by
+/// `set_display_format`, the alternate form `{:#}` displays it in libsystemd
+/// format. Width, fill and alignment are honored, a precision truncates the
+/// ID, e.g. `{:.8}` displays the first 8 digits like journalctl shortens boot
+/// IDs.
 impl fmt::Display for ID128 {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter,
-               "{}",
-               self.to_string_formatted(Format::RFC, Case::Lower))
+        let (format, case) = if formatter.alternate() {
+            (Format::LibSystemD, Case::Lower)
+        } else {
+            display_format()
+        };
+        fmt::Display::fmt(&self.formatted(format, case), formatter)
     }
 }
 
+#[cfg(feature = "ffi")]
 impl From<ID128> for ffi::sd_id128 {
     fn from(id128: ID128) -> ffi::sd_id128 {
         id128.ffi
     }
 }
 
+#[cfg(feature = "ffi")]
 impl From<ffi::sd_id128> for ID128 {
     fn from(sd_id128: ffi::sd_id128) -> ID128 {
         ID128 { ffi: sd_id128 }
@@ -233,7 +1038,52 @@ impl From<[u8; 16]> for ID128 {
     }
 }
 
+/// Values which denote an ID, accepted by the APIs of the crates of
+/// systemd.rs, e.g. to add a boot filter to sd-journal, so they share the
+/// conversions of this crate instead of redefining them.
+///
+/// Provided implementations:
+/// - ID128
+/// - MessageId: the wrapped ID
+/// - [u8; 16]: the raw value
+/// - references to any of them
+///
+/// ```rust
+/// use sd_id128::{messages, AsId128, ID128};
+/// fn boot_filter(boot: impl AsId128) -> String {
+///     boot.as_id128().boot_match()
+/// }
+/// let id128 = ID128::from_raw_value([0x11; 16]);
+/// assert_eq!(boot_filter(&id128), boot_filter([0x11; 16]));
+/// assert_eq!(messages::UNIT_FAILED.as_id128(), messages::UNIT_FAILED);
+/// ```
+pub trait AsId128 {
+    /// Returns the ID.
+    fn as_id128(&self) -> ID128;
+}
+
+impl AsId128 for ID128 {
+    fn as_id128(&self) -> ID128 {
+        self.clone()
+    }
+}
+
+impl AsId128 for [u8; 16] {
+    fn as_id128(&self) -> ID128 {
+        ID128::from(*self)
+    }
+}
+
+impl<T: AsId128 + ?Sized> AsId128 for &T {
+    fn as_id128(&self) -> ID128 {
+        (**self).as_id128()
+    }
+}
+
 impl ID128 {
+    /// The null ID, i.e. all bits are 0.
+    pub const NULL: ID128 = ID128::from_raw_value([0; 16]);
+
     /// Generates a new randomized 128-bit ID
     /// ([`sd_id128_randomize`](https://www.freedesktop.org/software/systemd/man/sd_id128_randomize.html#)).
     ///
@@ -241,16 +1091,33 @@ impl ID128 {
     /// uses the /dev/urandom kernel random number generator. Note that
     /// `sd_id128_randomize()` always returns a UUID v4-compatible ID.
     ///
+    /// With the feature `getrandom`, a failure of libsystemd is not reported
+    /// but the ID is generated by `random_id_getrandom` instead.
+    ///
+    /// libsystemd is only used with the default entropy policy
+    /// `EntropyPolicy::LibSystemD`. Any other policy reads the random bits
+    /// from the configured source natively, see module `entropy`. With the
+    /// feature `no-ffi`, the random bits are always read natively.
+    ///
     /// # Return Values
     /// - Ok(ID128): initialized ID128 struct
     /// - Err(Error::SDError(i32)): sd-id128 returned an error code
     pub fn random_id() -> Result<Self, Error> {
-        let mut id128 = ffi::sd_id128::default();
-        let result = unsafe { ffi::sd_id128_randomize(&mut id128) };
-        if result < 0 {
-            return Err(Error::SDError(result));
+        #[cfg(feature = "failpoints")]
+        failpoints::check(failpoints::Call::Randomize)?;
+        if entropy::policy() != entropy::EntropyPolicy::LibSystemD {
+            let mut value = [0u8; 16];
+            entropy::fill(&mut value)?;
+            uuid::set_version(&mut value, 4);
+            return Ok(ID128::from_raw_value(value));
+        }
+        match backend::randomize() {
+            Ok(value) => Ok(ID128::from_raw_value(value)),
+            #[cfg(feature = "getrandom")]
+            Err(_) => ID128::random_id_getrandom(),
+            #[cfg(not(feature = "getrandom"))]
+            Err(error) => Err(error)
         }
-        Ok(id128.into())
     }
 
     /// Returns the boot ID of the executing kernel
@@ -261,16 +1128,16 @@ impl ID128 {
     /// unique for every running kernel instance. This function also internally
     /// caches the returned ID to make this call a cheap operation.
     ///
+    /// With the feature `no-ffi`, /proc/sys/kernel/random/boot_id is read
+    /// natively on every call.
+    ///
     /// # Return Values
     /// - Ok(ID128): initialized ID128 struct
     /// - Err(Error::SDError(i32)): sd-id128 returned an error code
     pub fn boot_id() -> Result<Self, Error> {
-        let mut id128 = ffi::sd_id128::default();
-        let result = unsafe { ffi::sd_id128_get_boot(&mut id128) };
-        if result < 0 {
-            return Err(Error::SDError(result));
-        }
-        Ok(id128.into())
+        #[cfg(feature = "failpoints")]
+        failpoints::check(failpoints::Call::Boot)?;
+        backend::get_boot().map(ID128::from_raw_value)
     }
 
     /// Returns an app specific boot id
@@ -285,14 +1152,12 @@ impl ID128 {
     /// # Return Values
     /// - Ok(ID128): initialized ID128 struct
     /// - Err(Error::SDError(i32)): sd-id128 returned an error code
-    #[cfg(feature = "240")]
+    #[cfg(any(systemd_v240,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
     pub fn boot_id_app_specific(app: ID128) -> Result<Self, Error> {
-        let mut boot = ffi::sd_id128::default();
-        let result = unsafe { ffi::sd_id128_get_boot_app_specific(app.ffi, &mut boot) };
-        if result < 0 {
-            return Err(Error::SDError(result));
-        }
-        Ok(boot.into())
+        #[cfg(feature = "failpoints")]
+        failpoints::check(failpoints::Call::BootAppSpecific)?;
+        backend::get_boot_app_specific(app.into_raw_value()).map(ID128::from_raw_value)
     }
 
     /// Returns the machine ID of the executing host
@@ -303,16 +1168,16 @@ impl ID128 {
     /// operation. This ID may be used wherever a unique identifier for the
     /// local system is needed.
     ///
+    /// With the feature `no-ffi`, /etc/machine-id is read natively on every
+    /// call.
+    ///
     /// # Return Values
     /// - Ok(ID128): initialized ID128 struct
     /// - Err(Error::SDError(i32)): sd-id128 returned an error code
     pub fn machine_id() -> Result<Self, Error> {
-        let mut id128 = ffi::sd_id128::default();
-        let result = unsafe { ffi::sd_id128_get_machine(&mut id128) };
-        if result < 0 {
-            return Err(Error::SDError(result));
-        }
-        Ok(id128.into())
+        #[cfg(feature = "failpoints")]
+        failpoints::check(failpoints::Call::Machine)?;
+        backend::get_machine().map(ID128::from_raw_value)
     }
 
     /// Returns an app specific machine id
@@ -328,14 +1193,33 @@ impl ID128 {
     /// # Return Values
     /// - Ok(ID128): initialized ID128 struct
     /// - Err(Error::SDError(i32)): sd-id128 returned an error code
-    #[cfg(any(feature = "233", feature = "240"))]
+    #[cfg(any(systemd_v233,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
     pub fn machine_id_app_specific(app: ID128) -> Result<Self, Error> {
-        let mut machine = ffi::sd_id128::default();
-        let result = unsafe { ffi::sd_id128_get_machine_app_specific(app.ffi, &mut machine) };
-        if result < 0 {
-            return Err(Error::SDError(result));
-        }
-        Ok(machine.into())
+        #[cfg(feature = "failpoints")]
+        failpoints::check(failpoints::Call::MachineAppSpecific)?;
+        backend::get_machine_app_specific(app.into_raw_value()).map(ID128::from_raw_value)
+    }
+
+    /// Returns an app specific id derived from an arbitrary base id
+    /// ([`sd_id128_get_app_specific`](https://www.freedesktop.org/software/systemd/man/sd_id128_get_machine.html#)).
+    ///
+    /// This is the generalization of `machine_id_app_specific` and
+    /// `boot_id_app_specific`: the derivation is applied to `base` instead of
+    /// the machine or boot ID. This way any ID may be hashed with the official
+    /// libsystemd implementation before it is passed to untrusted
+    /// environments.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): sd-id128 returned an error code
+    #[cfg(any(systemd_v255,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+    pub fn app_specific(base: ID128, app: ID128) -> Result<Self, Error> {
+        #[cfg(feature = "failpoints")]
+        failpoints::check(failpoints::Call::AppSpecific)?;
+        backend::get_app_specific(base.into_raw_value(),
+                                  app.into_raw_value()).map(ID128::from_raw_value)
     }
 
     /// Returns the invocation ID of the service
@@ -343,19 +1227,27 @@ impl ID128 {
     ///
     /// In its current implementation, this reads and parses the $INVOCATION_ID
     /// environment variable that the service manager sets when activating a
-    /// service.
+    /// service. With the feature `no-ffi`, the variable is read natively.
     ///
     /// # Return Values
     /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::NotUnderSystemd): the process was not started by systemd,
+    ///   i.e. libsystemd reported ENXIO or ENOENT
     /// - Err(Error::SDError(i32)): sd-id128 returned an error code
-    #[cfg(any(feature = "232", feature = "233", feature = "240"))]
+    ///
+    /// ```rust
+    /// use sd_id128::{Error, ID128};
+    /// let run_id = match ID128::invocation_id() {
+    ///     Err(Error::NotUnderSystemd) => ID128::random_id(),
+    ///     result => result
+    /// };
+    /// ```
+    #[cfg(any(systemd_v232, feature = "no-ffi", not(feature = "ffi")))]
     pub fn invocation_id() -> Result<Self, Error> {
-        let mut id128 = ffi::sd_id128::default();
-        let result = unsafe { ffi::sd_id128_get_invocation(&mut id128) };
-        if result < 0 {
-            return Err(Error::SDError(result));
-        }
-        Ok(id128.into())
+        #[cfg(feature = "failpoints")]
+        failpoints::check(failpoints::Call::Invocation).map_err(not_under_systemd)?;
+        backend::get_invocation().map(ID128::from_raw_value)
+                                 .map_err(not_under_systemd)
     }
 
     /// Parses a string into an ID applying strict rules using native Rust
@@ -373,73 +1265,77 @@ impl ID128 {
     /// - only dashes an hexadecimal numbers are allowed
     /// - letter casing can be either upper or lower case
     /// - dashes must conform precisely to any of the formats
+    /// - the prefix `urn:uuid:` of `Format::Urn` is accepted in any case
+    /// - braces enclosing the RFC format (`Format::Braced`) are accepted
     ///
     /// # Return Values
     /// - Ok(ID128): success
     /// - Err(Error::ParseStringError): the source string did not strictly
     ///   comply with the expected format
     pub fn from_str(string: &str) -> Result<Self, Error> {
+        ID128::from_ascii(string.as_bytes())
+    }
+
+    /// Parses ASCII bytes into an ID applying strict rules like `from_str`.
+    ///
+    /// The bytes are parsed in place, i.e. without UTF-8 validation or an
+    /// intermediate String, e.g. for raw journal exports or network frames.
+    /// Any byte but dashes and hexadecimal digits (as well as the prefix and
+    /// braces of `Format::Urn` and `Format::Braced`) is rejected.
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// let frame = b"_BOOT_ID=0123456789abcdef0123456789abcdef\n";
+    /// let id128 = ID128::from_ascii(&frame[9..41]).unwrap();
+    /// ```
+    ///
+    /// # Return Values
+    /// - Ok(ID128): success
+    /// - Err(Error::ParseStringError): the source bytes did not strictly comply
+    ///   with the expected format
+    pub fn from_ascii(bytes: &[u8]) -> Result<Self, Error> {
+        ID128::decode_ascii(bytes).map_err(|error| match error {
+                                      Error::ParseStringError(ref parse)
+                                          if matches!(parse.problem(),
+                                                      ParseProblem::InvalidLength(_)) =>
+                                      {
+                                          within(error, bytes, ANY_FORMAT)
+                                      },
+                                      error => within(error, bytes, expected_formats(bytes))
+                                  })
+    }
+
+    /// Parses ASCII bytes like `from_ascii` without attaching the input to
+    /// errors.
+    fn decode_ascii(bytes: &[u8]) -> Result<Self, Error> {
+        if let Some((uuid, offset)) = unwrap_rfc_ascii(bytes) {
+            if uuid.len() != 36 {
+                return Err(parse_error(ParseProblem::InvalidLength(bytes.len()), 0));
+            }
+            return ID128::decode_ascii(uuid).map_err(|error| shift(error, offset));
+        }
+        let groups = digit_groups(bytes).ok_or_else(|| {
+                                            parse_error(ParseProblem::InvalidLength(bytes.len()), 0)
+                                        })?;
+        #[cfg(feature = "simd")]
+        if let Some(value) = <&[u8; 32]>::try_from(bytes).ok().and_then(simd::decode) {
+            return Ok(ID128::from_raw_value(value));
+        }
+        // With the digits in place, the remaining positions hold the dashes.
         let mut id = ID128::default();
-        let mut idseg = 0;
-        let mut value = 0;
-        let mut pair = false;
-        let format = match (string.len(), string.matches('-').count()) {
-            (39, 7) => Format::Simple,
-            (32, 0) => Format::LibSystemD,
-            (36, 4) => Format::RFC,
-            _ => return Err(Error::ParseStringError("Invalid string length: ", string.len()))
-        };
-        for (charpos, char) in string.char_indices() {
-            value += match char {
-                '0'..='9' => char as u32 - '0' as u32,
-                'a'..='f' => char as u32 - 'a' as u32 + 10,
-                'A'..='F' => char as u32 - 'A' as u32 + 10,
-                '-' => match format {
-                    Format::LibSystemD => {
-                        return Err(Error::ParseStringError("String contains an unexpected \
-                                                            dash at position: ",
-                                                           charpos))
-                    },
-                    Format::RFC => {
-                        if charpos == 8 || charpos == 13 || charpos == 18 || charpos == 23 {
-                            continue;
-                        } else {
-                            return Err(Error::ParseStringError("String contains an unexpected \
-                                                                dash at position: ",
-                                                               charpos));
-                        }
-                    },
-                    Format::Simple => {
-                        if charpos == 4
-                           || charpos == 9
-                           || charpos == 14
-                           || charpos == 19
-                           || charpos == 24
-                           || charpos == 29
-                           || charpos == 34
-                        {
-                            continue;
-                        } else {
-                            return Err(Error::ParseStringError("String contains an unexpected \
-                                                                dash at position: ",
-                                                               charpos));
-                        }
-                    }
-                },
-                _ => {
-                    return Err(Error::ParseStringError("String contains an invalid \
-                                                        character at position: ",
-                                                       charpos))
-                },
-            };
-            if pair {
-                id.ffi.value[idseg] = value as u8;
-                idseg += 1;
-                value = 0;
-            } else {
-                value *= 16;
+        let mut invalid = 0;
+        let mut index = 0;
+        for &(start, length) in groups {
+            for pair in bytes[start..start + length].chunks_exact(2) {
+                let high = HEX_VALUES[pair[0] as usize];
+                let low = HEX_VALUES[pair[1] as usize];
+                invalid |= high | low;
+                id.ffi.value[index] = high << 4 | low;
+                index += 1;
             }
-            pair = !pair;
+        }
+        if invalid > 0x0F {
+            return Err(locate_parse_error(bytes, groups));
         }
         Ok(id)
     }
@@ -457,16 +1353,305 @@ impl ID128 {
     /// This method reuses the strict parsing of `from_string` after
     /// pre-processing the source string as follows:
     /// - trim
+    /// - strip the prefix `urn:uuid:` or the enclosing braces
     /// - remove all dashes: transform the string from any valid or invalid
     ///   format into a libsystemd conforming format
     ///
+    /// These are the defaults of `LaxOptions`, see `from_str_lax_with` for
+    /// other sources.
+    ///
     /// # Return Values
     /// - Ok(ID128): success
     /// - Err(Error::ParseStringError): the source string did not comply with
     ///   the expected format
     pub fn from_str_lax(string: &str) -> Result<Self, Error> {
-        let string = string.trim().replace("-", "");
-        ID128::from_str(string.as_str())
+        ID128::from_str_lax_with(string, &LaxOptions::default())
+    }
+
+    /// Parses a string into an ID tolerating what `options` allows.
+    ///
+    /// The source string is trimmed, the enabled prefixes and braces are
+    /// stripped and the enabled separators are removed. The remainder is
+    /// parsed by `from_str`, i.e. it must be in libsystemd format then.
+    ///
+    /// ```rust
+    /// use sd_id128::{LaxOptions, ID128};
+    /// let options = LaxOptions::default().hex_prefix(true).colons(true);
+    /// let id128 =
+    ///     ID128::from_str_lax_with("0x0123:4567:89ab:cdef:0123:4567:89ab:cdef", &options).unwrap();
+    /// ```
+    ///
+    /// # Return Values
+    /// - Ok(ID128): success
+    /// - Err(Error::ParseStringError): the source string did not comply with
+    ///   the expected format
+    pub fn from_str_lax_with(string: &str, options: &LaxOptions) -> Result<Self, Error> {
+        let mut string = string.trim();
+        if options.urn_prefix {
+            string = match string.get(..URN_PREFIX.len()) {
+                Some(prefix) if prefix.eq_ignore_ascii_case(URN_PREFIX) => {
+                    &string[URN_PREFIX.len()..]
+                },
+                _ => string
+            };
+        }
+        if options.hex_prefix {
+            string = string.strip_prefix("0x")
+                           .or_else(|| string.strip_prefix("0X"))
+                           .unwrap_or(string);
+        }
+        if options.braces {
+            string = string.strip_prefix('{')
+                           .and_then(|string| string.strip_suffix('}'))
+                           .unwrap_or(string);
+        }
+        let string = string.chars()
+                           .filter(|char| match char {
+                               '-' => !options.dashes,
+                               '_' => !options.underscores,
+                               ':' => !options.colons,
+                               _ => !(options.whitespace && char.is_whitespace())
+                           })
+                           .collect::<String>();
+        ID128::from_str(&string)
+    }
+
+    /// Parses a string into an ID and returns the format and case it was
+    /// given in.
+    ///
+    /// The string is parsed strictly like `from_str`. Re-emitting the ID with
+    /// the returned format and case reproduces the source string, e.g. to
+    /// keep configuration files stable when rewriting them. Hence the prefix
+    /// `urn:uuid:` is only accepted in lower case, the case it is formatted in.
+    ///
+    /// ```rust
+    /// use sd_id128::{Case, Format, ID128};
+    /// let source = "{01234567-89AB-CDEF-0123-456789ABCDEF}";
+    /// let (id128, format, case) = ID128::parse_detailed(source).unwrap();
+    /// assert_eq!((format, case), (Format::Braced, Case::Upper));
+    /// assert_eq!(id128.to_string_formatted(format, case), source);
+    /// ```
+    ///
+    /// # Return Values
+    /// - Ok((ID128, Format, Case)): success
+    /// - Err(Error::ParseStringError): the source string did not strictly
+    ///   comply with the expected format, mixes upper and lower case letters or
+    ///   has an upper case URN prefix, i.e. it cannot be reproduced
+    pub fn parse_detailed(string: &str) -> Result<(Self, Format, Case), Error> {
+        let id128 = ID128::from_str(string)?;
+        match Format::detect(string) {
+            Some((Format::Urn, _)) if !string.starts_with(URN_PREFIX) => {
+                let position = string.bytes()
+                                     .position(|byte| byte.is_ascii_uppercase())
+                                     .unwrap_or(0);
+                Err(within(parse_error(ParseProblem::InvalidCharacter,
+                                       position),
+                           string.as_bytes(),
+                           &[Format::Urn]))
+            },
+            Some((format, case)) => Ok((id128, format, case)),
+            None => {
+                let (digits, offset) = unwrap_rfc(string).unwrap_or((string, 0));
+                let upper = digits.bytes()
+                                  .find(u8::is_ascii_alphabetic)
+                                  .map(|byte| byte.is_ascii_uppercase());
+                let position = digits.bytes()
+                                     .position(|byte| {
+                                         byte.is_ascii_alphabetic()
+                                         && Some(byte.is_ascii_uppercase()) != upper
+                                     })
+                                     .unwrap_or(0);
+                Err(within(parse_error(ParseProblem::MixedCase,
+                                       position + offset),
+                           string.as_bytes(),
+                           expected_formats(string.as_bytes())))
+            }
+        }
+    }
+
+    /// Checks whether `from_str` accepts a string without constructing an ID
+    /// or an error, e.g. for validating large amounts of input.
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// assert!(ID128::is_valid("01234567-89ab-cdef-0123-456789abcdef"));
+    /// assert!(!ID128::is_valid("01234567-89ab-cdef-0123-456789abcdeg"));
+    /// ```
+    pub fn is_valid(string: &str) -> bool {
+        let (bytes, groups) = match unwrapped_digit_groups(string.as_bytes()) {
+            Some(unwrapped) => unwrapped,
+            None => return false
+        };
+        // With the digits in place, the remaining positions hold the dashes.
+        groups.iter()
+              .all(|&(start, length)| is_hex(&bytes[start..start + length]))
+    }
+
+    /// Checks whether `from_str_format` accepts a string in `format`. Neither
+    /// the check nor a failure allocates.
+    pub fn is_valid_format(string: &str, format: Format) -> bool {
+        if string.len() != format.encoded_len() {
+            return false;
+        }
+        let (prefix, groups, separator, suffix) = format.layout();
+        let body = match string.get(..prefix.len()) {
+            Some(head) if head.eq_ignore_ascii_case(prefix) && string.ends_with(suffix) => {
+                string.get(prefix.len()..string.len() - suffix.len())
+            },
+            _ => None
+        };
+        let body = match body {
+            Some(body) => body,
+            None => return false
+        };
+        let mut ends = groups.iter()
+                             .scan(0, |end, size| {
+                                 *end += size;
+                                 Some(*end)
+                             })
+                             .take_while(|end| *end < 32)
+                             .peekable();
+        let mut count = 0;
+        for char in body.chars() {
+            if ends.peek() == Some(&count) {
+                if char != separator {
+                    return false;
+                }
+                ends.next();
+            } else if char.is_ascii_hexdigit() && count < 32 {
+                count += 1;
+            } else {
+                return false;
+            }
+        }
+        count == 32
+    }
+
+    /// Compares the ID with a string in any format accepted by `from_str`,
+    /// ignoring the case, without parsing the string into another ID.
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// let id128 = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    /// assert!(id128.equal_str("{01234567-89AB-CDEF-0123-456789ABCDEF}"));
+    /// assert!(!id128.equal_str("0123456789abcdef0123456789abcdee"));
+    /// ```
+    ///
+    /// # Return Values
+    /// - true: the string is a valid representation of this ID
+    /// - false: the string is invalid or represents a different ID
+    pub fn equal_str(&self, string: &str) -> bool {
+        let (bytes, groups) = match unwrapped_digit_groups(string.as_bytes()) {
+            Some(unwrapped) => unwrapped,
+            None => return false
+        };
+        let nibbles = self.ffi
+                          .value
+                          .iter()
+                          .flat_map(|byte| [byte >> 4, byte & 0x0F]);
+        groups.iter()
+              .flat_map(|&(start, length)| &bytes[start..start + length])
+              .zip(nibbles)
+              .all(|(digit, nibble)| HEX_VALUES[*digit as usize] == nibble)
+    }
+
+    /// Checks whether the hexadecimal digits of the ID start with `prefix`,
+    /// e.g. to look up an abbreviated boot ID like journalctl does.
+    ///
+    /// Dashes in `prefix` are ignored and letters are compared ignoring the
+    /// case, so prefixes of the libsystemd, RFC and simple formats match.
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// let id128 = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    /// assert!(id128.matches_prefix("01234567-89AB"));
+    /// assert!(!id128.matches_prefix("1234"));
+    /// ```
+    ///
+    /// # Return Values
+    /// - true: the digits of `prefix` are the leading digits of the ID
+    /// - false: `prefix` differs, contains other characters than hexadecimal
+    ///   digits and dashes or has more than 32 digits
+    pub fn matches_prefix(&self, prefix: &str) -> bool {
+        let mut nibbles = self.ffi
+                              .value
+                              .iter()
+                              .flat_map(|byte| [byte >> 4, byte & 0x0F]);
+        prefix.bytes()
+              .filter(|byte| *byte != b'-')
+              .all(|digit| nibbles.next() == Some(HEX_VALUES[digit as usize]))
+    }
+
+    /// Parses a string into an ID accepting only the given format.
+    ///
+    /// Unlike `from_str`, which accepts any of the formats, the source string
+    /// must comply exactly with `format`, e.g. to enforce the libsystemd
+    /// format of a configuration value. Hexadecimal letters and the prefix of
+    /// `Format::Urn` are accepted in either case. Custom formats are
+    /// supported as well.
+    ///
+    /// ```rust
+    /// use sd_id128::{Format, ID128};
+    /// let source = "01234567-89ab-cdef-0123-456789abcdef";
+    /// assert!(ID128::from_str_format(source, Format::RFC).is_ok());
+    /// assert!(ID128::from_str_format(source, Format::LibSystemD).is_err());
+    /// ```
+    ///
+    /// # Return Values
+    /// - Ok(ID128): success
+    /// - Err(Error::ParseStringError): the source string did not strictly
+    ///   comply with `format`
+    pub fn from_str_format(string: &str, format: Format) -> Result<Self, Error> {
+        ID128::decode_format(string, format).map_err(|error| {
+                                                within(error, string.as_bytes(), &[format])
+                                            })
+    }
+
+    /// Parses a string like `from_str_format` without attaching the input to
+    /// errors.
+    fn decode_format(string: &str, format: Format) -> Result<Self, Error> {
+        if string.len() != format.encoded_len() {
+            return Err(parse_error(ParseProblem::InvalidLength(string.len()), 0));
+        }
+        let (prefix, groups, separator, suffix) = format.layout();
+        let invalid = |position| parse_error(ParseProblem::InvalidCharacter, position);
+        if let Some(position) =
+            string.bytes()
+                  .zip(prefix.bytes())
+                  .position(|(byte, expected)| !byte.eq_ignore_ascii_case(&expected))
+        {
+            return Err(invalid(position));
+        }
+        if !string.ends_with(suffix) {
+            return Err(invalid(string.len() - suffix.len()));
+        }
+        let body = string.get(prefix.len()..string.len() - suffix.len())
+                         .ok_or_else(|| invalid(prefix.len()))?;
+        let mut ends = groups.iter()
+                             .scan(0, |end, size| {
+                                 *end += size;
+                                 Some(*end)
+                             })
+                             .take_while(|end| *end < 32)
+                             .peekable();
+        let mut digits = [0u8; 32];
+        let mut count = 0;
+        for (position, char) in body.char_indices() {
+            let position = position + prefix.len();
+            if ends.peek() == Some(&count) {
+                if char != separator {
+                    return Err(invalid(position));
+                }
+                ends.next();
+            } else if char.is_ascii_hexdigit() && count < digits.len() {
+                digits[count] = char as u8;
+                count += 1;
+            } else {
+                return Err(invalid(position));
+            }
+        }
+        let digits = str::from_utf8(&digits[..count]).map_err(|_| invalid(0))?;
+        ID128::from_str(digits)
     }
 
     /// Parses a string into an ID using libsystemd
@@ -479,6 +1664,7 @@ impl ID128 {
     /// - Ok(ID128): initialized ID128 struct
     /// - Err(Error::NulError): the source string did contain a 0-byte
     /// - Err(Error::SDError): sd-id128 returned an error code
+    #[cfg(feature = "ffi")]
     pub fn from_str_sd(string: &str) -> Result<Self, Error> {
         let string = CString::new(string).map_err(Error::NullError)?;
         let mut id128 = ffi::sd_id128::default();
@@ -512,6 +1698,7 @@ impl ID128 {
     /// - Err(Error::SDError): If this error is reported, it indicates an error
     ///   in libsystemd and/or in this library. The error code is always 0 and
     ///   thus won't reveal any further information.
+    #[cfg(feature = "ffi")]
     pub fn into_cstring_sd(self) -> Result<CString, Error> {
         let c_string = CString::new("0123456789ABCDEF0123456789ABCDEF").map_err(Error::NullError)?;
         let raw = c_string.into_raw();
@@ -547,6 +1734,7 @@ impl ID128 {
     /// - Err(Error::SDError): If this error is reported, it indicates an error
     ///   in libsystemd and/or in this library. The error code is always 0 and
     ///   thus won't reveal any further information.
+    #[cfg(feature = "ffi")]
     pub fn to_string_sd(&self) -> Result<String, Error> {
         let clone = self.clone();
         let c_string = clone.into_cstring_sd()?;
@@ -565,42 +1753,73 @@ impl ID128 {
     /// 01234567-89ab-cdef-0123-456789abcdef. This is the official defined
     /// standard in RFC 4122.
     ///
+    /// The String is allocated once with the exact length of the result, the
+    /// digits are looked up in a table.
+    ///
     /// # Return Values
     /// - String: text representation of the id
     pub fn to_string_formatted(&self, format: Format, case: Case) -> String {
-        self.ffi
-            .value
-            .iter()
-            .enumerate()
-            .map(move |(pos, digit)| {
-                let dash = match format {
-                    Format::Simple => {
-                        if (pos + 1) % 2 == 0 && pos < 15 {
-                            "-"
-                        } else {
-                            ""
-                        }
-                    },
-                    Format::RFC => {
-                        if pos == 3 || pos == 5 || pos == 7 || pos == 9 {
-                            "-"
-                        } else {
-                            ""
-                        }
-                    },
-                    Format::LibSystemD => ""
-                };
-                match case {
-                    Case::Lower => format!("{:02x}{}", digit, dash),
-                    Case::Upper => format!("{:02X}{}", digit, dash)
-                }
-            })
-            .collect::<String>()
+        let mut string = String::with_capacity(format.encoded_len());
+        let _ = self.write_formatted(&mut string, format, case);
+        string
+    }
+
+    /// Returns the 32 hexadecimal digits of the ID.
+    fn hex_digits(&self, case: Case) -> [u8; 32] {
+        #[cfg(feature = "simd")]
+        if let Some(hex) = simd::encode(&self.ffi.value, case == Case::Upper) {
+            return hex;
+        }
+        let digits = match case {
+            Case::Lower => b"0123456789abcdef",
+            Case::Upper => b"0123456789ABCDEF"
+        };
+        let mut hex = [0u8; 32];
+        for (position, byte) in self.ffi.value.iter().enumerate() {
+            hex[2 * position] = digits[(byte >> 4) as usize];
+            hex[2 * position + 1] = digits[(byte & 0x0F) as usize];
+        }
+        hex
+    }
+
+    /// Writes the ID in `format` and `case` to `writer` without allocating.
+    pub(crate) fn write_formatted<W: fmt::Write + ?Sized>(&self,
+                                                          writer: &mut W,
+                                                          format: Format,
+                                                          case: Case)
+                                                          -> fmt::Result {
+        let (prefix, groups, separator, suffix) = format.layout();
+        let hex = self.hex_digits(case);
+        let digits = str::from_utf8(&hex).map_err(|_| fmt::Error)?;
+        writer.write_str(prefix)?;
+        let mut start = 0;
+        let mut end = 0;
+        for size in groups {
+            end += size;
+            if end >= digits.len() {
+                break;
+            }
+            writer.write_str(&digits[start..end])?;
+            writer.write_char(separator)?;
+            start = end;
+        }
+        writer.write_str(&digits[start..])?;
+        writer.write_str(suffix)
+    }
+
+    /// Formats an ID as String according to `options`.
+    pub fn to_string_with(&self, options: &FormatOptions) -> String {
+        let id128 = self.to_string_formatted(options.format, options.case);
+        match options.prefix {
+            Some(ref prefix) => prefix.clone() + &id128,
+            None => id128
+        }
     }
 
     /// Transform an ID128 into a FFI binding sd_id128.
     ///
     /// The FFI binding struct sd_id128 is only required for direct FFI calls.
+    #[cfg(feature = "ffi")]
     pub fn into_ffi(self) -> ffi::sd_id128 {
         self.ffi
     }
@@ -608,6 +1827,7 @@ impl ID128 {
     /// Returns a reference to the inner FFI binding sd_id128.
     ///
     /// The FFI binding struct sd_id128 is only required for direct FFI calls.
+    #[cfg(feature = "ffi")]
     pub fn as_ffi(&self) -> &ffi::sd_id128 {
         &self.ffi
     }
@@ -616,6 +1836,7 @@ impl ID128 {
     ///
     /// The FFI binding struct sd_id128 retrieved from a direct FFI call may be
     /// used to construct a full ID128.
+    #[cfg(feature = "ffi")]
     pub fn from_ffi(ffi: ffi::sd_id128) -> ID128 {
         ID128 { ffi }
     }
@@ -636,7 +1857,76 @@ impl ID128 {
     }
 
     /// Constructs an ID128 from a raw value slice.
-    pub fn from_raw_value(value: [u8; 16]) -> ID128 {
+    pub const fn from_raw_value(value: [u8; 16]) -> ID128 {
         ID128 { ffi: ffi::sd_id128 { value } }
     }
+
+    /// Parses an ID in const contexts, so well-known IDs can be declared as
+    /// const or static items and used in match patterns.
+    ///
+    /// The ID is accepted in simple, libsystemd, RFC, URN or braced format in
+    /// any case. Custom formats are not supported.
+    ///
+    /// # Panics
+    /// Panics if the string is no valid ID, which fails the build when
+    /// evaluated in a const context. Use `from_str` for input at runtime.
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// const UNIT_FAILED: ID128 = ID128::from_str_const("be02cf68-55d2-428b-a40d-f7e9d022f03d");
+    /// match ID128::from_str("be02cf6855d2428ba40df7e9d022f03d").unwrap() {
+    ///     UNIT_FAILED => {},
+    ///     ID128::NULL => panic!("null ID"),
+    ///     _ => panic!("unknown ID")
+    /// }
+    /// ```
+    pub const fn from_str_const(string: &str) -> ID128 {
+        const HEX: [u8; 256] = hex_values();
+        const SIMPLE_DASHES: &[usize] = &[4, 9, 14, 19, 24, 29, 34];
+        const RFC_DASHES: &[usize] = &[8, 13, 18, 23];
+        let bytes = string.as_bytes();
+        let (start, dashes) = match bytes.len() {
+            32 => (0, &[] as &[usize]),
+            36 => (0, RFC_DASHES),
+            38 if bytes[0] == b'{' && bytes[37] == b'}' => (1, RFC_DASHES),
+            39 => (0, SIMPLE_DASHES),
+            45 => {
+                let prefix = URN_PREFIX.as_bytes();
+                let mut index = 0;
+                while index < prefix.len() {
+                    if bytes[index].to_ascii_lowercase() != prefix[index] {
+                        panic!("invalid ID: unknown prefix");
+                    }
+                    index += 1;
+                }
+                (prefix.len(), RFC_DASHES)
+            },
+            _ => panic!("invalid ID: invalid length")
+        };
+        let mut value = [0u8; 16];
+        let (mut position, mut digit, mut dash) = (start, 0, 0);
+        while digit < 32 {
+            if dash < dashes.len() && position - start == dashes[dash] {
+                if bytes[position] != b'-' {
+                    panic!("invalid ID: missing dash");
+                }
+                position += 1;
+                dash += 1;
+            }
+            let nibble = HEX[bytes[position] as usize];
+            if nibble > 15 {
+                panic!("invalid ID: invalid character");
+            }
+            value[digit / 2] |= nibble << (4 * (1 - digit % 2));
+            position += 1;
+            digit += 1;
+        }
+        ID128::from_raw_value(value)
+    }
+
+    /// Constructs an ID128 from its big endian numeric value, e.g. for the
+    /// constants of well-known IDs.
+    pub(crate) const fn from_u128(value: u128) -> ID128 {
+        ID128 { ffi: ffi::sd_id128 { value: value.to_be_bytes() } }
+    }
 }
@@ -0,0 +1,104 @@
+// sd-id128: host identity on macOS
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{Error, ID128};
+use libc::{c_char, c_void};
+use std::{ffi::CStr, ptr};
+
+type CFTypeRef = *const c_void;
+type CFStringRef = *const c_void;
+type CFAllocatorRef = *const c_void;
+type CFDictionaryRef = *mut c_void;
+type IoObject = u32;
+
+// kIOMainPortDefault (formerly kIOMasterPortDefault) is MACH_PORT_NULL
+const IO_MAIN_PORT_DEFAULT: u32 = 0;
+const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOServiceMatching(name: *const c_char) -> CFDictionaryRef;
+    fn IOServiceGetMatchingService(main_port: u32, matching: CFDictionaryRef) -> IoObject;
+    fn IORegistryEntryCreateCFProperty(entry: IoObject,
+                                       key: CFStringRef,
+                                       allocator: CFAllocatorRef,
+                                       options: u32)
+                                       -> CFTypeRef;
+    fn IOObjectRelease(object: IoObject) -> i32;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFStringCreateWithCString(allocator: CFAllocatorRef,
+                                 string: *const c_char,
+                                 encoding: u32)
+                                 -> CFStringRef;
+    fn CFStringGetCString(string: CFStringRef,
+                          buffer: *mut c_char,
+                          size: isize,
+                          encoding: u32)
+                          -> u8;
+    fn CFGetTypeID(object: CFTypeRef) -> usize;
+    fn CFStringGetTypeID() -> usize;
+    fn CFRelease(object: CFTypeRef);
+}
+
+impl ID128 {
+    /// Returns the platform UUID of a Mac.
+    ///
+    /// The `IOPlatformUUID` property of the `IOPlatformExpertDevice` is read
+    /// via IOKit. This is the hardware UUID shown in the system information,
+    /// which is the closest counterpart of the machine ID of systemd.
+    ///
+    /// This function is only available on macOS with the feature `portable`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(-ENOENT)): the platform expert or its UUID are not
+    ///   available
+    /// - Err(Error::ParseStringError): the property is no valid UUID
+    pub fn macos_platform_uuid() -> Result<Self, Error> {
+        let mut buffer = [0 as c_char; 64];
+        unsafe {
+            // IOServiceGetMatchingService consumes the matching dictionary
+            let matching = IOServiceMatching(b"IOPlatformExpertDevice\0".as_ptr() as *const c_char);
+            let service = IOServiceGetMatchingService(IO_MAIN_PORT_DEFAULT, matching);
+            if service == 0 {
+                return Err(Error::SDError(-libc::ENOENT));
+            }
+            let key = CFStringCreateWithCString(ptr::null(),
+                                                b"IOPlatformUUID\0".as_ptr() as *const c_char,
+                                                CF_STRING_ENCODING_UTF8);
+            let property = IORegistryEntryCreateCFProperty(service, key, ptr::null(), 0);
+            CFRelease(key);
+            IOObjectRelease(service);
+            if property.is_null() {
+                return Err(Error::SDError(-libc::ENOENT));
+            }
+            let copied = CFGetTypeID(property) == CFStringGetTypeID()
+                         && CFStringGetCString(property,
+                                               buffer.as_mut_ptr(),
+                                               buffer.len() as isize,
+                                               CF_STRING_ENCODING_UTF8)
+                            != 0;
+            CFRelease(property);
+            if !copied {
+                return Err(Error::SDError(-libc::ENOENT));
+            }
+        }
+        let uuid = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        ID128::from_str(&uuid.to_string_lossy())
+    }
+}
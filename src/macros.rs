@@ -0,0 +1,93 @@
+// sd-id128: declaration macros
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Declares the application ID of a crate once and generates helpers using
+/// it.
+///
+/// The macro generates the following functions in the module it is invoked
+/// in:
+/// - `app_id() -> ID128`: the declared application ID
+/// - `machine_id_app_specific() -> Result<ID128, Error>`: the app specific
+///   machine ID, requires feature 233 or 240
+/// - `boot_id_app_specific() -> Result<ID128, Error>`: the app specific boot
+///   ID, requires feature 240
+///
+/// This avoids passing a wrong application ID at one of many call sites. The
+/// ID may be given in any format accepted by `ID128::from_str`. An invalid ID
+/// panics on first use.
+///
+/// ```rust
+/// mod identity {
+///     sd_id128::app_id!("4e7290a0-a9a1-4f3f-b7bb-f5a05b46f1ad");
+/// }
+/// let app = identity::app_id();
+/// # #[cfg(systemd_v233)]
+/// let machine = identity::machine_id_app_specific().unwrap();
+/// ```
+#[macro_export]
+macro_rules! app_id {
+    ($id:literal) => {
+        /// Returns the application ID.
+        pub fn app_id() -> $crate::ID128 {
+            $crate::ID128::from_str($id).expect("invalid application ID")
+        }
+
+        $crate::__app_id_machine!(app_id);
+        $crate::__app_id_boot!(app_id);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(any(systemd_v233,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+macro_rules! __app_id_machine {
+    ($app_id:ident) => {
+        /// Returns the machine ID hashed with the application ID.
+        pub fn machine_id_app_specific() -> ::std::result::Result<$crate::ID128, $crate::Error> {
+            $crate::ID128::machine_id_app_specific($app_id())
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(any(systemd_v233,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))))]
+macro_rules! __app_id_machine {
+    ($app_id:ident) => {};
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(any(systemd_v240,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+macro_rules! __app_id_boot {
+    ($app_id:ident) => {
+        /// Returns the boot ID hashed with the application ID.
+        pub fn boot_id_app_specific() -> ::std::result::Result<$crate::ID128, $crate::Error> {
+            $crate::ID128::boot_id_app_specific($app_id())
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(any(systemd_v240,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))))]
+macro_rules! __app_id_boot {
+    ($app_id:ident) => {};
+}
@@ -0,0 +1,316 @@
+// sd-id128: detection of reboots and restarts
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Markers persisting IDs in state files to detect what happened since the
+//! previous start of a program.
+//!
+//! Crash-loop detection and update orchestration need to know whether the
+//! system rebooted since the program ran last. A `BootMarker` stores the boot
+//! ID in a state file, e.g. below `/var/lib/<program>` (`StateDirectory=`),
+//! and compares it on the next start. Likewise, an `InvocationMarker` tells a
+//! fresh start of a service from a restart within the same boot, and a
+//! `MachineMarker` detects a changed machine ID, e.g. of a cloned VM.
+//!
+//! ```rust
+//! use sd_id128::marker::{BootMarker, BootState};
+//! let path = std::env::temp_dir().join(format!("sd-id128-doc-boot-{}", std::process::id()));
+//! let marker = BootMarker::new(&path);
+//! if let BootState::Rebooted(previous) = marker.check().unwrap() {
+//!     println!("rebooted since boot {}", previous);
+//! }
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+use crate::{errno, from_io,
+            id_file::{self, Content},
+            persistent::replace_atomic,
+            Case, Error, Format, ID128};
+use std::{fs, io,
+          path::{Path, PathBuf}};
+
+/// Reads the ID stored in a marker file, a missing file is no error.
+fn read_marker(path: &Path) -> Result<Option<ID128>, Error> {
+    match id_file::read(path) {
+        Ok(Content::Id(id128)) => Ok(Some(id128)),
+        Ok(_) => Ok(None),
+        Err(Error::SDError(errno)) if errno == -libc::ENOENT => Ok(None),
+        Err(error) => Err(error)
+    }
+}
+
+/// Result of `BootMarker::check`.
+///
+/// Variants:
+/// - FirstStart: no boot ID was recorded before
+/// - SameBoot: the system did not reboot since the boot ID was recorded
+/// - Rebooted: the system rebooted, the previously recorded boot ID is given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BootState {
+    FirstStart,
+    SameBoot,
+    Rebooted(ID128)
+}
+
+/// Boot ID persisted in a state file.
+///
+/// The file is written in the format of `/etc/machine-id` via `id_file`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootMarker {
+    path: PathBuf
+}
+
+impl BootMarker {
+    /// Creates a marker stored in `path`. The file is not accessed until
+    /// `check` is called.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        BootMarker { path: path.as_ref().to_path_buf() }
+    }
+
+    /// Returns the path of the state file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Compares the recorded boot ID with the current one and records the
+    /// current boot ID.
+    ///
+    /// # Return Values
+    /// - Ok(BootState): whether the system rebooted since the last check
+    /// - Err(Error::SDError(i32)): the boot ID could not be retrieved or the
+    ///   file could not be read or written, the code is the negative errno
+    pub fn check(&self) -> Result<BootState, Error> {
+        let current = ID128::boot_id()?;
+        let state = match read_marker(&self.path)? {
+            Some(previous) if previous == current => return Ok(BootState::SameBoot),
+            Some(previous) => BootState::Rebooted(previous),
+            None => BootState::FirstStart
+        };
+        id_file::write(&self.path, &Content::Id(current))?;
+        Ok(state)
+    }
+}
+
+/// Result of `InvocationMarker::check`.
+///
+/// Variants:
+/// - FreshStart: no invocation was recorded within the current boot
+/// - Restarted: the service was restarted within the current boot; the
+///   previously recorded invocation ID and the number of restarts within the
+///   current boot, including this one, are given
+/// - SameInvocation: the current invocation was recorded already
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvocationState {
+    FreshStart,
+    Restarted { previous: ID128, restarts: u64 },
+    SameInvocation
+}
+
+/// Invocation ID persisted in a state file.
+///
+/// systemd assigns a new invocation ID on every start of a unit, including
+/// restarts triggered by `Restart=`. The marker records the boot ID, the
+/// invocation ID and the number of restarts in the current boot, so a service
+/// can tell a fresh start from a restart, e.g. to back off after repeated
+/// failures. Note that manual restarts via `systemctl restart` are counted as
+/// well, since they cannot be told apart from automatic ones.
+///
+/// The file contains one `KEY=value` assignment per line: `BOOT_ID`,
+/// `INVOCATION_ID` and `RESTARTS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvocationMarker {
+    path: PathBuf
+}
+
+/// State recorded by an `InvocationMarker`.
+struct Record {
+    boot:       ID128,
+    invocation: ID128,
+    restarts:   u64
+}
+
+impl Record {
+    fn parse(content: &str) -> Option<Record> {
+        let (mut boot, mut invocation, mut restarts) = (None, None, None);
+        for line in content.lines() {
+            match line.split_once('=')? {
+                ("BOOT_ID", value) => boot = ID128::from_str(value).ok(),
+                ("INVOCATION_ID", value) => invocation = ID128::from_str(value).ok(),
+                ("RESTARTS", value) => restarts = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(Record { boot:       boot?,
+                      invocation: invocation?,
+                      restarts:   restarts? })
+    }
+
+    fn format(&self) -> String {
+        format!("BOOT_ID={}\nINVOCATION_ID={}\nRESTARTS={}\n",
+                self.boot
+                    .to_string_formatted(Format::LibSystemD, Case::Lower),
+                self.invocation
+                    .to_string_formatted(Format::LibSystemD, Case::Lower),
+                self.restarts)
+    }
+}
+
+impl InvocationMarker {
+    /// Creates a marker stored in `path`. The file is not accessed until
+    /// `check` is called.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        InvocationMarker { path: path.as_ref().to_path_buf() }
+    }
+
+    /// Returns the path of the state file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Compares the recorded invocation with the current one and records the
+    /// current invocation.
+    ///
+    /// The invocation ID is read from `$INVOCATION_ID` via
+    /// `ID128::invocation_id_from_env`.
+    ///
+    /// # Return Values
+    /// - Ok(InvocationState): whether the service was restarted
+    /// - Err(Error::NotUnderSystemd): the process was not started by systemd
+    /// - Err(Error::SDError(-EUCLEAN)): the file is no valid marker
+    /// - Err(Error::SDError(i32)): the boot ID could not be retrieved or the
+    ///   file could not be read or written, the code is the negative errno
+    pub fn check(&self) -> Result<InvocationState, Error> {
+        let invocation = ID128::invocation_id_from_env()?;
+        let boot = ID128::boot_id()?;
+        let previous = match fs::read_to_string(&self.path) {
+            Ok(content) => Some(Record::parse(&content).ok_or(Error::SDError(-errno::EUCLEAN))?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(from_io(error))
+        };
+        let (state, restarts) = match previous {
+            Some(record) if record.boot != boot => (InvocationState::FreshStart, 0),
+            Some(record) if record.invocation == invocation => {
+                return Ok(InvocationState::SameInvocation)
+            },
+            Some(record) => {
+                let restarts = record.restarts + 1;
+                (InvocationState::Restarted { previous: record.invocation,
+                                              restarts },
+                 restarts)
+            },
+            None => (InvocationState::FreshStart, 0)
+        };
+        let record = Record { boot,
+                              invocation,
+                              restarts };
+        replace_atomic(&self.path, record.format().as_bytes(), 0o644).map_err(from_io)?;
+        Ok(state)
+    }
+}
+
+/// Result of `MachineMarker::check`.
+///
+/// Variants:
+/// - FirstStart: no fingerprint was recorded before
+/// - Unchanged: the machine ID matches the recorded fingerprint
+/// - Changed: the machine ID differs from the recorded one, e.g. because the
+///   machine was cloned from an image without resetting `/etc/machine-id`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineState {
+    FirstStart,
+    Unchanged,
+    Changed
+}
+
+/// Salted fingerprint of the machine ID persisted in a state file.
+///
+/// Agents registering a machine with a central service need to register anew
+/// if their state was copied along with the whole disk to another machine.
+/// Storing the machine ID itself in the state would leak it to everybody able
+/// to read the state, so the marker stores a random salt and the fingerprint
+/// `ID128::app_specific_native(machine_id, salt)` instead.
+///
+/// The file contains one `KEY=value` assignment per line: `SALT` and
+/// `FINGERPRINT`.
+///
+/// This is only available with the feature `crypto`.
+#[cfg(feature = "crypto")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineMarker {
+    path: PathBuf
+}
+
+#[cfg(feature = "crypto")]
+impl MachineMarker {
+    /// Creates a marker stored in `path`. The file is not accessed until
+    /// `check` is called.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        MachineMarker { path: path.as_ref().to_path_buf() }
+    }
+
+    /// Returns the path of the state file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Compares the machine ID with the recorded fingerprint, see `check_id`.
+    pub fn check(&self) -> Result<MachineState, Error> {
+        self.check_id(&ID128::machine_id()?)
+    }
+
+    /// Compares an arbitrary host identity, e.g. of `host_id`, with the
+    /// recorded fingerprint. Unless it is unchanged, a new fingerprint is
+    /// recorded with a new salt.
+    ///
+    /// # Return Values
+    /// - Ok(MachineState): whether the identity changed
+    /// - Err(Error::SDError(-EUCLEAN)): the file is no valid marker
+    /// - Err(Error::SDError(i32)): the file could not be read or written, the
+    ///   code is the negative errno
+    pub fn check_id(&self, current: &ID128) -> Result<MachineState, Error> {
+        let state = match fs::read_to_string(&self.path) {
+            Ok(content) => {
+                let (salt, fingerprint) =
+                    parse_fingerprint(&content).ok_or(Error::SDError(-errno::EUCLEAN))?;
+                if current.derive(&salt) == fingerprint {
+                    return Ok(MachineState::Unchanged);
+                }
+                MachineState::Changed
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => MachineState::FirstStart,
+            Err(error) => return Err(from_io(error))
+        };
+        let salt = ID128::random_id()?;
+        let content = format!("SALT={}\nFINGERPRINT={}\n",
+                              salt.to_string_formatted(Format::LibSystemD, Case::Lower),
+                              current.derive(&salt)
+                                     .to_string_formatted(Format::LibSystemD, Case::Lower));
+        replace_atomic(&self.path, content.as_bytes(), 0o600).map_err(from_io)?;
+        Ok(state)
+    }
+}
+
+/// Parses the salt and the fingerprint recorded by a `MachineMarker`.
+#[cfg(feature = "crypto")]
+fn parse_fingerprint(content: &str) -> Option<(ID128, ID128)> {
+    let (mut salt, mut fingerprint) = (None, None);
+    for line in content.lines() {
+        match line.split_once('=')? {
+            ("SALT", value) => salt = ID128::from_str(value).ok(),
+            ("FINGERPRINT", value) => fingerprint = ID128::from_str(value).ok(),
+            _ => {}
+        }
+    }
+    Some((salt?, fingerprint?))
+}
@@ -0,0 +1,235 @@
+// sd-id128: well-known message IDs of the journal
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//! Message IDs of the journal declared in `<systemd/sd-messages.h>`.
+//!
+//! systemd attaches these IDs as field `MESSAGE_ID=` to log messages of
+//! well-known events, e.g. to look up the explanation in the message catalog
+//! or to filter the journal like `journalctl MESSAGE_ID=...`. `MessageId`
+//! wraps an ID with the name and description of well-known IDs. With the
+//! feature `codegen`, the IDs of the installed systemd which are missing in
+//! `MESSAGE_IDS` are well-known as well, by name only.
+//!
+//! ```rust
+//! use sd_id128::messages;
+//! assert_eq!(format!("{:x}", messages::UNIT_FAILED),
+//!            "be02cf6855d2428ba40df7e9d022f03d");
+//! ```
+use crate::{AsId128, ID128};
+use std::fmt;
+
+/// The journal was started.
+pub const JOURNAL_START: ID128 = ID128::from_u128(0xf77379a8490b408bbe5f6940505a777b);
+/// The journal was stopped.
+pub const JOURNAL_STOP: ID128 = ID128::from_u128(0xd93fb3c9c24d451a97cea615ce59c00b);
+/// The journal dropped messages due to rate limiting.
+pub const JOURNAL_DROPPED: ID128 = ID128::from_u128(0xa596d6fe7bfa4994828e72309e95d61e);
+/// The journal missed kernel messages.
+pub const JOURNAL_MISSED: ID128 = ID128::from_u128(0xe9bf28e6e834481bb6f48f548ad13606);
+/// Messages could not be forwarded to syslog.
+pub const FORWARD_SYSLOG_MISSED: ID128 = ID128::from_u128(0x0027229ca0644181a76c4e92458afa2e);
+/// A process dumped core.
+pub const COREDUMP: ID128 = ID128::from_u128(0xfc2e22bc6ee647b6b90729ab34a250b1);
+/// A login session was started.
+pub const SESSION_START: ID128 = ID128::from_u128(0x8d45620c1a4348dbb17410da57c60c66);
+/// A login session was stopped.
+pub const SESSION_STOP: ID128 = ID128::from_u128(0x3354939424b4456d9802ca8333ed424a);
+/// A seat was started.
+pub const SEAT_START: ID128 = ID128::from_u128(0xfcbefc5da23d428093f97c82a9290f7b);
+/// A seat was stopped.
+pub const SEAT_STOP: ID128 = ID128::from_u128(0xe7852bfe46784ed0accde04bc864c2d5);
+/// The system clock was changed.
+pub const TIME_CHANGE: ID128 = ID128::from_u128(0xc7a787079b354eaaa9e77b371893cd27);
+/// The time zone was changed.
+pub const TIMEZONE_CHANGE: ID128 = ID128::from_u128(0x45f82f4aef7a4bbf942ce861d1f20990);
+/// The system finished starting up.
+pub const STARTUP_FINISHED: ID128 = ID128::from_u128(0xb07a249cd024414a82dd00cd181378ff);
+/// A user service manager finished starting up.
+pub const USER_STARTUP_FINISHED: ID128 = ID128::from_u128(0xeed00a68ffd84e31882105fd973abdd1);
+/// The system is entering a sleep state.
+pub const SLEEP_START: ID128 = ID128::from_u128(0x6bbd95ee977941e497c48be27c254128);
+/// The system left a sleep state.
+pub const SLEEP_STOP: ID128 = ID128::from_u128(0x8811e6df2a8e40f58a94cea26f8ebf14);
+/// The system is shutting down.
+pub const SHUTDOWN: ID128 = ID128::from_u128(0x98268866d1d54a499c4e98921d93bc40);
+/// A unit is starting.
+pub const UNIT_STARTING: ID128 = ID128::from_u128(0x7d4958e842da4a758f6c1cdc7b36dcc5);
+/// A unit has started.
+pub const UNIT_STARTED: ID128 = ID128::from_u128(0x39f53479d3a045ac8e11786248231fbf);
+/// A unit has finished successfully.
+pub const UNIT_SUCCESS: ID128 = ID128::from_u128(0x7ad2d189f7e94e70a38c781354912448);
+/// A unit has failed.
+pub const UNIT_FAILED: ID128 = ID128::from_u128(0xbe02cf6855d2428ba40df7e9d022f03d);
+/// A unit is stopping.
+pub const UNIT_STOPPING: ID128 = ID128::from_u128(0xde5b426a63be47a7b6ac3eaac82e2f6f);
+/// A unit has stopped.
+pub const UNIT_STOPPED: ID128 = ID128::from_u128(0x9d1aaa27d60140bd96365438aad20286);
+/// A unit is reloading.
+pub const UNIT_RELOADING: ID128 = ID128::from_u128(0xd34d037fff1847e6ae669a370e694725);
+/// A unit has reloaded.
+pub const UNIT_RELOADED: ID128 = ID128::from_u128(0x7b05ebc668384222baa8881179cfda54);
+/// A process of a unit has exited.
+pub const UNIT_PROCESS_EXIT: ID128 = ID128::from_u128(0x98e322203f7a4ed290d09fe03c09fe15);
+/// The resources consumed by a unit.
+pub const UNIT_RESOURCES: ID128 = ID128::from_u128(0xae8f7b866b0347b9af31fe1c80b127c0);
+/// A process of a unit could not be spawned.
+pub const SPAWN_FAILED: ID128 = ID128::from_u128(0x641257651c1b4ec9a8624d7a40a9e1e7);
+/// A unit configuration file contains an error.
+pub const CONFIG_ERROR: ID128 = ID128::from_u128(0xc772d24e9a884cbeb9ea12625c306c01);
+/// A mount point is not empty and is overmounted.
+pub const OVERMOUNTING: ID128 = ID128::from_u128(0x1dee0369c7fc4736b7099b38ecb46ee7);
+
+/// All message IDs of this module by name, with their description.
+pub const MESSAGE_IDS: &[(&str, ID128, &str)] =
+    &[("journal-start", JOURNAL_START, "The journal was started"),
+      ("journal-stop", JOURNAL_STOP, "The journal was stopped"),
+      ("journal-dropped", JOURNAL_DROPPED, "The journal dropped messages due to rate limiting"),
+      ("journal-missed", JOURNAL_MISSED, "The journal missed kernel messages"),
+      ("forward-syslog-missed",
+       FORWARD_SYSLOG_MISSED,
+       "Messages could not be forwarded to syslog"),
+      ("coredump", COREDUMP, "A process dumped core"),
+      ("session-start", SESSION_START, "A login session was started"),
+      ("session-stop", SESSION_STOP, "A login session was stopped"),
+      ("seat-start", SEAT_START, "A seat was started"),
+      ("seat-stop", SEAT_STOP, "A seat was stopped"),
+      ("time-change", TIME_CHANGE, "The system clock was changed"),
+      ("timezone-change", TIMEZONE_CHANGE, "The time zone was changed"),
+      ("startup-finished", STARTUP_FINISHED, "The system finished starting up"),
+      ("user-startup-finished",
+       USER_STARTUP_FINISHED,
+       "A user service manager finished starting up"),
+      ("sleep-start", SLEEP_START, "The system is entering a sleep state"),
+      ("sleep-stop", SLEEP_STOP, "The system left a sleep state"),
+      ("shutdown", SHUTDOWN, "The system is shutting down"),
+      ("unit-starting", UNIT_STARTING, "A unit is starting"),
+      ("unit-started", UNIT_STARTED, "A unit has started"),
+      ("unit-success", UNIT_SUCCESS, "A unit has finished successfully"),
+      ("unit-failed", UNIT_FAILED, "A unit has failed"),
+      ("unit-stopping", UNIT_STOPPING, "A unit is stopping"),
+      ("unit-stopped", UNIT_STOPPED, "A unit has stopped"),
+      ("unit-reloading", UNIT_RELOADING, "A unit is reloading"),
+      ("unit-reloaded", UNIT_RELOADED, "A unit has reloaded"),
+      ("unit-process-exit", UNIT_PROCESS_EXIT, "A process of a unit has exited"),
+      ("unit-resources", UNIT_RESOURCES, "The resources consumed by a unit"),
+      ("spawn-failed", SPAWN_FAILED, "A process of a unit could not be spawned"),
+      ("config-error", CONFIG_ERROR, "A unit configuration file contains an error"),
+      ("overmounting", OVERMOUNTING, "A mount point is not empty and is overmounted")];
+
+/// Message ID of the journal with the name and description of well-known IDs,
+/// e.g. to explain the field `MESSAGE_ID=` in the output of log tools.
+///
+/// The display shows the ID in libsystemd format followed by name and
+/// description if the ID is well-known, by the name only if there is no
+/// description.
+///
+/// ```rust
+/// use sd_id128::{messages::{self, MessageId},
+///                ID128};
+/// let message_id = MessageId::new(messages::UNIT_FAILED);
+/// assert_eq!(message_id.name(), Some("unit-failed"));
+/// assert_eq!(message_id.to_string(),
+///            "be02cf6855d2428ba40df7e9d022f03d (unit-failed: A unit has failed)");
+/// let unknown = MessageId::new(ID128::from_str("0123456789abcdef0123456789abcdef").unwrap());
+/// assert_eq!(unknown.to_string(), "0123456789abcdef0123456789abcdef");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageId {
+    id:          ID128,
+    name:        Option<&'static str>,
+    description: Option<&'static str>
+}
+
+impl MessageId {
+    /// Wraps an ID, looking up its name and description in `MESSAGE_IDS`. With
+    /// the feature `codegen`, other IDs get their name from
+    /// `SYSTEMD_MESSAGE_IDS`.
+    pub fn new(id: ID128) -> Self {
+        let (name, description) = match MESSAGE_IDS.iter().find(|(_, known, _)| *known == id) {
+            Some((name, _, description)) => (Some(*name), Some(*description)),
+            None => (generated_name(&id), None)
+        };
+        MessageId { id,
+                    name,
+                    description }
+    }
+
+    /// Returns the wrapped ID.
+    pub fn id(&self) -> &ID128 {
+        &self.id
+    }
+
+    /// Returns the name of a well-known ID, e.g. "unit-failed".
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// Returns the description of a well-known ID, e.g. "A unit has failed".
+    /// IDs known from `SYSTEMD_MESSAGE_IDS` only have no description.
+    pub fn description(&self) -> Option<&'static str> {
+        self.description
+    }
+
+    /// Returns true if the ID is one of `MESSAGE_IDS` or, with the feature
+    /// `codegen`, of `SYSTEMD_MESSAGE_IDS`.
+    pub fn is_well_known(&self) -> bool {
+        self.name.is_some()
+    }
+}
+
+/// Returns the name of an ID generated from the installed headers.
+#[cfg(feature = "codegen")]
+fn generated_name(id: &ID128) -> Option<&'static str> {
+    SYSTEMD_MESSAGE_IDS.iter()
+                       .find(|(_, known)| known == id)
+                       .map(|(name, _)| *name)
+}
+
+#[cfg(not(feature = "codegen"))]
+fn generated_name(_id: &ID128) -> Option<&'static str> {
+    None
+}
+
+impl From<ID128> for MessageId {
+    fn from(id: ID128) -> Self {
+        MessageId::new(id)
+    }
+}
+
+impl AsId128 for MessageId {
+    fn as_id128(&self) -> ID128 {
+        self.id.clone()
+    }
+}
+
+impl From<MessageId> for ID128 {
+    fn from(message_id: MessageId) -> Self {
+        message_id.id
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{:#}", self.id)?;
+        match (self.name(), self.description()) {
+            (Some(name), Some(description)) => write!(formatter, " ({}: {})", name, description),
+            (Some(name), None) => write!(formatter, " ({})", name),
+            _ => Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "codegen")]
+include!(concat!(env!("OUT_DIR"), "/messages.rs"));
@@ -0,0 +1,260 @@
+// sd-id128: pure Rust implementation of the libsystemd API
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// The functions mirror their libsystemd counterparts including the negative
+// errno codes reported on failure.
+#[cfg(any(not(feature = "ffi"), feature = "no-ffi"))]
+use crate::{entropy, uuid::set_version};
+use crate::{errno, from_io,
+            id_file::{self, Content},
+            Error, ParseError, ParseProblem, ID128};
+use std::{env::{self, VarError},
+          fs,
+          path::Path,
+          str};
+
+pub(crate) const MACHINE_ID_PATH: &str = "/etc/machine-id";
+pub(crate) const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+/// Parses an ID in plain (libsystemd) or RFC format like
+/// `sd_id128_from_string()` does.
+pub(crate) fn parse_plain_or_rfc(string: &str) -> Option<[u8; 16]> {
+    match string.len() {
+        32 | 36 => ID128::from_str(string).ok().map(ID128::into_raw_value),
+        _ => None
+    }
+}
+
+/// Reads an ID file like `/etc/machine-id`.
+///
+/// Errors:
+/// - ENOMEDIUM: the file is empty or contains the null ID
+/// - ENOPKG: the file contains the "uninitialized" marker
+/// - EUCLEAN: the file contains anything else but an ID
+pub(crate) fn read_id_file(path: &Path) -> Result<[u8; 16], Error> {
+    match id_file::read(path)? {
+        Content::Id(id128) => Ok(id128.into_raw_value()),
+        Content::Uninitialized => Err(Error::SDError(-errno::ENOPKG)),
+        Content::Empty => Err(Error::SDError(-errno::ENOMEDIUM))
+    }
+}
+
+/// Reads the machine ID from `/etc/machine-id`.
+#[cfg(any(not(feature = "ffi"), feature = "no-ffi"))]
+pub(crate) fn get_machine() -> Result<[u8; 16], Error> {
+    read_id_file(Path::new(MACHINE_ID_PATH))
+}
+
+/// Reads the boot ID from `/proc/sys/kernel/random/boot_id`.
+pub(crate) fn get_boot() -> Result<[u8; 16], Error> {
+    read_id_file(Path::new(BOOT_ID_PATH))
+}
+
+/// Reads the invocation ID from `$INVOCATION_ID`.
+///
+/// Errors:
+/// - ENXIO: the variable is not set or contains the null ID
+/// - EINVAL: the variable does not contain an ID
+#[cfg(any(not(feature = "ffi"), feature = "no-ffi"))]
+pub(crate) fn get_invocation() -> Result<[u8; 16], Error> {
+    let variable = env::var_os("INVOCATION_ID").ok_or(Error::SDError(-libc::ENXIO))?;
+    let value = variable.to_str()
+                        .and_then(parse_plain_or_rfc)
+                        .ok_or(Error::SDError(-libc::EINVAL))?;
+    if value == [0u8; 16] {
+        return Err(Error::SDError(-libc::ENXIO));
+    }
+    Ok(value)
+}
+
+/// Generates a random UUID v4-compatible ID from the kernel random number
+/// generator.
+#[cfg(any(not(feature = "ffi"), feature = "no-ffi"))]
+pub(crate) fn randomize() -> Result<[u8; 16], Error> {
+    let mut value = [0u8; 16];
+    entropy::fill(&mut value)?;
+    set_version(&mut value, 4);
+    Ok(value)
+}
+
+/// Returns a variable of the environment of PID 1, if permitted.
+fn environment_of_init(name: &str) -> Option<String> {
+    let environ = fs::read("/proc/1/environ").ok()?;
+    environ.split(|byte| *byte == 0)
+           .filter_map(|entry| str::from_utf8(entry).ok())
+           .find_map(|entry| entry.strip_prefix(name)?.strip_prefix('='))
+           .map(String::from)
+}
+
+impl ID128 {
+    /// Returns the machine ID of an alternative root directory.
+    ///
+    /// This reads and parses `<root>/etc/machine-id` natively, e.g. for
+    /// chroots, mounted images or tooling with a `--root=` option. The file is
+    /// validated like systemd does:
+    /// - a missing file is reported as ENOENT
+    /// - an empty file or the null ID is reported as ENOMEDIUM
+    /// - the marker "uninitialized" of a machine ID to be committed on first
+    ///   boot is reported as ENOPKG
+    /// - any other content but an ID is reported as EUCLEAN
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the machine ID could not be read, the code
+    ///   is the negative errno
+    pub fn machine_id_of<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        let path = root.as_ref().join(MACHINE_ID_PATH.trim_start_matches('/'));
+        read_id_file(&path).map(ID128::from_raw_value)
+    }
+
+    /// Returns an ID passed to the service as credential.
+    ///
+    /// The credential `name` is read from the directory in
+    /// `$CREDENTIALS_DIRECTORY`, which the service manager sets up for
+    /// credentials configured with `LoadCredential=` or `SetCredential=`. The
+    /// credential must contain an ID in libsystemd or RFC format, optionally
+    /// followed by a newline.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the credential could not be read, the code
+    ///   is the negative errno:
+    ///   - ENXIO: `$CREDENTIALS_DIRECTORY` is not set, i.e. the service has no
+    ///     credentials
+    ///   - EINVAL: `name` is not a valid credential name
+    ///   - ENOENT: the credential does not exist
+    ///   - ENOMEDIUM/ENOPKG/EUCLEAN: the credential does not contain an ID
+    pub fn from_credential(name: &str) -> Result<Self, Error> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(Error::SDError(-libc::EINVAL));
+        }
+        let directory = env::var_os("CREDENTIALS_DIRECTORY").ok_or(Error::SDError(-libc::ENXIO))?;
+        read_id_file(&Path::new(&directory).join(name)).map(ID128::from_raw_value)
+    }
+
+    /// Returns the UUID of the container the process runs in.
+    ///
+    /// Container managers like systemd-nspawn announce themselves in
+    /// `$container` and `/run/systemd/container`, and may pass a UUID
+    /// identifying the container in `$container_uuid` to the init process of
+    /// the container. The UUID is read from the environment of this process
+    /// or, since services do not inherit it, from the environment of PID 1 if
+    /// permitted.
+    ///
+    /// # Return Values
+    /// - Ok(Some(ID128)): the UUID assigned to the container
+    /// - Ok(None): the process does not run in a container or the container has
+    ///   no UUID assigned
+    /// - Err(Error::ParseStringError): `$container_uuid` is no valid ID
+    pub fn container_uuid() -> Result<Option<Self>, Error> {
+        let manager = env::var("container").unwrap_or_default();
+        let announced = fs::read_to_string("/run/systemd/container").unwrap_or_default();
+        if manager.is_empty() && announced.trim().is_empty() {
+            return Ok(None);
+        }
+        let uuid = match env::var("container_uuid") {
+            Ok(uuid) => Some(uuid),
+            Err(_) => environment_of_init("container_uuid")
+        };
+        match uuid {
+            Some(uuid) if !uuid.is_empty() => ID128::from_str_lax(&uuid).map(Some),
+            _ => Ok(None)
+        }
+    }
+
+    /// Returns the UUID of the hardware as reported by the firmware.
+    ///
+    /// The product UUID of SMBIOS (DMI) is read from
+    /// `/sys/class/dmi/id/product_uuid`, which is only readable by root. The
+    /// kernel already decodes the UUID depending on the SMBIOS version, see
+    /// `from_smbios_uuid`. systemd uses this UUID as machine ID if
+    /// `systemd.machine_id=firmware` is given on the kernel command line.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the UUID could not be read, the code is the
+    ///   negative errno; ENOMEDIUM indicates that the firmware does not provide
+    ///   a UUID (all bits 0 or 1)
+    /// - Err(Error::ParseStringError): the file does not contain a valid UUID
+    pub fn dmi_product_uuid() -> Result<Self, Error> {
+        let uuid = fs::read_to_string("/sys/class/dmi/id/product_uuid").map_err(from_io)?;
+        let id128 = ID128::from_str(uuid.trim_end())?;
+        if id128.as_raw_value() == &[0u8; 16] || id128.as_raw_value() == &[0xFFu8; 16] {
+            return Err(Error::SDError(-errno::ENOMEDIUM));
+        }
+        Ok(id128)
+    }
+
+    /// Decodes the raw UUID of a SMBIOS system information structure (type 1).
+    ///
+    /// Since SMBIOS 2.6, the first three fields of the UUID are encoded little
+    /// endian, while older versions did not specify the byte order and
+    /// firmwares commonly stored them big endian. For `major`.`minor` >= 2.6,
+    /// the first three fields are swapped, so the result matches the textual
+    /// representation shown by the firmware and by `dmi_product_uuid`.
+    pub fn from_smbios_uuid(raw: [u8; 16], major: u8, minor: u8) -> Self {
+        let mut value = raw;
+        if (major, minor) >= (2, 6) {
+            value[..4].reverse();
+            value[4..6].reverse();
+            value[6..8].reverse();
+        }
+        ID128::from_raw_value(value)
+    }
+
+    /// Returns the boot ID by parsing `/proc/sys/kernel/random/boot_id`
+    /// natively.
+    ///
+    /// libsystemd caches the boot ID per process, while this function reads
+    /// the file exposed by the kernel on every call. This is meant for early
+    /// boot code and for verifying the value returned by `boot_id`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the file could not be read or parsed, the
+    ///   code is the negative errno
+    pub fn boot_id_from_procfs() -> Result<Self, Error> {
+        get_boot().map(ID128::from_raw_value)
+    }
+
+    /// Returns the invocation ID of the service by parsing `$INVOCATION_ID`
+    /// natively.
+    ///
+    /// Unlike `invocation_id`, this never calls libsystemd and reports parse
+    /// failures as such, so callers can tell a process not started by systemd
+    /// from a malformed variable. Unit tests may set the variable to exercise
+    /// the same code path as under systemd.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::NotUnderSystemd): the variable is not set or contains the
+    ///   null ID, i.e. the process was not started as a systemd service
+    /// - Err(Error::ParseStringError): the variable does not contain an ID
+    pub fn invocation_id_from_env() -> Result<Self, Error> {
+        let variable = env::var("INVOCATION_ID").map_err(|error| match error {
+                           VarError::NotPresent => Error::NotUnderSystemd,
+                           VarError::NotUnicode(_) => {
+                               let error = ParseError::new(ParseProblem::InvalidCharacter, 0);
+                               Error::ParseStringError(error)
+                           }
+                       })?;
+        let id128 = ID128::from_str(&variable)?;
+        if id128 == ID128::default() {
+            return Err(Error::NotUnderSystemd);
+        }
+        Ok(id128)
+    }
+}
@@ -0,0 +1,68 @@
+// sd-id128: parsing lists of IDs
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{shift, Error, ID128};
+
+/// Iterator over the IDs of a delimited list, see `ID128::parse_many`.
+#[derive(Debug, Clone)]
+pub struct ParseMany<'a> {
+    input:    &'a str,
+    position: usize
+}
+
+/// Returns true for the bytes separating IDs of a list.
+fn is_separator(byte: u8) -> bool {
+    byte == b',' || byte.is_ascii_whitespace()
+}
+
+impl Iterator for ParseMany<'_> {
+    type Item = Result<ID128, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.input.as_bytes();
+        let start = self.position
+                    + bytes[self.position..].iter()
+                                            .position(|byte| !is_separator(*byte))?;
+        let end = bytes[start..].iter()
+                                .position(|byte| is_separator(*byte))
+                                .map_or(bytes.len(), |length| start + length);
+        self.position = end;
+        let token = &bytes[start..end];
+        Some(ID128::from_ascii(token).map_err(|error| shift(error, start)))
+    }
+}
+
+impl ID128 {
+    /// Parses a list of IDs separated by newlines, commas or whitespace, e.g.
+    /// the output of `journalctl --list-boots` filtered to IDs or an
+    /// allow-list.
+    ///
+    /// Each ID is parsed strictly like `from_str`. Empty entries are skipped.
+    /// The iterator returns an error per malformed entry and continues with
+    /// the next entry; positions reported by errors refer to `input`, while
+    /// the input they carry is the malformed entry.
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// let list = "0123456789abcdef0123456789abcdef,\n 01234567-89ab-cdef-0123-456789abcdef";
+    /// let ids = ID128::parse_many(list).collect::<Result<Vec<_>, _>>()
+    ///                                  .unwrap();
+    /// assert_eq!(ids.len(), 2);
+    /// ```
+    pub fn parse_many(input: &str) -> ParseMany<'_> {
+        ParseMany { input,
+                    position: 0 }
+    }
+}
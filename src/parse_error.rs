@@ -0,0 +1,276 @@
+// sd-id128: errors of parsing IDs
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::Format;
+use std::{error, fmt};
+
+/// Number of characters of the input kept by a `ParseError`.
+const INPUT_LIMIT: usize = 64;
+
+/// Problems detected while parsing, see `ParseError::problem`.
+///
+/// Variants:
+/// - InvalidLength: the input has the given length, which none of the expected
+///   formats has
+/// - InvalidCharacter: a character is not allowed at its position
+/// - UnexpectedDash: a dash is found in place of a digit
+/// - UnexpectedSeparator: a separator is found in place of a digit, e.g. in a
+///   MAC address
+/// - MixedCase: a letter differs in case from the preceding letters
+/// - UnknownName: the input is none of the known names, e.g. of formats
+/// - WrongVersion: the version digit of a UUID differs from the required one,
+///   e.g. by `ID128::validate_rfc4122`
+/// - WrongVariant: the variant digit of a UUID is not the one of RFC 4122
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ParseProblem {
+    InvalidLength(usize),
+    InvalidCharacter,
+    UnexpectedDash,
+    UnexpectedSeparator,
+    MixedCase,
+    UnknownName,
+    WrongVersion,
+    WrongVariant
+}
+
+/// Error of parsing a malformed ID, carried by `Error::ParseStringError`.
+///
+/// The error owns the offending input, truncated to 64 characters, the byte
+/// offset of the problem, the formats which were expected and the detected
+/// problem, so its message points out what to fix, e.g. "unexpected '-' at
+/// position 13 while parsing simple format".
+///
+/// ```rust
+/// use sd_id128::{Error, Format, ParseProblem, ID128};
+/// match ID128::from_str("01234567-89ab-cdef-0123-456789abcdeX") {
+///     Err(Error::ParseStringError(error)) => {
+///         assert_eq!(error.problem(), ParseProblem::InvalidCharacter);
+///         assert_eq!(error.position(), 35);
+///         assert_eq!(error.expected(), &[Format::RFC]);
+///         assert_eq!(error.to_string(),
+///                    "invalid character 'X' at position 35 while parsing RFC format");
+///     },
+///     _ => panic!()
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    problem:  ParseProblem,
+    position: usize,
+    input:    String,
+    expected: Vec<Format>,
+    subject:  &'static str,
+    found:    Option<char>,
+    shifted:  usize
+}
+
+impl ParseError {
+    /// Returns an error of parsing an ID without input or expected formats.
+    pub(crate) fn new(problem: ParseProblem, position: usize) -> Self {
+        ParseError { problem,
+                     position,
+                     input: String::new(),
+                     expected: Vec::new(),
+                     subject: "ID",
+                     found: None,
+                     shifted: 0 }
+    }
+
+    /// Sets what was parsed if not an ID, e.g. "MAC address".
+    pub(crate) fn subject(mut self, subject: &'static str) -> Self {
+        self.subject = subject;
+        self
+    }
+
+    /// Sets the input and the formats expected while parsing it. The position
+    /// refers to `input`.
+    pub(crate) fn within(mut self, input: &[u8], expected: &[Format]) -> Self {
+        self.found = input.get(self.position..)
+                          .and_then(|rest| String::from_utf8_lossy(rest).chars().next());
+        self.input = String::from_utf8_lossy(input).chars()
+                                                   .take(INPUT_LIMIT)
+                                                   .collect();
+        self.expected = expected.to_vec();
+        self.shifted = 0;
+        self
+    }
+
+    /// Moves the position by `offset` bytes, e.g. to refer to a list the
+    /// input was taken from.
+    pub(crate) fn shift(mut self, offset: usize) -> Self {
+        self.position += offset;
+        self.shifted += offset;
+        self
+    }
+
+    /// Returns the detected problem.
+    pub fn problem(&self) -> ParseProblem {
+        self.problem
+    }
+
+    /// Returns the byte offset of the problem, the start of the ID for
+    /// `ParseProblem::InvalidLength`.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the offending input, truncated to 64 characters.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Returns the formats expected while parsing, empty if the input was no
+    /// ID, e.g. a MAC address.
+    pub fn expected(&self) -> &[Format] {
+        &self.expected
+    }
+
+    /// Returns what was parsed, i.e. the expected formats or the subject.
+    fn parsed(&self) -> String {
+        match self.expected.split_last() {
+            None => String::from(self.subject),
+            Some((last, [])) => format!("{} format", format_name(last)),
+            Some((last, others)) => {
+                let others = others.iter().map(format_name).collect::<Vec<_>>();
+                format!("{} or {} format", others.join(", "), format_name(last))
+            }
+        }
+    }
+}
+
+/// Returns the name of a format as used in messages.
+fn format_name(format: &Format) -> &'static str {
+    match format {
+        Format::Simple => "simple",
+        Format::LibSystemD => "libsystemd",
+        Format::RFC => "RFC",
+        Format::Urn => "URN",
+        Format::Braced => "braced",
+        Format::Custom { .. } => "custom"
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.problem, self.found) {
+            (ParseProblem::InvalidLength(length), _) => {
+                write!(formatter, "invalid length {}", length)?
+            },
+            (ParseProblem::InvalidCharacter, Some(found)) => {
+                write!(formatter, "invalid character {:?}", found)?
+            },
+            (ParseProblem::InvalidCharacter, None) => write!(formatter, "invalid character")?,
+            (ParseProblem::UnexpectedDash, _) => write!(formatter, "unexpected '-'")?,
+            (ParseProblem::UnexpectedSeparator, Some(found)) => {
+                write!(formatter, "unexpected {:?}", found)?
+            },
+            (ParseProblem::UnexpectedSeparator, None) => write!(formatter, "unexpected separator")?,
+            (ParseProblem::MixedCase, Some(found)) => {
+                write!(formatter, "letter {:?} of mixed case", found)?
+            },
+            (ParseProblem::MixedCase, None) => write!(formatter, "letter of mixed case")?,
+            (ParseProblem::UnknownName, _) => write!(formatter, "unknown name {:?}", self.input)?,
+            (ParseProblem::WrongVersion, Some(found)) => {
+                write!(formatter, "wrong version {:?}", found)?
+            },
+            (ParseProblem::WrongVersion, None) => write!(formatter, "wrong version")?,
+            (ParseProblem::WrongVariant, Some(found)) => {
+                write!(formatter, "wrong variant {:?}", found)?
+            },
+            (ParseProblem::WrongVariant, None) => write!(formatter, "wrong variant")?
+        }
+        write!(formatter,
+               " at position {} while parsing {}",
+               self.position,
+               self.parsed())
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Points at the offending characters of the input (feature `diagnostics`),
+/// so tools reporting errors with miette underline them.
+#[cfg(feature = "diagnostics")]
+impl miette::Diagnostic for ParseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("sd_id128::parse"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        if self.expected.is_empty() {
+            return None;
+        }
+        Some(Box::new(format!("expected an ID in {}", self.parsed())))
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.input)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let character = self.found.map_or(1, char::len_utf8);
+        let (length, label) = match self.problem {
+            ParseProblem::InvalidLength(length) => {
+                (self.input.len(), format!("{} characters", length))
+            },
+            ParseProblem::UnknownName => (self.input.len(), String::from("unknown name")),
+            ParseProblem::InvalidCharacter => (character, String::from("invalid character")),
+            ParseProblem::UnexpectedDash => (character, String::from("unexpected dash")),
+            ParseProblem::UnexpectedSeparator => (character, String::from("unexpected separator")),
+            ParseProblem::MixedCase => (character, String::from("letter of mixed case")),
+            ParseProblem::WrongVersion => (character, String::from("wrong version")),
+            ParseProblem::WrongVariant => (character, String::from("wrong variant"))
+        };
+        // shifted positions refer to the text the input was taken from
+        let offset = self.position - self.shifted;
+        if offset + length > self.input.len() {
+            return None;
+        }
+        Some(Box::new(std::iter::once(miette::LabeledSpan::new(Some(label), offset, length))))
+    }
+}
+
+/// Forwards the diagnostic of parse errors (feature `diagnostics`).
+#[cfg(feature = "diagnostics")]
+impl miette::Diagnostic for crate::Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        match self {
+            crate::Error::ParseStringError(error) => error.code(),
+            _ => None
+        }
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        match self {
+            crate::Error::ParseStringError(error) => error.help(),
+            _ => None
+        }
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            crate::Error::ParseStringError(error) => error.source_code(),
+            _ => None
+        }
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            crate::Error::ParseStringError(error) => error.labels(),
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// sd-id128: IDs persisted in state files
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{from_io, Case, Error, Format, ID128};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(target_os = "linux")]
+use std::{ffi::CString,
+          os::unix::{ffi::OsStrExt, io::AsRawFd}};
+use std::{fs::{self, File, OpenOptions},
+          io::{self, Write},
+          path::{Path, PathBuf}};
+
+/// Returns the directory a file is created in.
+fn directory_of(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    }
+}
+
+/// Creates a new file with permissions `mode`, which only Unix systems honor.
+fn create_new(path: &Path, mode: u32) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    options.open(path)
+}
+
+/// Writes `content` to a new temporary file with permissions `mode` next to
+/// `path`. The name has a random suffix, so concurrent writers never share a
+/// temporary file; the file is only removed again by the call creating it.
+fn write_temporary(path: &Path, content: &[u8], mode: u32) -> io::Result<PathBuf> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    loop {
+        let suffix = ID128::random_id()?;
+        let temporary = directory_of(path).join(format!(".{}.{:x}.tmp", name, suffix));
+        let mut file = match create_new(&temporary, mode) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => result?
+        };
+        if let Err(error) = file.write_all(content).and_then(|_| file.sync_all()) {
+            let _ = fs::remove_file(&temporary);
+            return Err(error);
+        }
+        return Ok(temporary);
+    }
+}
+
+/// Flushes the entry of a new file to disk by syncing its directory, which
+/// is not possible on Windows.
+fn sync_directory(path: &Path) -> io::Result<()> {
+    if cfg!(unix) {
+        File::open(directory_of(path))?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Links an anonymous temporary file (`O_TMPFILE`) with the final content
+/// into place. Fails with `AlreadyExists` if `path` exists.
+#[cfg(target_os = "linux")]
+fn link_tmpfile(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true)
+                                     .mode(0o644)
+                                     .custom_flags(libc::O_TMPFILE)
+                                     .open(directory_of(path))?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    let source = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))?;
+    let target = CString::new(path.as_os_str().as_bytes())?;
+    let result = unsafe {
+        libc::linkat(libc::AT_FDCWD,
+                     source.as_ptr(),
+                     libc::AT_FDCWD,
+                     target.as_ptr(),
+                     libc::AT_SYMLINK_FOLLOW)
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Links a named temporary file with the final content into place, for file
+/// systems and operating systems without `O_TMPFILE` support. Fails with
+/// `AlreadyExists` if `path` exists.
+fn link_named(path: &Path, content: &[u8]) -> io::Result<()> {
+    let temporary = write_temporary(path, content, 0o644)?;
+    let result = fs::hard_link(&temporary, path);
+    let _ = fs::remove_file(&temporary);
+    result
+}
+
+/// Atomically creates `path` with `content`, never replacing an existing file.
+pub(crate) fn create_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    match link_tmpfile(path, content) {
+        Err(error) if error.kind() != io::ErrorKind::AlreadyExists => link_named(path, content)?,
+        result => result?
+    }
+    #[cfg(not(target_os = "linux"))]
+    link_named(path, content)?;
+    sync_directory(path)
+}
+
+/// Atomically replaces `path` with `content` by renaming a complete temporary
+/// file with permissions `mode` over it.
+pub(crate) fn replace_atomic(path: &Path, content: &[u8], mode: u32) -> io::Result<()> {
+    let temporary = write_temporary(path, content, mode)?;
+    if let Err(error) = fs::rename(&temporary, path) {
+        let _ = fs::remove_file(&temporary);
+        return Err(error);
+    }
+    sync_directory(path)
+}
+
+impl ID128 {
+    /// Returns a persistent ID stored in a state file, e.g. an application
+    /// instance ID.
+    ///
+    /// If `path` exists, the ID is read from it. Otherwise a new random ID is
+    /// generated and stored in `path` in libsystemd format followed by a
+    /// newline, like `/etc/machine-id`. The file is created atomically: the
+    /// content is written to an anonymous temporary file (`O_TMPFILE` on
+    /// Linux, with a named temporary file as fallback) which is linked into
+    /// place only when complete. An existing file is never replaced; if
+    /// several processes race for the first run, all of them return the ID
+    /// of the process which linked its file first.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): the persistent ID
+    /// - Err(Error::SDError(i32)): the file could not be read or created, the
+    ///   code is the negative errno
+    /// - Err(Error::ParseStringError): the file does not contain a valid ID
+    pub fn persistent<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => return ID128::from_str(content.trim_end()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+            Err(error) => return Err(from_io(error))
+        }
+        let id128 = ID128::random_id()?;
+        let content = format!("{}\n",
+                              id128.to_string_formatted(Format::LibSystemD, Case::Lower));
+        match create_atomic(path, content.as_bytes()) {
+            Ok(()) => Ok(id128),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                let content = fs::read_to_string(path).map_err(from_io)?;
+                ID128::from_str(content.trim_end())
+            },
+            Err(error) => Err(from_io(error))
+        }
+    }
+}
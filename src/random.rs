@@ -0,0 +1,307 @@
+// sd-id128: random IDs generated natively in Rust
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{entropy, uuid::set_version, Error, ID128};
+#[cfg(feature = "rand")]
+use rand::{distributions::{Distribution, Standard},
+           Rng};
+#[cfg(feature = "rand_core")]
+use rand_core::RngCore;
+use std::{cell::RefCell, process};
+
+/// Samples random UUID v4-compatible IDs, i.e. `rng.gen::<ID128>()`.
+///
+/// This implementation is only available with the feature `rand`.
+#[cfg(feature = "rand")]
+impl Distribution<ID128> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ID128 {
+        let mut value = [0u8; 16];
+        rng.fill_bytes(&mut value);
+        set_version(&mut value, 4);
+        ID128::from_raw_value(value)
+    }
+}
+
+/// Advances the SplitMix64 state and returns the next output.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Number of bytes a thread-local generator delivers before it is reseeded
+/// from the kernel.
+const FAST_RESEED_INTERVAL: usize = 1 << 20;
+
+/// ChaCha20 based generator backing `fast_random_id`.
+struct FastRng {
+    key:       [u32; 8],
+    counter:   u64,
+    block:     [u8; 64],
+    position:  usize,
+    generated: usize,
+    pid:       u32
+}
+
+thread_local! {
+    static FAST_RNG: RefCell<Option<FastRng>> = const { RefCell::new(None) };
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// Computes a ChaCha20 block (RFC 8439) of `key` with the words 12 to 15 of
+/// the state, i.e. the block counter and the nonce.
+fn block(key: &[u32; 8], tail: [u32; 4]) -> [u8; 64] {
+    let mut input = [0u32; 16];
+    input[..4].copy_from_slice(&[0x6170_7865, 0x3320_646E, 0x7962_2D32, 0x6B20_6574]);
+    input[4..12].copy_from_slice(key);
+    input[12..].copy_from_slice(&tail);
+    let mut state = input;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    let mut block = [0u8; 64];
+    for (index, word) in state.iter().enumerate() {
+        let word = word.wrapping_add(input[index]);
+        block[index * 4..index * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    block
+}
+
+/// Returns the little endian words of `bytes`.
+fn words<const N: usize>(bytes: &[u8]) -> [u32; N] {
+    let mut words = [0u32; N];
+    for (word, bytes) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+impl FastRng {
+    /// Creates a generator keyed from the kernel random number generator.
+    fn new() -> Result<Self, Error> {
+        let mut seed = [0u8; 32];
+        entropy::fill(&mut seed)?;
+        Ok(FastRng { key:       words(&seed),
+                     counter:   0,
+                     block:     [0u8; 64],
+                     position:  64,
+                     generated: 0,
+                     pid:       process::id() })
+    }
+
+    /// Returns true if the generator has to be replaced by a freshly seeded
+    /// one, either because it delivered enough bytes or because the process
+    /// forked and the parent shares the same state.
+    fn exhausted(&self) -> bool {
+        self.generated >= FAST_RESEED_INTERVAL || self.pid != process::id()
+    }
+
+    /// Computes the next ChaCha20 block (RFC 8439) into `self.block`, with a
+    /// 64-bit block counter and the nonce 0.
+    fn refill(&mut self) {
+        self.block = block(&self.key, [self.counter as u32,
+                                       (self.counter >> 32) as u32,
+                                       0,
+                                       0]);
+        self.counter += 1;
+        self.position = 0;
+    }
+
+    fn fill(&mut self, value: &mut [u8; 16]) {
+        if self.position + 16 > self.block.len() {
+            self.refill();
+        }
+        value.copy_from_slice(&self.block[self.position..self.position + 16]);
+        self.block[self.position..self.position + 16].copy_from_slice(&[0u8; 16]);
+        self.position += 16;
+        self.generated += 16;
+    }
+}
+
+impl ID128 {
+    /// Generates a new randomized 128-bit ID from a thread-local generator.
+    ///
+    /// `random_id` performs a call into libsystemd and a system call for
+    /// every single ID. This function uses a ChaCha20 based cryptographically
+    /// secure generator per thread instead, which is seeded from the kernel
+    /// random number generator and reseeded after every 1 MiB of output as
+    /// well as after a fork of the process. Only the (re-)seeding performs a
+    /// system call, which makes this function suitable for minting large
+    /// amounts of IDs, e.g. request IDs. Like `random_id`, the returned ID is
+    /// UUID v4-compatible.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): seeding from the kernel random number
+    ///   generator failed, the code is the negative errno
+    pub fn fast_random_id() -> Result<Self, Error> {
+        FAST_RNG.with(|cell| {
+                    let mut slot = cell.borrow_mut();
+                    let mut rng = match slot.take() {
+                        Some(rng) if !rng.exhausted() => rng,
+                        _ => FastRng::new()?
+                    };
+                    let mut value = [0u8; 16];
+                    rng.fill(&mut value);
+                    *slot = Some(rng);
+                    set_version(&mut value, 4);
+                    Ok(ID128::from_raw_value(value))
+                })
+    }
+
+    /// Constructs a deterministic ID from a numeric seed.
+    ///
+    /// The same seed always results in the same ID, while different seeds
+    /// result in well-distributed IDs. This is meant for tests and fixtures
+    /// which need stable IDs without hard-coding literals. The ID consists of
+    /// the first two outputs of SplitMix64 seeded with `seed` (big-endian),
+    /// with the UUID v4 version and variant bits applied. The algorithm is
+    /// part of the API and will not change.
+    ///
+    /// IDs constructed by this function are predictable and must never be used
+    /// where a random ID is required.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut state = seed;
+        let mut value = [0u8; 16];
+        value[..8].copy_from_slice(&splitmix64(&mut state).to_be_bytes());
+        value[8..].copy_from_slice(&splitmix64(&mut state).to_be_bytes());
+        set_version(&mut value, 4);
+        ID128::from_raw_value(value)
+    }
+
+    /// Constructs a deterministic ID from a seed of arbitrary bytes.
+    ///
+    /// The bytes are reduced to a numeric seed by the 64-bit FNV-1a hash and
+    /// passed on to `from_seed`. This allows fixtures to name IDs by text,
+    /// e.g. `ID128::from_seed_bytes(b"first user")`.
+    ///
+    /// IDs constructed by this function are predictable and must never be used
+    /// where a random ID is required.
+    pub fn from_seed_bytes(seed: &[u8]) -> Self {
+        let seed = seed.iter().fold(0xCBF2_9CE4_8422_2325u64, |hash, byte| {
+                                  (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01B3)
+                              });
+        ID128::from_seed(seed)
+    }
+
+    /// Generates a new randomized 128-bit ID using the `getrandom` crate.
+    ///
+    /// This is a pure Rust alternative to `random_id` which works without
+    /// libsystemd. The ID is UUID v4-compatible, the version and variant bits
+    /// are set exactly like `sd_id128_randomize()` does.
+    ///
+    /// This function is only available with the feature `getrandom`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the operating system random number generator
+    ///   failed, the code is the negative errno
+    #[cfg(feature = "getrandom")]
+    pub fn random_id_getrandom() -> Result<Self, Error> {
+        let mut value = [0u8; 16];
+        getrandom::getrandom(&mut value).map_err(|error| {
+                                            Error::SDError(-error.raw_os_error()
+                                                                 .unwrap_or(libc::EIO))
+                                        })?;
+        set_version(&mut value, 4);
+        Ok(ID128::from_raw_value(value))
+    }
+
+    /// Generates `n` new randomized 128-bit IDs at once.
+    ///
+    /// Instead of one call into libsystemd per ID, the random bits of all IDs
+    /// are retrieved by a single read from the kernel random number generator
+    /// (`getrandom(2)` or as configured by the entropy policy). This makes
+    /// generating large amounts of IDs considerably cheaper. Like `random_id`,
+    /// all returned IDs are UUID v4-compatible.
+    ///
+    /// # Return Values
+    /// - Ok(Vec<ID128>): `n` initialized ID128 structs
+    /// - Err(Error::SDError(-EINVAL)): `n` IDs exceed the addressable memory
+    /// - Err(Error::SDError(i32)): the kernel random number generator failed,
+    ///   the code is the negative errno
+    pub fn random_batch(n: usize) -> Result<Vec<Self>, Error> {
+        let size = n.checked_mul(16).ok_or(Error::SDError(-libc::EINVAL))?;
+        let mut buffer = vec![0u8; size];
+        entropy::fill(&mut buffer)?;
+        Ok(buffer.chunks_exact(16)
+                 .map(|chunk| {
+                     let mut value = [0u8; 16];
+                     value.copy_from_slice(chunk);
+                     set_version(&mut value, 4);
+                     ID128::from_raw_value(value)
+                 })
+                 .collect())
+    }
+
+    /// Generates a new randomized 128-bit ID using a caller supplied random
+    /// number generator.
+    ///
+    /// Instead of going through libsystemd, the random bits are taken from
+    /// `rng`. This allows deterministic RNGs in tests as well as hardware
+    /// backed RNGs to drive the ID generation. Like `random_id`, the returned
+    /// ID is always UUID v4-compatible.
+    ///
+    /// This function is only available with the feature `rand_core`.
+    #[cfg(feature = "rand_core")]
+    pub fn random_with(rng: &mut impl RngCore) -> Self {
+        let mut value = [0u8; 16];
+        rng.fill_bytes(&mut value);
+        set_version(&mut value, 4);
+        ID128::from_raw_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block, words};
+
+    #[test]
+    fn block_rfc8439() {
+        // test vector of RFC 8439, section 2.3.2
+        let mut key = [0u8; 32];
+        key.iter_mut()
+           .enumerate()
+           .for_each(|(index, byte)| *byte = index as u8);
+        let nonce = words::<3>(&[0, 0, 0, 0x09, 0, 0, 0, 0x4a, 0, 0, 0, 0]);
+        let expected = [0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50, 0x0f, 0xdd, 0x1f,
+                        0xa3, 0x20, 0x71, 0xc4, 0xc7, 0xd1, 0xf4, 0xc7, 0x33, 0xc0, 0x68, 0x03,
+                        0x04, 0x22, 0xaa, 0x9a, 0xc3, 0xd4, 0x6c, 0x4e, 0xd2, 0x82, 0x64, 0x46,
+                        0x07, 0x9f, 0xaa, 0x09, 0x14, 0xc2, 0xd7, 0x05, 0xd9, 0x8b, 0x02, 0xa2,
+                        0xb5, 0x12, 0x9c, 0xd1, 0xde, 0x16, 0x4e, 0xb9, 0xcb, 0xd0, 0x83, 0xe8,
+                        0xa2, 0x50, 0x3c, 0x4e];
+        assert_eq!(block(&words(&key), [1, nonce[0], nonce[1], nonce[2]])[..],
+                   expected[..]);
+    }
+}
@@ -0,0 +1,162 @@
+// sd-id128: vectorized encoding and decoding of hexadecimal digits
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// SSE2 is part of the x86_64 baseline and NEON of the aarch64 baseline, so no
+// runtime detection is required. Other targets use the scalar code.
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+use std::arch::aarch64::*;
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+use std::arch::x86_64::*;
+
+/// Encodes the 16 bytes of an ID into 32 hexadecimal digits.
+///
+/// Returns None if the target has no vectorized implementation.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+pub(crate) fn encode(value: &[u8; 16], upper: bool) -> Option<[u8; 32]> {
+    let mut hex = [0u8; 32];
+    let letters = if upper {
+        b'A' - b'0' - 10
+    } else {
+        b'a' - b'0' - 10
+    };
+    unsafe {
+        let value = _mm_loadu_si128(value.as_ptr() as *const __m128i);
+        let nibble = _mm_set1_epi8(0x0F);
+        let high = _mm_and_si128(_mm_srli_epi16(value, 4), nibble);
+        let low = _mm_and_si128(value, nibble);
+        let to_ascii = |nibbles: __m128i| {
+            let letter = _mm_cmpgt_epi8(nibbles, _mm_set1_epi8(9));
+            _mm_add_epi8(_mm_add_epi8(nibbles, _mm_set1_epi8(b'0' as i8)),
+                         _mm_and_si128(letter, _mm_set1_epi8(letters as i8)))
+        };
+        _mm_storeu_si128(hex.as_mut_ptr() as *mut __m128i,
+                         to_ascii(_mm_unpacklo_epi8(high, low)));
+        _mm_storeu_si128(hex[16..].as_mut_ptr() as *mut __m128i,
+                         to_ascii(_mm_unpackhi_epi8(high, low)));
+    }
+    Some(hex)
+}
+
+/// Decodes 32 hexadecimal digits of either case into the 16 bytes of an ID.
+///
+/// Returns None if any byte is no hexadecimal digit or if the target has no
+/// vectorized implementation.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+pub(crate) fn decode(digits: &[u8; 32]) -> Option<[u8; 16]> {
+    let mut value = [0u8; 16];
+    unsafe {
+        let nibbles = |chunk: &[u8]| {
+            let ascii = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            let digit = _mm_sub_epi8(ascii, _mm_set1_epi8(b'0' as i8));
+            let is_digit = _mm_cmpeq_epi8(_mm_min_epu8(digit, _mm_set1_epi8(9)), digit);
+            let letter = _mm_sub_epi8(_mm_or_si128(ascii, _mm_set1_epi8(0x20)),
+                                      _mm_set1_epi8(b'a' as i8));
+            let is_letter = _mm_cmpeq_epi8(_mm_min_epu8(letter, _mm_set1_epi8(5)), letter);
+            let letter = _mm_add_epi8(letter, _mm_set1_epi8(10));
+            let nibbles = _mm_or_si128(_mm_and_si128(digit, is_digit),
+                                       _mm_and_si128(letter, is_letter));
+            // each 16-bit lane holds a high nibble in its low byte and a low
+            // nibble in its high byte
+            let bytes = _mm_or_si128(_mm_and_si128(_mm_slli_epi16(nibbles, 4),
+                                                   _mm_set1_epi16(0xF0)),
+                                     _mm_srli_epi16(nibbles, 8));
+            (bytes, _mm_movemask_epi8(_mm_or_si128(is_digit, is_letter)))
+        };
+        let (first, first_valid) = nibbles(&digits[..16]);
+        let (second, second_valid) = nibbles(&digits[16..]);
+        if first_valid & second_valid != 0xFFFF {
+            return None;
+        }
+        _mm_storeu_si128(value.as_mut_ptr() as *mut __m128i,
+                         _mm_packus_epi16(first, second));
+    }
+    Some(value)
+}
+
+/// Encodes the 16 bytes of an ID into 32 hexadecimal digits.
+///
+/// Returns None if the target has no vectorized implementation.
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+pub(crate) fn encode(value: &[u8; 16], upper: bool) -> Option<[u8; 32]> {
+    let mut hex = [0u8; 32];
+    let letters = if upper {
+        b'A' - b'0' - 10
+    } else {
+        b'a' - b'0' - 10
+    };
+    unsafe {
+        let value = vld1q_u8(value.as_ptr());
+        let high = vshrq_n_u8::<4>(value);
+        let low = vandq_u8(value, vdupq_n_u8(0x0F));
+        let to_ascii = |nibbles: uint8x16_t| {
+            let letter = vcgtq_u8(nibbles, vdupq_n_u8(9));
+            vaddq_u8(vaddq_u8(nibbles, vdupq_n_u8(b'0')),
+                     vandq_u8(letter, vdupq_n_u8(letters)))
+        };
+        vst1q_u8(hex.as_mut_ptr(), to_ascii(vzip1q_u8(high, low)));
+        vst1q_u8(hex[16..].as_mut_ptr(), to_ascii(vzip2q_u8(high, low)));
+    }
+    Some(hex)
+}
+
+/// Decodes 32 hexadecimal digits of either case into the 16 bytes of an ID.
+///
+/// Returns None if any byte is no hexadecimal digit or if the target has no
+/// vectorized implementation.
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+pub(crate) fn decode(digits: &[u8; 32]) -> Option<[u8; 16]> {
+    let mut value = [0u8; 16];
+    unsafe {
+        let nibbles = |chunk: &[u8]| {
+            let ascii = vld1q_u8(chunk.as_ptr());
+            let digit = vsubq_u8(ascii, vdupq_n_u8(b'0'));
+            let is_digit = vcleq_u8(digit, vdupq_n_u8(9));
+            let letter = vsubq_u8(vorrq_u8(ascii, vdupq_n_u8(0x20)), vdupq_n_u8(b'a'));
+            let is_letter = vcleq_u8(letter, vdupq_n_u8(5));
+            let letter = vaddq_u8(letter, vdupq_n_u8(10));
+            let nibbles = vorrq_u8(vandq_u8(digit, is_digit), vandq_u8(letter, is_letter));
+            (nibbles, vminvq_u8(vorrq_u8(is_digit, is_letter)))
+        };
+        let (first, first_valid) = nibbles(&digits[..16]);
+        let (second, second_valid) = nibbles(&digits[16..]);
+        if first_valid & second_valid != 0xFF {
+            return None;
+        }
+        let high = vuzp1q_u8(first, second);
+        let low = vuzp2q_u8(first, second);
+        vst1q_u8(value.as_mut_ptr(), vorrq_u8(vshlq_n_u8::<4>(high), low));
+    }
+    Some(value)
+}
+
+/// Encodes the 16 bytes of an ID into 32 hexadecimal digits.
+///
+/// Returns None if the target has no vectorized implementation.
+#[cfg(not(any(all(target_arch = "x86_64", target_feature = "sse2"),
+              all(target_arch = "aarch64", target_feature = "neon"))))]
+pub(crate) fn encode(_value: &[u8; 16], _upper: bool) -> Option<[u8; 32]> {
+    None
+}
+
+/// Decodes 32 hexadecimal digits of either case into the 16 bytes of an ID.
+///
+/// Returns None if any byte is no hexadecimal digit or if the target has no
+/// vectorized implementation.
+#[cfg(not(any(all(target_arch = "x86_64", target_feature = "sse2"),
+              all(target_arch = "aarch64", target_feature = "neon"))))]
+pub(crate) fn decode(_digits: &[u8; 32]) -> Option<[u8; 16]> {
+    None
+}
@@ -0,0 +1,175 @@
+// sd-id128: configurable sources of the machine ID
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Prioritized chains of sources for the machine ID.
+//!
+//! `ID128::machine_id` only asks libsystemd, which fails on systems without a
+//! valid `/etc/machine-id`, e.g. Debian systems where only the D-Bus machine
+//! ID exists. A chain of sources defines exactly which alternatives are tried
+//! in which order, so portable daemons behave deterministically.
+//!
+//! The machine ID configured on the kernel command line is available via
+//! `kernel_cmdline_machine_id`.
+//!
+//! ```rust
+//! use sd_id128::{source::MachineIdSource, ID128};
+//! let machine = ID128::machine_id_from(&[MachineIdSource::LibSystemD,
+//!                                        MachineIdSource::EtcMachineId,
+//!                                        MachineIdSource::DBusMachineId,
+//!                                        MachineIdSource::Random]).unwrap();
+//! ```
+use crate::{from_io,
+            native::{read_id_file, MACHINE_ID_PATH},
+            Error, ID128};
+use std::{fs,
+          path::{Path, PathBuf}};
+
+const DBUS_MACHINE_ID_PATH: &str = "/var/lib/dbus/machine-id";
+
+/// Source of a machine ID.
+///
+/// Variants:
+/// - LibSystemD: `ID128::machine_id`
+/// - EtcMachineId: the file `/etc/machine-id`, read natively
+/// - DBusMachineId: the file `/var/lib/dbus/machine-id` of D-Bus
+/// - File: any file in machine-id format
+/// - Random: a random ID, i.e. the machine ID changes on every start; this
+///   source never fails and thus terminates a chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineIdSource {
+    LibSystemD,
+    EtcMachineId,
+    DBusMachineId,
+    File(PathBuf),
+    Random
+}
+
+/// Chain of `machine_id_default_chain`: libsystemd, /etc/machine-id and the
+/// D-Bus machine ID.
+pub const DEFAULT_CHAIN: [MachineIdSource; 3] = [MachineIdSource::LibSystemD,
+                                                 MachineIdSource::EtcMachineId,
+                                                 MachineIdSource::DBusMachineId];
+
+/// Machine ID configured by `systemd.machine_id=` on the kernel command line.
+///
+/// Variants:
+/// - Id: the given ID is used as transient machine ID
+/// - Firmware: the keyword `firmware`, i.e. the ID of the firmware (DMI product
+///   UUID or the UUID of the virtual machine) is used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CmdlineMachineId {
+    Id(ID128),
+    Firmware
+}
+
+/// Compares keys of the kernel command line, dashes and underscores are
+/// equivalent.
+fn key_matches(key: &str, expected: &str) -> bool {
+    key.len() == expected.len()
+    && key.bytes()
+          .zip(expected.bytes())
+          .all(|(a, b)| a == b || (a == b'-' || a == b'_') && (b == b'-' || b == b'_'))
+}
+
+/// Parses `systemd.machine_id=` of a kernel command line.
+///
+/// Like systemd, dashes and underscores in the key are equivalent and the last
+/// occurrence wins. Quotes are removed.
+///
+/// # Return Values
+/// - Ok(Some(CmdlineMachineId)): the configured machine ID
+/// - Ok(None): no machine ID is configured
+/// - Err(Error::ParseStringError): the configured value is no valid ID
+pub fn parse_cmdline(cmdline: &str) -> Result<Option<CmdlineMachineId>, Error> {
+    let mut value = None;
+    for argument in cmdline.split_whitespace() {
+        let argument = argument.trim_matches('"');
+        if let Some((key, found)) = argument.split_once('=') {
+            if key_matches(key, "systemd.machine_id") {
+                value = Some(found.trim_matches('"'));
+            }
+        }
+    }
+    match value {
+        Some("firmware") => Ok(Some(CmdlineMachineId::Firmware)),
+        Some(value) => ID128::from_str(value).map(|id128| Some(CmdlineMachineId::Id(id128))),
+        None => Ok(None)
+    }
+}
+
+/// Returns the machine ID configured by `systemd.machine_id=` on the kernel
+/// command line of the running kernel (`/proc/cmdline`), e.g. for tooling in
+/// the initrd.
+///
+/// # Return Values
+/// - Ok(Some(CmdlineMachineId)): the configured machine ID
+/// - Ok(None): no machine ID is configured
+/// - Err(Error::SDError(i32)): `/proc/cmdline` could not be read, the code is
+///   the negative errno
+/// - Err(Error::ParseStringError): the configured value is no valid ID
+pub fn kernel_cmdline_machine_id() -> Result<Option<CmdlineMachineId>, Error> {
+    let cmdline = fs::read_to_string("/proc/cmdline").map_err(from_io)?;
+    parse_cmdline(&cmdline)
+}
+
+impl MachineIdSource {
+    /// Retrieves the machine ID from this source.
+    pub fn machine_id(&self) -> Result<ID128, Error> {
+        match self {
+            MachineIdSource::LibSystemD => ID128::machine_id(),
+            MachineIdSource::EtcMachineId => {
+                read_id_file(Path::new(MACHINE_ID_PATH)).map(ID128::from_raw_value)
+            },
+            MachineIdSource::DBusMachineId => {
+                read_id_file(Path::new(DBUS_MACHINE_ID_PATH)).map(ID128::from_raw_value)
+            },
+            MachineIdSource::File(path) => read_id_file(path).map(ID128::from_raw_value),
+            MachineIdSource::Random => ID128::random_id()
+        }
+    }
+}
+
+impl ID128 {
+    /// Returns the machine ID of the first source in `chain` which succeeds.
+    ///
+    /// The sources are tried in the given order, see module `source`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): all sources failed, the error of the last
+    ///   source is returned; an empty chain is reported as ENOENT
+    pub fn machine_id_from(chain: &[MachineIdSource]) -> Result<Self, Error> {
+        let mut last = Error::SDError(-libc::ENOENT);
+        for source in chain {
+            match source.machine_id() {
+                Ok(id128) => return Ok(id128),
+                Err(error) => last = error
+            }
+        }
+        Err(last)
+    }
+
+    /// Returns the machine ID of the `DEFAULT_CHAIN`: libsystemd,
+    /// /etc/machine-id and /var/lib/dbus/machine-id.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): all sources failed, the code is the negative
+    ///   errno of the last source
+    pub fn machine_id_default_chain() -> Result<Self, Error> {
+        ID128::machine_id_from(&DEFAULT_CHAIN)
+    }
+}
@@ -0,0 +1,245 @@
+// sd-id128: streaming IDs from readers and into writers
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{from_io, Case, Error, Format, ParseError, ParseProblem, ID128};
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+/// Iterator over the IDs of an `io::Read`, e.g. a file or a pipe.
+///
+/// The source is consumed incrementally, so it is never loaded into memory
+/// as a whole. In text mode, each line holds one ID in any format accepted by
+/// `ID128::from_str`; surrounding whitespace and empty lines are ignored. In
+/// binary mode, each 16 bytes hold the raw value of one ID.
+///
+/// A malformed line, including one which is no valid UTF-8 or longer than 128
+/// bytes, is reported as error and the iteration continues with the next line.
+/// The iteration ends after an I/O error.
+///
+/// ```rust
+/// use sd_id128::Id128Reader;
+/// let input = "0123456789abcdef0123456789abcdef\n\n01234567-89ab-cdef-0123-456789abcdef\n";
+/// let ids = Id128Reader::new(input.as_bytes()).collect::<Result<Vec<_>, _>>()
+///                                             .unwrap();
+/// assert_eq!(ids.len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Id128Reader<R> {
+    reader: BufReader<R>,
+    binary: bool,
+    line:   Vec<u8>,
+    done:   bool
+}
+
+/// Maximum number of bytes of a line in text mode, leaving room for
+/// whitespace around the longest format.
+const MAX_LINE: usize = 128;
+
+/// Returns `bytes` without leading and trailing ASCII whitespace.
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter()
+                     .position(|byte| !byte.is_ascii_whitespace())
+                     .unwrap_or(bytes.len());
+    let end = bytes.iter()
+                   .rposition(|byte| !byte.is_ascii_whitespace())
+                   .map_or(start, |end| end + 1);
+    &bytes[start..end]
+}
+
+impl<R: Read> Id128Reader<R> {
+    /// Returns a reader yielding one ID per line.
+    pub fn new(reader: R) -> Self {
+        Id128Reader { reader: BufReader::new(reader),
+                      binary: false,
+                      line:   Vec::new(),
+                      done:   false }
+    }
+
+    /// Returns a reader yielding one ID per 16 bytes of raw values.
+    pub fn binary(reader: R) -> Self {
+        Id128Reader { binary: true,
+                      ..Id128Reader::new(reader) }
+    }
+
+    /// Returns the wrapped reader. Data buffered but not yet yielded is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Skips the rest of an oversized line up to and including the newline and
+    /// returns the number of bytes skipped.
+    fn skip_line(&mut self) -> io::Result<usize> {
+        let mut skipped = 0;
+        loop {
+            let buffer = match self.reader.fill_buf() {
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                result => result?
+            };
+            let (length, done) = match buffer.iter().position(|byte| *byte == b'\n') {
+                Some(newline) => (newline + 1, true),
+                None => (buffer.len(), buffer.is_empty())
+            };
+            self.reader.consume(length);
+            skipped += length;
+            if done {
+                return Ok(skipped);
+            }
+        }
+    }
+
+    /// Reads the next line holding an ID. The line is parsed as ASCII, so
+    /// invalid UTF-8 is reported as invalid character of that line. Lines are
+    /// read up to `MAX_LINE` bytes, the rest of a longer line is skipped.
+    fn next_text(&mut self) -> Option<Result<ID128, Error>> {
+        loop {
+            self.line.clear();
+            let limit = MAX_LINE as u64 + 1;
+            match (&mut self.reader).take(limit)
+                                    .read_until(b'\n', &mut self.line)
+            {
+                Ok(0) => return None,
+                Ok(length) if length > MAX_LINE && self.line.last() != Some(&b'\n') => {
+                    let skipped = match self.skip_line() {
+                        Ok(skipped) => skipped,
+                        Err(error) => {
+                            self.done = true;
+                            return Some(Err(from_io(error)));
+                        }
+                    };
+                    let error = ParseError::new(ParseProblem::InvalidLength(length + skipped), 0);
+                    return Some(Err(Error::ParseStringError(error.subject("line")
+                                                                 .within(&self.line, &[]))));
+                },
+                Ok(_) if trim(&self.line).is_empty() => continue,
+                Ok(_) => return Some(ID128::from_ascii(trim(&self.line))),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(from_io(error)));
+                }
+            }
+        }
+    }
+
+    /// Reads the next 16 bytes. A truncated last value is reported as EIO.
+    fn next_binary(&mut self) -> Option<Result<ID128, Error>> {
+        let mut value = [0u8; 16];
+        let mut filled = 0;
+        while filled < value.len() {
+            match self.reader.read(&mut value[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => {
+                    self.done = true;
+                    return Some(Err(Error::SDError(-libc::EIO)));
+                },
+                Ok(length) => filled += length,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(from_io(error)));
+                }
+            }
+        }
+        Some(Ok(ID128::from_raw_value(value)))
+    }
+}
+
+impl<R: Read> Iterator for Id128Reader<R> {
+    type Item = Result<ID128, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.binary {
+            self.next_binary()
+        } else {
+            self.next_text()
+        }
+    }
+}
+
+/// Writes IDs to an `io::Write` in a configured format, e.g. for exports or
+/// allow-lists read back by `Id128Reader`.
+///
+/// Each ID is followed by the delimiter, a newline by default. The IDs are
+/// formatted without intermediate Strings, but written to the wrapped writer
+/// directly, so wrap unbuffered writers like files in an `io::BufWriter`.
+///
+/// ```rust
+/// use sd_id128::{Case, Format, Id128Writer, ID128};
+/// let id128 = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+/// let mut writer = Id128Writer::new(Vec::new()).format(Format::LibSystemD)
+///                                              .case(Case::Upper)
+///                                              .delimiter(",");
+/// writer.write_ids(&[id128.clone(), id128]).unwrap();
+/// assert_eq!(writer.into_inner(),
+///            b"0123456789ABCDEF0123456789ABCDEF,0123456789ABCDEF0123456789ABCDEF,");
+/// ```
+#[derive(Debug)]
+pub struct Id128Writer<W> {
+    writer:    W,
+    format:    Format,
+    case:      Case,
+    delimiter: String
+}
+
+impl<W: Write> Id128Writer<W> {
+    /// Returns a writer emitting one ID per line in RFC format, lower case.
+    pub fn new(writer: W) -> Self {
+        Id128Writer { writer,
+                      format: Format::default(),
+                      case: Case::default(),
+                      delimiter: String::from("\n") }
+    }
+
+    /// Sets the format of the IDs.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the case of hexadecimal letters.
+    pub fn case(mut self, case: Case) -> Self {
+        self.case = case;
+        self
+    }
+
+    /// Sets the delimiter written after each ID.
+    pub fn delimiter(mut self, delimiter: &str) -> Self {
+        self.delimiter = String::from(delimiter);
+        self
+    }
+
+    /// Writes an ID followed by the delimiter.
+    pub fn write_id(&mut self, id128: &ID128) -> io::Result<()> {
+        id128.write_io(&mut self.writer, self.format, self.case)?;
+        self.writer.write_all(self.delimiter.as_bytes())
+    }
+
+    /// Writes all IDs of `ids`, each followed by the delimiter.
+    pub fn write_ids<'a, I: IntoIterator<Item = &'a ID128>>(&mut self, ids: I) -> io::Result<()> {
+        ids.into_iter().try_for_each(|id128| self.write_id(id128))
+    }
+
+    /// Flushes the wrapped writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,278 @@
+// sd-id128: UUID versions as specified in RFC 9562
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{from_io, Error, ParseError, ParseProblem, ID128};
+use std::{fs,
+          time::{Duration, SystemTime, UNIX_EPOCH}};
+
+/// Number of 100ns intervals between the Gregorian epoch (1582-10-15) and the
+/// Unix epoch (1970-01-01).
+const GREGORIAN_OFFSET: u64 = 0x01B2_1DD2_1381_4000;
+
+/// Sets the version nibble and the RFC 4122/9562 variant bits.
+pub(crate) fn set_version(value: &mut [u8; 16], version: u8) {
+    value[6] = (value[6] & 0x0F) | (version << 4);
+    value[8] = (value[8] & 0x3F) | 0x80;
+}
+
+/// Converts a duration into 100ns intervals.
+fn intervals(duration: Duration) -> Option<u64> {
+    duration.as_secs()
+            .checked_mul(10_000_000)?
+            .checked_add(u64::from(duration.subsec_nanos()) / 100)
+}
+
+/// Converts a point in time into 100ns intervals since the Gregorian epoch.
+///
+/// Points in time before the Gregorian epoch are clamped to it; points in time
+/// not fitting into the 60-bit timestamp of a UUID yield EINVAL.
+pub(crate) fn gregorian(time: SystemTime) -> Result<u64, Error> {
+    let timestamp = match time.duration_since(UNIX_EPOCH) {
+        Ok(since_unix) => {
+            intervals(since_unix).and_then(|since_unix| GREGORIAN_OFFSET.checked_add(since_unix))
+        },
+        Err(error) => Some(intervals(error.duration()).map_or(0, |before_unix| {
+                               GREGORIAN_OFFSET.saturating_sub(before_unix)
+                           }))
+    };
+    timestamp.filter(|timestamp| timestamp >> 60 == 0)
+             .ok_or(Error::SDError(-libc::EINVAL))
+}
+
+/// Converts 100ns intervals since the Gregorian epoch into a point in time.
+pub(crate) fn from_gregorian(timestamp: u64) -> SystemTime {
+    if timestamp >= GREGORIAN_OFFSET {
+        let since_unix = timestamp - GREGORIAN_OFFSET;
+        UNIX_EPOCH
+        + Duration::new(since_unix / 10_000_000,
+                        (since_unix % 10_000_000) as u32 * 100)
+    } else {
+        let before_unix = GREGORIAN_OFFSET - timestamp;
+        UNIX_EPOCH
+        - Duration::new(before_unix / 10_000_000,
+                        (before_unix % 10_000_000) as u32 * 100)
+    }
+}
+
+/// Returns the version of an ID with RFC 4122/9562 variant.
+pub(crate) fn version(value: &[u8; 16]) -> Option<u8> {
+    if value[8] & 0xC0 == 0x80 {
+        Some(value[6] >> 4)
+    } else {
+        None
+    }
+}
+
+impl ID128 {
+    /// Generates a new time-ordered UUID version 6 (RFC 9562).
+    ///
+    /// Version 6 is a field-compatible reordering of version 1: the 60-bit
+    /// Gregorian timestamp is stored most significant bits first, so IDs sort
+    /// by their creation time. Clock sequence and node are filled with random
+    /// bits retrieved from libsystemd, as recommended by RFC 9562 for new
+    /// applications. The multicast bit of the node is set to mark it as
+    /// random.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): sd-id128 returned an error code
+    pub fn new_v6() -> Result<Self, Error> {
+        let mut value = ID128::random_id()?.into_raw_value();
+        let timestamp = gregorian(SystemTime::now())?;
+        value[0] = (timestamp >> 52) as u8;
+        value[1] = (timestamp >> 44) as u8;
+        value[2] = (timestamp >> 36) as u8;
+        value[3] = (timestamp >> 28) as u8;
+        value[4] = (timestamp >> 20) as u8;
+        value[5] = (timestamp >> 12) as u8;
+        value[6] = (timestamp >> 8) as u8 & 0x0F;
+        value[7] = timestamp as u8;
+        value[10] |= 0x01;
+        set_version(&mut value, 6);
+        Ok(ID128::from_raw_value(value))
+    }
+
+    /// Constructs a custom UUID version 8 (RFC 9562).
+    ///
+    /// All bits of `custom` are taken over as they are, except for the version
+    /// nibble and the variant bits which are set to version 8 and the RFC
+    /// variant. This leaves 122 bits for vendor specific content.
+    pub fn new_v8(custom: [u8; 16]) -> Self {
+        let mut value = custom;
+        set_version(&mut value, 8);
+        ID128::from_raw_value(value)
+    }
+
+    /// Constructs a time-based UUID version 1 (RFC 4122).
+    ///
+    /// Version 1 IDs are made of a 60-bit timestamp counting 100ns intervals
+    /// since the Gregorian epoch (1582-10-15), a 14-bit clock sequence and a
+    /// 48-bit node, traditionally the MAC address of a network interface. This
+    /// constructor is meant for interoperation with legacy systems requiring
+    /// version 1 IDs; new applications should prefer `new_v6`.
+    ///
+    /// The upper 2 bits of `clock_seq` are ignored. The node of a network
+    /// interface is available via `node_of_interface`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(-EINVAL)): `timestamp` lies beyond the 60-bit range
+    ///   of version 1 timestamps (year 5236)
+    pub fn new_v1(timestamp: SystemTime, clock_seq: u16, node: [u8; 6]) -> Result<Self, Error> {
+        let timestamp = gregorian(timestamp)?;
+        let mut value = [0u8; 16];
+        value[0] = (timestamp >> 24) as u8;
+        value[1] = (timestamp >> 16) as u8;
+        value[2] = (timestamp >> 8) as u8;
+        value[3] = timestamp as u8;
+        value[4] = (timestamp >> 40) as u8;
+        value[5] = (timestamp >> 32) as u8;
+        value[6] = (timestamp >> 56) as u8 & 0x0F;
+        value[7] = (timestamp >> 48) as u8;
+        value[8] = (clock_seq >> 8) as u8;
+        value[9] = clock_seq as u8;
+        value[10..].copy_from_slice(&node);
+        set_version(&mut value, 1);
+        Ok(ID128::from_raw_value(value))
+    }
+
+    /// Returns the MAC address of a network interface as node for `new_v1`.
+    ///
+    /// The address is read from `/sys/class/net/<interface>/address`.
+    ///
+    /// # Return Values
+    /// - Ok([u8; 6]): MAC address of the interface
+    /// - Err(Error::ParseStringError): `interface` is no valid interface name
+    ///   or the address is no valid MAC address
+    /// - Err(Error::SDError(i32)): the address could not be read, the code is
+    ///   the negative errno
+    pub fn node_of_interface(interface: &str) -> Result<[u8; 6], Error> {
+        let invalid_name = |problem, position| {
+            Error::ParseStringError(ParseError::new(problem, position).subject("interface name")
+                                                                      .within(interface.as_bytes(),
+                                                                              &[]))
+        };
+        if interface.is_empty() {
+            return Err(invalid_name(ParseProblem::InvalidLength(0), 0));
+        }
+        if interface.starts_with('.') {
+            return Err(invalid_name(ParseProblem::InvalidCharacter, 0));
+        }
+        if let Some(position) = interface.find('/') {
+            return Err(invalid_name(ParseProblem::InvalidCharacter, position));
+        }
+        let path = format!("/sys/class/net/{}/address", interface);
+        let address = fs::read_to_string(path).map_err(from_io)?;
+        let address = address.trim_end();
+        let invalid = |problem, position| {
+            Error::ParseStringError(ParseError::new(problem, position).subject("MAC address")
+                                                                      .within(address.as_bytes(),
+                                                                              &[]))
+        };
+        if address.len() != 17 {
+            return Err(invalid(ParseProblem::InvalidLength(address.len()), 0));
+        }
+        let mut node = [0u8; 6];
+        for (index, octet) in address.split(':').enumerate() {
+            let position = index * 3;
+            if index >= 6 || octet.len() != 2 {
+                return Err(invalid(ParseProblem::UnexpectedSeparator, position));
+            }
+            if let Some(offset) = octet.bytes().position(|byte| !byte.is_ascii_hexdigit()) {
+                return Err(invalid(ParseProblem::InvalidCharacter, position + offset));
+            }
+            node[index] = u8::from_str_radix(octet, 16).unwrap();
+        }
+        Ok(node)
+    }
+
+    /// Returns the timestamp embedded in a time-based ID.
+    ///
+    /// Time-based IDs carry their creation time which can be used to order
+    /// and bucket IDs. The timestamp is extracted from:
+    /// - version 1: 60-bit Gregorian timestamp with 100ns resolution
+    /// - version 6: 60-bit Gregorian timestamp with 100ns resolution
+    /// - version 7: 48-bit Unix timestamp with 1ms resolution
+    ///
+    /// # Return Values
+    /// - Some(SystemTime): the embedded timestamp
+    /// - None: the ID is not time-based, e.g. a random or a machine ID
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        let value = self.as_raw_value();
+        match version(value)? {
+            1 => {
+                let timestamp = u64::from(value[6] & 0x0F) << 56
+                                | u64::from(value[7]) << 48
+                                | u64::from(value[4]) << 40
+                                | u64::from(value[5]) << 32
+                                | u64::from(value[0]) << 24
+                                | u64::from(value[1]) << 16
+                                | u64::from(value[2]) << 8
+                                | u64::from(value[3]);
+                Some(from_gregorian(timestamp))
+            },
+            6 => {
+                let timestamp = u64::from(value[0]) << 52
+                                | u64::from(value[1]) << 44
+                                | u64::from(value[2]) << 36
+                                | u64::from(value[3]) << 28
+                                | u64::from(value[4]) << 20
+                                | u64::from(value[5]) << 12
+                                | u64::from(value[6] & 0x0F) << 8
+                                | u64::from(value[7]);
+                Some(from_gregorian(timestamp))
+            },
+            7 => {
+                let millis = value[..6].iter()
+                                       .fold(0u64, |millis, byte| millis << 8 | u64::from(*byte));
+                Some(UNIX_EPOCH + Duration::from_millis(millis))
+            },
+            _ => None
+        }
+    }
+
+    /// Validates that the ID is a random UUID version 4 (RFC 4122), like the
+    /// IDs generated by `sd_id128_randomize()`.
+    ///
+    /// Values which are supposed to be random IDs, e.g. ingested from other
+    /// systems, can be checked right after parsing to catch corrupted or
+    /// fabricated IDs early:
+    ///
+    /// ```rust
+    /// use sd_id128::ID128;
+    /// let random = ID128::from_str("6e4a1ecd-0b1c-4d41-9b2e-4f19e3b0c4a2").unwrap();
+    /// assert!(random.validate_rfc4122().is_ok());
+    /// let fabricated = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    /// assert!(fabricated.validate_rfc4122().is_err());
+    /// ```
+    ///
+    /// # Return Values
+    /// - Ok(ID128): the ID has the RFC 4122 variant and version 4
+    /// - Err(Error::ParseStringError): the version
+    ///   (`ParseProblem::WrongVersion`) or the variant
+    ///   (`ParseProblem::WrongVariant`) differs, the position refers to the ID
+    ///   in simple format
+    pub fn validate_rfc4122(self) -> Result<Self, Error> {
+        let (problem, position) = match version(self.as_raw_value()) {
+            Some(4) => return Ok(self),
+            Some(_) => (ParseProblem::WrongVersion, 12),
+            None => (ParseProblem::WrongVariant, 16)
+        };
+        let simple = format!("{:x}", self);
+        Err(Error::ParseStringError(ParseError::new(problem, position).subject("random UUID")
+                                                                      .within(simple.as_bytes(),
+                                                                              &[])))
+    }
+}
@@ -0,0 +1,179 @@
+// sd-id128: notification on changes of ID files
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Notification on changes of `/etc/machine-id`.
+//!
+//! Agents started before the machine ID is initialized on first boot see the
+//! "uninitialized" marker or a transient ID. Instead of polling, they may
+//! watch the file via inotify and pick up the final ID once it is written.
+//!
+//! ```rust,no_run
+//! use sd_id128::watch;
+//! let watcher = watch::watch_machine_id(|machine_id| {
+//!                   if let Ok(machine_id) = machine_id {
+//!                       println!("machine id: {}", machine_id);
+//!                   }
+//!               }).unwrap();
+//! // the watch ends when the watcher is dropped
+//! drop(watcher);
+//! ```
+//!
+//! This module is only available with the feature `watch`.
+use crate::{from_io,
+            native::{read_id_file, MACHINE_ID_PATH},
+            Error, ID128};
+use std::{ffi::{CString, OsString},
+          fs::File,
+          io::{self, Read, Write},
+          mem,
+          os::unix::{ffi::{OsStrExt, OsStringExt},
+                     io::{AsRawFd, FromRawFd}},
+          path::{Path, PathBuf},
+          thread::{self, JoinHandle}};
+
+const EVENTS: u32 = libc::IN_CREATE | libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO;
+
+/// Handle of a running watch.
+///
+/// The watch runs in a thread of its own, which is stopped and joined when the
+/// watcher is dropped.
+#[derive(Debug)]
+pub struct Watcher {
+    stop:   File,
+    thread: Option<JoinHandle<()>>
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let _ = self.stop.write_all(&1u64.to_ne_bytes());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Takes ownership of a file descriptor returned by a libc call.
+fn owned(fd: libc::c_int) -> Result<File, Error> {
+    if fd < 0 {
+        return Err(from_io(io::Error::last_os_error()));
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Checks a buffer of inotify events for events on `name`. Returns `None` if
+/// the watch was removed, e.g. because the directory was deleted.
+fn concerns(buffer: &[u8], name: &[u8]) -> Option<bool> {
+    let header = mem::size_of::<libc::inotify_event>();
+    let mut found = false;
+    let mut offset = 0;
+    while offset + header <= buffer.len() {
+        let event =
+            unsafe { (buffer[offset..].as_ptr() as *const libc::inotify_event).read_unaligned() };
+        if event.mask & libc::IN_IGNORED != 0 {
+            return None;
+        }
+        let end = (offset + header + event.len as usize).min(buffer.len());
+        let event_name = &buffer[offset + header..end];
+        let length = event_name.iter()
+                               .position(|byte| *byte == 0)
+                               .unwrap_or(event_name.len());
+        found |= &event_name[..length] == name;
+        offset = end;
+    }
+    Some(found)
+}
+
+/// Waits for events until the watcher is dropped.
+fn run<F>(mut inotify: File, stop: File, path: PathBuf, name: OsString, mut callback: F)
+    where F: FnMut(Result<ID128, Error>) {
+    let mut buffer = [0u8; 4096];
+    loop {
+        let mut fds = [libc::pollfd { fd:      inotify.as_raw_fd(),
+                                      events:  libc::POLLIN,
+                                      revents: 0 },
+                       libc::pollfd { fd:      stop.as_raw_fd(),
+                                      events:  libc::POLLIN,
+                                      revents: 0 }];
+        if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        if fds[1].revents != 0 {
+            return;
+        }
+        let length = match inotify.read(&mut buffer) {
+            Ok(length) => length,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return
+        };
+        match concerns(&buffer[..length], name.as_bytes()) {
+            Some(true) => callback(read_id_file(&path).map(ID128::from_raw_value)),
+            Some(false) => {},
+            None => return
+        }
+    }
+}
+
+/// Watches an ID file like `/etc/machine-id` for changes.
+///
+/// The directory of `path` is watched for the file being created, written or
+/// renamed into place, which also covers files replaced atomically. On every
+/// change, the file is read and validated like `ID128::machine_id_of` does
+/// and the result is passed to `callback`, which runs in the thread of the
+/// watch. Since a file is usually created before it is written, the callback
+/// may see intermediate errors like ENOMEDIUM for an empty file.
+///
+/// # Return Values
+/// - Ok(Watcher): the watch is running until the watcher is dropped
+/// - Err(Error::SDError(i32)): the watch could not be set up, the code is the
+///   negative errno
+pub fn watch_id_file<P, F>(path: P, callback: F) -> Result<Watcher, Error>
+    where P: AsRef<Path>,
+          F: FnMut(Result<ID128, Error>) + Send + 'static
+{
+    let path = path.as_ref().to_path_buf();
+    let name = path.file_name()
+                   .ok_or(Error::SDError(-libc::EINVAL))?
+                   .to_os_string();
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from(".")
+    };
+    let directory = CString::new(directory.into_os_string().into_vec()).map_err(Error::NullError)?;
+    let inotify = owned(unsafe { libc::inotify_init1(libc::IN_CLOEXEC) })?;
+    if unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), directory.as_ptr(), EVENTS) } < 0 {
+        return Err(from_io(io::Error::last_os_error()));
+    }
+    let stop = owned(unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) })?;
+    let stop_thread = stop.try_clone().map_err(from_io)?;
+    let thread =
+        thread::Builder::new().name("sd-id128-watch".into())
+                              .spawn(move || run(inotify, stop_thread, path, name, callback))
+                              .map_err(from_io)?;
+    Ok(Watcher { stop,
+                 thread: Some(thread) })
+}
+
+/// Watches `/etc/machine-id` for changes, see `watch_id_file`.
+///
+/// Once the machine ID is committed on first boot, the callback receives the
+/// final ID. The marker "uninitialized" is reported as ENOPKG.
+pub fn watch_machine_id<F>(callback: F) -> Result<Watcher, Error>
+    where F: FnMut(Result<ID128, Error>) + Send + 'static {
+    watch_id_file(MACHINE_ID_PATH, callback)
+}
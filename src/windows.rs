@@ -0,0 +1,79 @@
+// sd-id128: host identity on Windows
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use crate::{from_io, Error, ID128};
+use std::{ffi::c_void, io, ptr};
+
+// HKEY_LOCAL_MACHINE is defined as the sign extended value 0x80000002
+const HKEY_LOCAL_MACHINE: isize = 0x8000_0002u32 as i32 as isize;
+const RRF_RT_REG_SZ: u32 = 0x0000_0002;
+const RRF_SUBKEY_WOW6464KEY: u32 = 0x0001_0000;
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegGetValueW(key: isize,
+                    sub_key: *const u16,
+                    value: *const u16,
+                    flags: u32,
+                    kind: *mut u32,
+                    data: *mut c_void,
+                    size: *mut u32)
+                    -> i32;
+}
+
+/// Encodes a string as NUL terminated UTF-16.
+fn wide(string: &str) -> Vec<u16> {
+    string.encode_utf16().chain(Some(0)).collect()
+}
+
+impl ID128 {
+    /// Returns the machine GUID of a Windows installation.
+    ///
+    /// The GUID is read from the registry value
+    /// `HKLM\SOFTWARE\Microsoft\Cryptography\MachineGuid`, which Windows
+    /// generates during installation. The 64-bit view of the registry is read
+    /// even from 32-bit processes. The GUID is the counterpart of the machine
+    /// ID of systemd: it identifies the installation, not the hardware.
+    ///
+    /// This function is only available on Windows with the feature `portable`.
+    ///
+    /// # Return Values
+    /// - Ok(ID128): initialized ID128 struct
+    /// - Err(Error::SDError(i32)): the registry value could not be read, the
+    ///   code is the negative Windows error code
+    /// - Err(Error::ParseStringError): the value is no valid GUID
+    pub fn windows_machine_guid() -> Result<Self, Error> {
+        let sub_key = wide("SOFTWARE\\Microsoft\\Cryptography");
+        let value = wide("MachineGuid");
+        let mut buffer = [0u16; 64];
+        let mut size = (buffer.len() * 2) as u32;
+        let result = unsafe {
+            RegGetValueW(HKEY_LOCAL_MACHINE,
+                         sub_key.as_ptr(),
+                         value.as_ptr(),
+                         RRF_RT_REG_SZ | RRF_SUBKEY_WOW6464KEY,
+                         ptr::null_mut(),
+                         buffer.as_mut_ptr() as *mut c_void,
+                         &mut size)
+        };
+        if result != 0 {
+            return Err(from_io(io::Error::from_raw_os_error(result)));
+        }
+        // the size in bytes includes the terminating NUL
+        let length = (size as usize / 2).saturating_sub(1);
+        let guid = String::from_utf16_lossy(&buffer[..length]);
+        ID128::from_str(guid.trim_matches(|c| c == '{' || c == '}'))
+    }
+}
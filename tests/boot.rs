@@ -0,0 +1,26 @@
+// testing on sd-id128 boot identity
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+#![cfg(target_os = "linux")]
+use sd_id128::{boot::BootIdentity, ID128};
+use std::time::SystemTime;
+
+#[test]
+fn current() {
+    let boot = BootIdentity::current().unwrap();
+    assert_eq!(boot.boot_id, ID128::boot_id().unwrap());
+    assert!(boot.boot_timestamp <= SystemTime::now());
+    assert_eq!(boot, BootIdentity::current().unwrap());
+}
@@ -0,0 +1,158 @@
+// testing on sd-id128 command line tool
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+#![cfg(feature = "cli")]
+use sd_id128::{Case, Format, ID128};
+use std::process::{Command, Output};
+
+fn sd_id128(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sd-id128")).args(arguments)
+                                                .env_remove("INVOCATION_ID")
+                                                .output()
+                                                .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(output.status.success());
+    std::str::from_utf8(&output.stdout).unwrap().trim_end()
+}
+
+#[test]
+fn new() {
+    let output = sd_id128(&["new"]);
+    let id = ID128::from_str_format(stdout(&output), Format::LibSystemD).unwrap();
+    assert!(id.validate_rfc4122().is_ok());
+}
+
+#[test]
+fn machine_id() {
+    let output = sd_id128(&["machine-id"]);
+    assert_eq!(stdout(&output),
+               ID128::machine_id().unwrap()
+                                  .to_string_formatted(Format::LibSystemD, Case::Lower));
+}
+
+#[test]
+fn invocation_id_missing_fails() {
+    let output = sd_id128(&["invocation-id"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn invalid_arguments_fail() {
+    assert_eq!(sd_id128(&["unknown"]).status.code(), Some(1));
+    assert_eq!(sd_id128(&["--unknown"]).status.code(), Some(1));
+    assert_eq!(sd_id128(&["new", "machine-id"]).status.code(), Some(1));
+    assert!(stdout(&sd_id128(&["--help"])).contains("machine-id"));
+}
+
+#[test]
+fn show() {
+    let output = sd_id128(&["show"]);
+    let table = stdout(&output);
+    assert!(table.starts_with("NAME"));
+    assert!(table.contains("4f68bce3e8cd4db196e7fbcaf984b709"));
+    assert!(table.contains("unit-failed"));
+    let output = sd_id128(&["show",
+                            "ROOT_X86_64",
+                            "c12a7328-f81f-11d2-ba4b-00a0c93ec93b"]);
+    let lines = stdout(&output).lines().skip(1).collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("root-x86-64 ")
+            && lines[0].ends_with("4f68bce3e8cd4db196e7fbcaf984b709"));
+    assert!(lines[1].starts_with("esp ") && lines[1].ends_with("c12a7328f81f11d2ba4b00a0c93ec93b"));
+    let output = sd_id128(&["show", "0123456789abcdef0123456789abcdef"]);
+    assert!(stdout(&output).ends_with("- 0123456789abcdef0123456789abcdef"));
+    assert_eq!(sd_id128(&["show", "unknown"]).status.code(), Some(1));
+}
+
+#[test]
+fn output_format() {
+    let machine = ID128::machine_id().unwrap();
+    let output = sd_id128(&["machine-id", "--uuid"]);
+    assert_eq!(stdout(&output),
+               machine.to_string_formatted(Format::RFC, Case::Lower));
+    let output = sd_id128(&["--format=braced", "--upper", "machine-id"]);
+    assert_eq!(stdout(&output),
+               machine.to_string_formatted(Format::Braced, Case::Upper));
+    let output = sd_id128(&["show", "--value", "-u", "esp"]);
+    assert_eq!(stdout(&output), "c12a7328-f81f-11d2-ba4b-00a0c93ec93b");
+    assert_eq!(sd_id128(&["--format=unknown", "new"]).status.code(),
+               Some(1));
+}
+
+#[test]
+fn app_specific() {
+    let app = "4e7290a0-a9a1-4f3f-b7bb-f5a05b46f1ad";
+    let expected = ID128::machine_id_app_specific_native(ID128::from_str(app).unwrap()).unwrap();
+    let output = sd_id128(&["machine-id", "-a", app]);
+    assert_eq!(stdout(&output),
+               expected.to_string_formatted(Format::LibSystemD, Case::Lower));
+    let output = sd_id128(&["--app-specific=4e7290a0a9a14f3fb7bbf5a05b46f1ad",
+                            "machine-id"]);
+    assert_eq!(stdout(&output),
+               expected.to_string_formatted(Format::LibSystemD, Case::Lower));
+    assert_eq!(sd_id128(&["new", "-a", app]).status.code(), Some(1));
+    assert_eq!(sd_id128(&["machine-id", "-a"]).status.code(), Some(1));
+    assert_eq!(sd_id128(&["machine-id", "-a", "invalid"]).status.code(),
+               Some(1));
+}
+
+#[test]
+fn convert() {
+    use std::{io::Write, process::Stdio};
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sd-id128")).args(["convert", "--uuid",
+                                                                       "--upper"])
+                                                                .stdin(Stdio::piped())
+                                                                .stdout(Stdio::piped())
+                                                                .stderr(Stdio::piped())
+                                                                .spawn()
+                                                                .unwrap();
+    child.stdin
+         .take()
+         .unwrap()
+         .write_all(b"0123456789abcdef0123456789abcdef\n\ninvalid\n{01234567-89ab-cdef-0123-456789abcdef}\n")
+         .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(std::str::from_utf8(&output.stdout).unwrap(),
+               "01234567-89AB-CDEF-0123-456789ABCDEF\n01234567-89AB-CDEF-0123-456789ABCDEF\n");
+    assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn init_file() {
+    let path = std::env::temp_dir().join(format!("sd-id128-cli-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    std::fs::write(path, "uninitialized\n").unwrap();
+    let output = sd_id128(&["init-file", path]);
+    let id = ID128::from_str(stdout(&output)).unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(),
+               format!("{}\n",
+                       id.to_string_formatted(Format::LibSystemD, Case::Lower)));
+    let output = sd_id128(&["init-file", path]);
+    assert_eq!(ID128::from_str(stdout(&output)).unwrap(), id);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(sd_id128(&["init-file"]).status.code(), Some(1));
+}
+
+#[test]
+fn var_partition_uuid() {
+    let expected = ID128::machine_id_app_specific_native(sd_id128::gpt::VAR).unwrap();
+    let output = sd_id128(&["var-partition-uuid", "-u"]);
+    assert_eq!(stdout(&output),
+               expected.to_string_formatted(Format::RFC, Case::Lower));
+}
@@ -0,0 +1,56 @@
+// testing on sd-id128 displaying IDs without allocations
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::ID128;
+use std::{alloc::{GlobalAlloc, Layout, System},
+          fmt::Write,
+          sync::atomic::{AtomicUsize, Ordering}};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[test]
+fn display_does_not_allocate() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let mut log = String::with_capacity(1024);
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    write!(log,
+           "{} {:#} {:>40} {:.8} {}",
+           id,
+           id,
+           id,
+           id,
+           id.urn().upper()).unwrap();
+    assert_eq!(ALLOCATIONS.load(Ordering::SeqCst), before);
+    assert_eq!(log,
+               "01234567-89ab-cdef-0123-456789abcdef 0123456789abcdef0123456789abcdef     \
+                01234567-89ab-cdef-0123-456789abcdef 01234567 \
+                urn:uuid:01234567-89AB-CDEF-0123-456789ABCDEF");
+}
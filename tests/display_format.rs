@@ -0,0 +1,33 @@
+// testing on sd-id128 process-wide display format
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{Case, Format, ID128};
+
+// the display format is process-wide, thus a single test covers it
+#[test]
+fn set_display_format() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(sd_id128::display_format(), (Format::RFC, Case::Lower));
+    sd_id128::set_display_format(Format::LibSystemD, Case::Upper);
+    assert_eq!(format!("{}", id), "0123456789ABCDEF0123456789ABCDEF");
+    assert_eq!(format!("{:#}", id), "0123456789abcdef0123456789abcdef");
+    let colons = Format::Custom { group_sizes: &[4; 8],
+                                  separator:   ':' };
+    sd_id128::set_display_format(colons, Case::Lower);
+    assert_eq!(sd_id128::display_format(), (colons, Case::Lower));
+    assert_eq!(id.to_string(), "0123:4567:89ab:cdef:0123:4567:89ab:cdef");
+    sd_id128::set_display_format(Format::default(), Case::default());
+    assert_eq!(id.to_string(), "01234567-89ab-cdef-0123-456789abcdef");
+}
@@ -0,0 +1,37 @@
+// testing on sd-id128 entropy policies
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{entropy::{self, Blocking, EntropyPolicy},
+               ID128};
+
+// the policy is process-wide, hence all policies are exercised in one test
+#[test]
+fn policies() {
+    assert_eq!(entropy::policy(), EntropyPolicy::LibSystemD);
+    for policy in [EntropyPolicy::GetRandom(Blocking::Block),
+                   EntropyPolicy::GetRandom(Blocking::NonBlock),
+                   EntropyPolicy::DevURandom,
+                   EntropyPolicy::LibSystemD].iter()
+    {
+        entropy::set_policy(*policy);
+        assert_eq!(entropy::policy(), *policy);
+        let random1 = ID128::random_id().unwrap();
+        let random2 = ID128::random_id().unwrap();
+        assert_ne!(random1, random2);
+        assert_eq!(random1.as_ref()[6] >> 4, 4);
+        assert_eq!(random1.as_ref()[8] >> 6, 2);
+        assert_eq!(ID128::random_batch(3).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,69 @@
+// testing on sd-id128 failpoints
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+#![cfg(feature = "failpoints")]
+use sd_id128::{failpoints::{self, Call},
+               Error, ID128};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Guards the environment of tests setting `INVOCATION_ID`.
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+/// Locks the environment, also after a test failed holding it.
+fn lock_environment() -> MutexGuard<'static, ()> {
+    ENVIRONMENT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[test]
+fn failing_calls() {
+    let _machine = failpoints::fail(Call::Machine, libc::ENOENT);
+    let _boot = failpoints::fail(Call::Boot, libc::EIO);
+    let _random = failpoints::fail(Call::Randomize, libc::ENOSYS);
+    assert_eq!(ID128::machine_id(), Err(Error::SDError(-libc::ENOENT)));
+    assert_eq!(ID128::boot_id(), Err(Error::SDError(-libc::EIO)));
+    assert_eq!(ID128::random_id(), Err(Error::SDError(-libc::ENOSYS)));
+}
+
+#[test]
+fn failing_invocation_is_not_under_systemd() {
+    let _environment = lock_environment();
+    std::env::set_var("INVOCATION_ID", "0123456789abcdef0123456789abcdef");
+    let guard = failpoints::fail(Call::Invocation, libc::ENXIO);
+    assert_eq!(ID128::invocation_id(), Err(Error::NotUnderSystemd));
+    drop(guard);
+    let guard = failpoints::fail(Call::Invocation, libc::EPERM);
+    assert_eq!(ID128::invocation_id(), Err(Error::SDError(-libc::EPERM)));
+    drop(guard);
+    std::env::remove_var("INVOCATION_ID");
+}
+
+#[test]
+fn guards_restore_failpoints() {
+    let outer = failpoints::fail(Call::Machine, libc::ENOMEDIUM);
+    {
+        let _inner = failpoints::fail(Call::Machine, libc::ENOPKG);
+        assert_eq!(ID128::machine_id(), Err(Error::SDError(-libc::ENOPKG)));
+    }
+    assert_eq!(ID128::machine_id(), Err(Error::SDError(-libc::ENOMEDIUM)));
+    drop(outer);
+    assert_ne!(ID128::machine_id(), Err(Error::SDError(-libc::ENOMEDIUM)));
+}
+
+#[test]
+fn failpoints_are_per_thread() {
+    let _guard = failpoints::fail(Call::Boot, libc::EIO);
+    let other = std::thread::spawn(ID128::boot_id).join().unwrap();
+    assert_ne!(other, Err(Error::SDError(-libc::EIO)));
+}
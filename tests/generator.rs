@@ -0,0 +1,105 @@
+// testing on sd-id128 generators
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{generator::{FastRandom, IdGenerator, Seeded, Snowflake, SystemdRandom, V7},
+               ID128};
+
+#[test]
+fn systemd_random() {
+    let mut generator = SystemdRandom;
+    assert_ne!(generator.generate().unwrap(), generator.generate().unwrap());
+}
+
+#[test]
+fn fast_random() {
+    let mut generator = FastRandom;
+    assert_ne!(generator.generate().unwrap(), generator.generate().unwrap());
+}
+
+#[test]
+#[cfg(feature = "getrandom")]
+fn getrandom() {
+    let mut generator = sd_id128::generator::GetRandom;
+    assert_ne!(generator.generate().unwrap(), generator.generate().unwrap());
+}
+
+#[test]
+fn v7_is_ascending() {
+    let mut generator = V7::new();
+    let mut last = generator.generate().unwrap();
+    for _ in 0..10_000 {
+        let next = generator.generate().unwrap();
+        assert!(last.as_ref() < next.as_ref());
+        last = next;
+    }
+}
+
+#[test]
+fn v7_version_and_timestamp() {
+    let before = std::time::SystemTime::now() - std::time::Duration::from_millis(1);
+    let id = V7::new().generate().unwrap();
+    assert_eq!(id.as_ref()[6] >> 4, 7);
+    assert_eq!(id.as_ref()[8] >> 6, 2);
+    assert!(id.timestamp().unwrap() >= before);
+}
+
+#[test]
+fn seeded_sequence() {
+    let mut generator = Seeded::new(7);
+    assert_eq!(generator.generate().unwrap(), ID128::from_seed(7));
+    assert_eq!(generator.generate().unwrap(), ID128::from_seed(8));
+}
+
+#[test]
+fn boxed_strategy() {
+    let mut generators: Vec<Box<dyn IdGenerator>> = vec![Box::new(SystemdRandom),
+                                                         Box::new(V7::new()),
+                                                         Box::new(Seeded::new(0))];
+    for generator in generators.iter_mut() {
+        assert!(generator.generate().is_ok());
+    }
+}
+
+#[test]
+fn snowflake_is_ascending() {
+    let mut generator = Snowflake::new().unwrap();
+    let mut last = generator.generate().unwrap();
+    for _ in 0..10_000 {
+        let next = generator.generate().unwrap();
+        assert!(last.as_ref() < next.as_ref());
+        last = next;
+    }
+}
+
+#[test]
+fn snowflake_layout() {
+    let machine = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let id = Snowflake::with_machine(&machine).worker(0xBEEF)
+                                              .generate()
+                                              .unwrap();
+    assert_eq!(id.as_ref()[6] >> 4, 8);
+    assert_eq!(&id.as_ref()[8..], &[0x81, 0x23, 0x45, 0x67, 0x89, 0xAB,
+                                    0xBE, 0xEF]);
+}
+
+#[test]
+#[cfg(any(systemd_v233,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+fn snowflake_app_specific() {
+    let app = ID128::random_id().unwrap();
+    let plain = Snowflake::new().unwrap().generate().unwrap();
+    let hashed = Snowflake::app_specific(app).unwrap().generate().unwrap();
+    assert_ne!(&plain.as_ref()[8..14], &hashed.as_ref()[8..14]);
+}
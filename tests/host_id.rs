@@ -0,0 +1,73 @@
+// testing on sd-id128 host identity
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{host_id::{self, HostIdBackend},
+               ID128};
+
+#[test]
+#[cfg(target_os = "linux")]
+fn machine_id_on_linux() {
+    assert_eq!(host_id::backend(), Some(HostIdBackend::MachineId));
+    assert_eq!(host_id::host_id(), ID128::machine_id());
+}
+
+#[test]
+#[cfg(all(feature = "portable", windows))]
+fn machine_guid_on_windows() {
+    assert_eq!(host_id::backend(), Some(HostIdBackend::MachineGuid));
+    assert_eq!(host_id::host_id(), ID128::windows_machine_guid());
+}
+
+#[test]
+#[cfg(all(feature = "portable", target_os = "macos"))]
+fn platform_uuid_on_macos() {
+    assert_eq!(host_id::backend(), Some(HostIdBackend::PlatformUuid));
+    assert_eq!(host_id::host_id(), ID128::macos_platform_uuid());
+}
+
+#[test]
+#[cfg(all(feature = "portable",
+          any(target_os = "freebsd", target_os = "openbsd")))]
+fn host_uuid_on_bsd() {
+    assert_eq!(host_id::backend(), Some(HostIdBackend::HostUuid));
+    assert_eq!(host_id::host_id(), ID128::bsd_host_uuid());
+}
+
+#[test]
+#[cfg(all(not(feature = "portable"), not(target_os = "linux")))]
+fn no_backend_without_portable() {
+    assert_eq!(host_id::backend(), None::<HostIdBackend>);
+    assert_eq!(host_id::host_id(),
+               Err::<ID128, _>(sd_id128::Error::SDError(-libc::ENOSYS)));
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn app_specific() {
+    let app = ID128::from_str("4e7290a0a9a14f3fb7bbf5a05b46f1ad").unwrap();
+    assert_eq!(host_id::app_specific(app.clone()).unwrap(),
+               ID128::app_specific_native(host_id::host_id().unwrap(), app));
+}
+
+#[test]
+#[cfg(all(feature = "crypto",
+          target_os = "linux",
+          any(systemd_v233,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))))]
+fn app_specific_matches_libsystemd() {
+    let app = ID128::from_str("4e7290a0a9a14f3fb7bbf5a05b46f1ad").unwrap();
+    assert_eq!(host_id::app_specific(app.clone()),
+               ID128::machine_id_app_specific(app));
+}
@@ -0,0 +1,94 @@
+// testing on sd-id128 machine-id files
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{id_file::{self, Content},
+               Error, ID128};
+use std::os::unix::fs::PermissionsExt;
+
+#[test]
+fn parse() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(id_file::parse(b"0123456789abcdef0123456789abcdef\n").unwrap(),
+               Content::Id(id.clone()));
+    assert_eq!(id_file::parse(b"0123456789abcdef0123456789abcdef").unwrap(),
+               Content::Id(id.clone()));
+    assert_eq!(id_file::parse(b"01234567-89ab-cdef-0123-456789abcdef\n").unwrap(),
+               Content::Id(id));
+    assert_eq!(id_file::parse(b"uninitialized\n").unwrap(),
+               Content::Uninitialized);
+    assert_eq!(id_file::parse(b"").unwrap(), Content::Empty);
+    assert_eq!(id_file::parse(b"\n").unwrap(), Content::Empty);
+    assert_eq!(id_file::parse(b"00000000000000000000000000000000\n").unwrap(),
+               Content::Empty);
+    assert_eq!(id_file::parse(b"0123456789abcdef0123456789abcdef\n\n"),
+               Err(Error::SDError(-libc::EUCLEAN)));
+    assert_eq!(id_file::parse(b"0123-4567-89ab-cdef-0123-4567-89ab-cdef"),
+               Err(Error::SDError(-libc::EUCLEAN)));
+}
+
+#[test]
+fn format() {
+    let id = ID128::from_str("0123456789ABCDEF0123456789ABCDEF").unwrap();
+    assert_eq!(id_file::format(&Content::Id(id)),
+               "0123456789abcdef0123456789abcdef\n");
+    assert_eq!(id_file::format(&Content::Uninitialized), "uninitialized\n");
+    assert_eq!(id_file::format(&Content::Empty), "");
+}
+
+#[test]
+fn write_and_read() {
+    let path = std::env::temp_dir().join(format!("sd-id128-id-file-{}", std::process::id()));
+    for content in [Content::Id(ID128::from_seed(1)),
+                    Content::Uninitialized,
+                    Content::Empty,
+                    Content::Id(ID128::from_seed(2))].iter()
+    {
+        id_file::write(&path, content).unwrap();
+        assert_eq!(&id_file::read(&path).unwrap(), content);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, id_file::MODE);
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(id_file::read(&path), Err(Error::SDError(-libc::ENOENT)));
+}
+
+#[test]
+fn write_concurrent_threads() {
+    let path =
+        std::sync::Arc::new(std::env::temp_dir().join(format!("sd-id128-id-file-threads-{}",
+                                                              std::process::id())));
+    let threads = (0..8u64).map(|seed| {
+                               let path = path.clone();
+                               std::thread::spawn(move || {
+                                   for _ in 0..20 {
+                                       id_file::write(&*path, &Content::Id(ID128::from_seed(seed)))
+                                           .unwrap();
+                                   }
+                               })
+                           })
+                           .collect::<Vec<_>>();
+    threads.into_iter()
+           .for_each(|thread| thread.join().unwrap());
+    assert!(matches!(id_file::read(&*path).unwrap(), Content::Id(_)));
+    let directory = path.parent().unwrap();
+    let prefix = format!(".{}.", path.file_name().unwrap().to_string_lossy());
+    assert!(!std::fs::read_dir(directory).unwrap().any(|entry| {
+                                                      entry.unwrap()
+                                                           .file_name()
+                                                           .to_string_lossy()
+                                                           .starts_with(&prefix)
+                                                  }));
+    std::fs::remove_file(&*path).unwrap();
+}
@@ -0,0 +1,145 @@
+// testing on sd-id128 journal files
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{journal_file, Error, ID128};
+
+fn header_data() -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&journal_file::SIGNATURE);
+    data.extend_from_slice(&[0u8; 16]);
+    for seed in 1..=4 {
+        data.extend_from_slice(ID128::from_seed(seed).as_ref());
+    }
+    data.extend_from_slice(&[0u8; 152]);
+    data
+}
+
+#[test]
+fn parse() {
+    let header = journal_file::parse(&header_data()).unwrap();
+    assert_eq!(header.file_id, ID128::from_seed(1));
+    assert_eq!(header.machine_id, ID128::from_seed(2));
+    assert_eq!(header.boot_id, ID128::from_seed(3));
+    assert_eq!(header.seqnum_id, ID128::from_seed(4));
+    assert_eq!(journal_file::parse(&header_data()[..journal_file::HEADER_SIZE]).unwrap(),
+               header);
+}
+
+#[test]
+fn parse_invalid() {
+    let mut data = header_data();
+    assert_eq!(journal_file::parse(&data[..60]),
+               Err(Error::SDError(-libc::EBADMSG)));
+    data[0] = b'X';
+    assert_eq!(journal_file::parse(&data),
+               Err(Error::SDError(-libc::EBADMSG)));
+}
+
+#[test]
+fn read() {
+    let path =
+        std::env::temp_dir().join(format!("sd-id128-journal-{}.journal", std::process::id()));
+    std::fs::write(&path, header_data()).unwrap();
+    assert_eq!(journal_file::read(&path).unwrap().machine_id,
+               ID128::from_seed(2));
+    std::fs::write(&path, &header_data()[..40]).unwrap();
+    assert_eq!(journal_file::read(&path),
+               Err(Error::SDError(-libc::EBADMSG)));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(journal_file::read(&path),
+               Err(Error::SDError(-libc::ENOENT)));
+}
+
+/// Builds a journal file holding entries of (boot seed, realtime), split into
+/// two entry arrays.
+#[cfg(feature = "journal")]
+fn journal_data(entries: &[(u64, u64)], compact: bool) -> Vec<u8> {
+    fn put(data: &mut [u8], offset: usize, value: u64) {
+        data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+    let mut data = header_data();
+    data.resize(256, 0);
+    if compact {
+        data[12] = 1 << 4;
+    }
+    put(&mut data, 152, entries.len() as u64);
+    let mut offsets = Vec::new();
+    for (seed, realtime) in entries {
+        let entry = data.len();
+        data.resize(entry + 64, 0);
+        put(&mut data, entry + 8, 64);
+        put(&mut data, entry + 24, *realtime);
+        data[entry + 40..entry + 56].copy_from_slice(ID128::from_seed(*seed).as_ref());
+        offsets.push(entry as u64);
+    }
+    let item_size = if compact { 4 } else { 8 };
+    let mut previous = 176;
+    for chunk in offsets.chunks(2) {
+        let array = data.len();
+        // one unused item at the end of each array
+        let size = 24 + item_size * (chunk.len() + 1);
+        data.resize(array + size, 0);
+        put(&mut data, array + 8, size as u64);
+        for (index, offset) in chunk.iter().enumerate() {
+            let item = array + 24 + index * item_size;
+            data[item..item + item_size].copy_from_slice(&offset.to_le_bytes()[..item_size]);
+        }
+        put(&mut data, previous, array as u64);
+        previous = array + 16;
+    }
+    data
+}
+
+#[test]
+#[cfg(feature = "journal")]
+fn list_boots_in() {
+    use std::{os::unix::fs::PermissionsExt,
+              time::{Duration, UNIX_EPOCH}};
+    let directory = std::env::temp_dir().join(format!("sd-id128-journal-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("system.journal"),
+                   journal_data(&[(1, 100), (2, 300), (1, 200), (2, 400), (3, 500)], false)).unwrap();
+    std::fs::write(directory.join("user-1000.journal"),
+                   journal_data(&[(2, 350), (2, 450)], true)).unwrap();
+    std::fs::write(directory.join("broken.journal"), b"LPKSHHRH").unwrap();
+    std::fs::write(directory.join("notes.txt"), b"no journal").unwrap();
+    // skipped as not readable, or as damaged when running as root
+    let unreadable = directory.join("unreadable.journal");
+    std::fs::write(&unreadable, b"LPKSHHRH").unwrap();
+    std::fs::set_permissions(&unreadable, std::fs::Permissions::from_mode(0o000)).unwrap();
+    // skipped like a file removed between listing and opening it
+    std::os::unix::fs::symlink(directory.join("vacuumed"),
+                               directory.join("removed.journal")).unwrap();
+    let boots = journal_file::list_boots_in(&directory).unwrap();
+    let summary = boots.iter()
+                       .map(|boot| (boot.index, boot.boot_id.clone(), boot.first, boot.last))
+                       .collect::<Vec<_>>();
+    let time = |microseconds| UNIX_EPOCH + Duration::from_micros(microseconds);
+    assert_eq!(summary, vec![(-2,
+                              ID128::from_seed(1),
+                              time(100),
+                              time(200)),
+                             (-1,
+                              ID128::from_seed(2),
+                              time(300),
+                              time(450)),
+                             (0,
+                              ID128::from_seed(3),
+                              time(500),
+                              time(500))]);
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(journal_file::list_boots_in(&directory),
+               Err(Error::SDError(-libc::ENOENT)));
+}
@@ -13,7 +13,25 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
-use sd_id128::ID128;
+use sd_id128::{Case, Format, FormatOptions, LaxOptions, ParseProblem, ID128};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Returns the problem and position of a parse error.
+fn parse_failure<T>(result: Result<T, sd_id128::Error>) -> Option<(ParseProblem, usize)> {
+    match result {
+        Err(sd_id128::Error::ParseStringError(error)) => Some((error.problem(), error.position())),
+        _ => None
+    }
+}
+
+/// Serializes the tests changing or reading environment variables, as
+/// libsystemd reads them without the lock of `std::env`.
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+/// Locks the environment for a test, also after another test failed.
+fn lock_environment() -> MutexGuard<'static, ()> {
+    ENVIRONMENT.lock().unwrap_or_else(PoisonError::into_inner)
+}
 
 #[test]
 fn debug_default() {
@@ -64,7 +82,8 @@ fn boot_id() {
 }
 
 #[test]
-#[cfg(any(feature = "240"))]
+#[cfg(any(systemd_v240,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
 fn boot_id_hashed() {
     let random = ID128::random_id().unwrap();
     let boot = ID128::boot_id().unwrap();
@@ -72,13 +91,20 @@ fn boot_id_hashed() {
     assert_ne!(boot, boot_hashed);
 }
 
+#[test]
+fn boot_id_from_procfs() {
+    assert_eq!(ID128::boot_id_from_procfs().unwrap(),
+               ID128::boot_id().unwrap());
+}
+
 #[test]
 fn machine_id() {
     let _ = ID128::machine_id().unwrap();
 }
 
 #[test]
-#[cfg(any(feature = "233", feature = "240"))]
+#[cfg(any(systemd_v233,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
 fn machine_id_hashed() {
     let random = ID128::random_id().unwrap();
     let machine = ID128::machine_id().unwrap();
@@ -87,12 +113,29 @@ fn machine_id_hashed() {
 }
 
 #[test]
-#[cfg(any(feature = "232", feature = "233", feature = "240"))]
+#[cfg(any(systemd_v232, feature = "no-ffi", not(feature = "ffi")))]
 fn invocation_id() {
+    let _environment = lock_environment();
     let inv_id = ID128::invocation_id();
     assert!(inv_id.is_err() || inv_id.is_ok());
 }
 
+#[test]
+#[cfg(any(feature = "no-ffi", not(feature = "ffi")))]
+fn no_ffi_machine_id_matches_file() {
+    let content = std::fs::read_to_string("/etc/machine-id").unwrap();
+    let expected = ID128::from_str(content.trim_end()).unwrap();
+    assert_eq!(ID128::machine_id().unwrap(), expected);
+}
+
+#[test]
+#[cfg(any(feature = "no-ffi", not(feature = "ffi")))]
+fn no_ffi_boot_id_matches_file() {
+    let content = std::fs::read_to_string("/proc/sys/kernel/random/boot_id").unwrap();
+    let expected = ID128::from_str(content.trim_end()).unwrap();
+    assert_eq!(ID128::boot_id().unwrap(), expected);
+}
+
 #[test]
 fn from_string_lower_libsystemd() {
     let parsed = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
@@ -201,6 +244,16 @@ fn from_string_invalid_dash_libsystemd() {
     assert!(ID128::from_str("0123456789ABCDEF0123456789ABCDE-F").is_err());
 }
 
+#[test]
+fn from_string_error_positions() {
+    assert_eq!(parse_failure(ID128::from_str("0123-4567-89A-BCDEF-0123-4567-89AB-CDEF")),
+               Some((ParseProblem::UnexpectedDash, 13)));
+    assert_eq!(parse_failure(ID128::from_str("01234567x89ab-cdef-0123-4567-9abcdef")),
+               Some((ParseProblem::InvalidCharacter, 8)));
+    assert_eq!(parse_failure(ID128::from_str("0123456789abcdef0123456789abcdeg")),
+               Some((ParseProblem::InvalidCharacter, 31)));
+}
+
 #[test]
 fn from_string_based_on_random_succeeds() {
     let random = ID128::random_id().unwrap();
@@ -255,28 +308,1354 @@ fn from_string_lax_invalid_dash_libsystemd() {
     assert!(ID128::from_str_lax("0123456789ABCDEF0123456789ABCDE-F").is_ok());
 }
 #[test]
+#[cfg(feature = "ffi")]
 fn from_string_sd_upper_libsystemd_succeeds() {
     assert!(ID128::from_str_sd("1234567890ABCDEF1234567890ABCDEF").is_ok());
 }
 
 #[test]
+#[cfg(feature = "ffi")]
 fn from_string_sd_lower_libsystemd_succeeds() {
     assert!(ID128::from_str_sd("1234567890abcdef1234567890abcdef").is_ok());
 }
 
 #[test]
+#[cfg(feature = "ffi")]
 fn from_string_sd_too_short_fails() {
     assert!(ID128::from_str_sd("1234567890abcd567890abcdef").is_err());
 }
 
 #[test]
+#[cfg(feature = "ffi")]
 fn from_string_sd_too_long_fails() {
     assert!(ID128::from_str_sd("1234567890ABCDEF1234567890ABCDEF1").is_err());
 }
 
 #[test]
+#[cfg(feature = "ffi")]
 fn ffi_from_string_upper_eq_lower_case() {
     let upper = ID128::from_str_sd("1234567890ABCDEF1234567890ABCDEF").unwrap();
     let lower = ID128::from_str_sd("1234567890abcdef1234567890abcdef").unwrap();
     assert_eq!(upper.as_ref(), lower.as_ref());
 }
+
+#[test]
+fn new_v6_version_and_variant() {
+    let id = ID128::new_v6().unwrap();
+    assert_eq!(id.as_ref()[6] >> 4, 6);
+    assert_eq!(id.as_ref()[8] >> 6, 2);
+}
+
+#[test]
+fn new_v6_is_time_ordered() {
+    let first = ID128::new_v6().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    let second = ID128::new_v6().unwrap();
+    assert!(first.as_ref()[..6] < second.as_ref()[..6]);
+}
+
+#[test]
+fn new_v8_keeps_custom_bits() {
+    let id = ID128::new_v8([0xFF; 16]);
+    assert_eq!(id.as_ref(), &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x8F,
+                              0xFF, 0xBF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                              0xFF, 0xFF]);
+}
+
+#[test]
+fn new_v1_layout() {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    let id = ID128::new_v1(time, 0x1234, [1, 2, 3, 4, 5, 6]).unwrap();
+    assert_eq!(id.to_string(), "83424000-a4c4-11d5-9234-010203040506");
+}
+
+#[test]
+fn new_v1_far_future_fails() {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(u64::MAX / 2);
+    assert_eq!(ID128::new_v1(time, 0, [0; 6]),
+               Err(sd_id128::Error::SDError(-libc::EINVAL)));
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(120_000_000_000);
+    assert!(ID128::new_v1(time, 0, [0; 6]).is_err());
+}
+
+#[test]
+fn node_of_interface_loopback() {
+    if let Ok(node) = ID128::node_of_interface("lo") {
+        assert_eq!(node, [0; 6]);
+    }
+}
+
+#[test]
+fn node_of_interface_missing_fails() {
+    assert!(ID128::node_of_interface("does-not-exist").is_err());
+}
+
+#[test]
+fn node_of_interface_rejects_paths() {
+    for interface in &["", ".", "..", "../../../etc", "lo/../lo", ".hidden"] {
+        match ID128::node_of_interface(interface) {
+            Err(sd_id128::Error::ParseStringError(error)) => {
+                assert_eq!(error.input(), *interface);
+                assert!(error.to_string().ends_with("while parsing interface name"));
+            },
+            result => panic!("{:?}", result)
+        }
+    }
+}
+
+#[test]
+fn timestamp_v1() {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    let id = ID128::new_v1(time, 0x1234, [1, 2, 3, 4, 5, 6]).unwrap();
+    assert_eq!(id.timestamp(), Some(time));
+}
+
+#[test]
+fn timestamp_v6() {
+    let before = std::time::SystemTime::now();
+    let timestamp = ID128::new_v6().unwrap().timestamp().unwrap();
+    assert!(timestamp >= before - std::time::Duration::from_micros(1));
+    assert!(timestamp <= std::time::SystemTime::now());
+}
+
+#[test]
+fn timestamp_v7() {
+    let id = ID128::from_str("017f22e2-79b0-7cc3-98c4-dc0c0c07398f").unwrap();
+    assert_eq!(id.timestamp(),
+               Some(std::time::UNIX_EPOCH + std::time::Duration::from_millis(0x017F22E279B0)));
+}
+
+#[test]
+fn timestamp_random_is_none() {
+    assert_eq!(ID128::random_id().unwrap().timestamp(), None);
+}
+
+#[test]
+fn validate_rfc4122() {
+    let random = ID128::random_id().unwrap();
+    assert_eq!(random.clone().validate_rfc4122(), Ok(random));
+    match ID128::new_v8([0u8; 16]).validate_rfc4122() {
+        Err(sd_id128::Error::ParseStringError(error)) => {
+            assert_eq!(error.problem(), ParseProblem::WrongVersion);
+            assert_eq!(error.position(), 12);
+            assert_eq!(error.to_string(),
+                       "wrong version '8' at position 12 while parsing random UUID");
+        },
+        result => panic!("{:?}", result)
+    }
+    let no_variant = ID128::from_str("0123456789ab4def0123456789abcdef").unwrap();
+    match no_variant.validate_rfc4122() {
+        Err(sd_id128::Error::ParseStringError(error)) => {
+            assert_eq!(error.problem(), ParseProblem::WrongVariant);
+            assert_eq!(error.position(), 16);
+        },
+        result => panic!("{:?}", result)
+    }
+}
+
+#[cfg(feature = "rand_core")]
+struct CountingRng(u8);
+
+#[cfg(feature = "rand_core")]
+impl rand_core::RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            *byte = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[test]
+#[cfg(feature = "rand_core")]
+fn random_with_deterministic_rng() {
+    let id = ID128::random_with(&mut CountingRng(0));
+    assert_eq!(id.to_string(), "00010203-0405-4607-8809-0a0b0c0d0e0f");
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn rand_gen() {
+    use rand::Rng;
+    let mut rng = rand::rngs::mock::StepRng::new(0, 1);
+    let first: ID128 = rng.gen();
+    let second: ID128 = rng.gen();
+    assert_ne!(first, second);
+    assert_eq!(first.as_ref()[6] >> 4, 4);
+    assert_eq!(first.as_ref()[8] >> 6, 2);
+}
+
+#[test]
+fn random_batch() {
+    let batch = ID128::random_batch(100).unwrap();
+    assert_eq!(batch.len(), 100);
+    for (index, id) in batch.iter().enumerate() {
+        assert_eq!(id.as_ref()[6] >> 4, 4);
+        assert_eq!(id.as_ref()[8] >> 6, 2);
+        assert!(batch[index + 1..].iter().all(|other| other != id));
+    }
+}
+
+#[test]
+fn random_batch_empty() {
+    assert!(ID128::random_batch(0).unwrap().is_empty());
+}
+
+#[test]
+fn random_batch_overflow_fails() {
+    assert_eq!(ID128::random_batch(usize::MAX / 8),
+               Err(sd_id128::Error::SDError(-libc::EINVAL)));
+}
+
+#[test]
+fn from_seed_is_deterministic() {
+    assert_eq!(ID128::from_seed(42), ID128::from_seed(42));
+    assert_ne!(ID128::from_seed(42), ID128::from_seed(43));
+}
+
+#[test]
+fn from_seed_known_value() {
+    assert_eq!(ID128::from_seed(0).to_string(),
+               "e220a839-7b1d-4daf-ae78-9e6aa1b965f4");
+}
+
+#[test]
+fn from_seed_bytes_is_deterministic() {
+    assert_eq!(ID128::from_seed_bytes(b"fixture"),
+               ID128::from_seed_bytes(b"fixture"));
+    assert_ne!(ID128::from_seed_bytes(b"fixture"),
+               ID128::from_seed_bytes(b"fixturf"));
+}
+
+#[test]
+#[cfg(feature = "getrandom")]
+fn random_id_getrandom() {
+    let random1 = ID128::random_id_getrandom().unwrap();
+    let random2 = ID128::random_id_getrandom().unwrap();
+    assert_ne!(random1, random2);
+    assert_eq!(random1.as_ref()[6] >> 4, 4);
+    assert_eq!(random1.as_ref()[8] >> 6, 2);
+}
+
+#[test]
+fn fast_random_id() {
+    let random1 = ID128::fast_random_id().unwrap();
+    let random2 = ID128::fast_random_id().unwrap();
+    assert_ne!(random1, random2);
+    assert_eq!(random1.as_ref()[6] >> 4, 4);
+    assert_eq!(random1.as_ref()[8] >> 6, 2);
+}
+
+#[test]
+fn fast_random_id_differs_between_threads() {
+    let local = ID128::fast_random_id().unwrap();
+    let other = std::thread::spawn(|| ID128::fast_random_id().unwrap()).join()
+                                                                       .unwrap();
+    assert_ne!(local, other);
+}
+
+#[test]
+fn fast_random_id_reseeds() {
+    let ids = (0..100_000).map(|_| ID128::fast_random_id().unwrap().into_raw_value())
+                          .collect::<std::collections::HashSet<_>>();
+    assert_eq!(ids.len(), 100_000);
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn rotating_id_within_window() {
+    let base = ID128::from_seed(1);
+    let app = ID128::from_seed(2);
+    let period = std::time::Duration::from_secs(3600);
+    let start = std::time::UNIX_EPOCH + std::time::Duration::from_secs(7200);
+    assert_eq!(ID128::rotating_id_at(&base, &app, period, start),
+               ID128::rotating_id_at(&base,
+                                     &app,
+                                     period,
+                                     start + std::time::Duration::from_secs(3599)));
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn rotating_id_across_windows() {
+    let base = ID128::from_seed(1);
+    let app = ID128::from_seed(2);
+    let period = std::time::Duration::from_secs(3600);
+    let start = std::time::UNIX_EPOCH + std::time::Duration::from_secs(7200);
+    assert_ne!(ID128::rotating_id_at(&base, &app, period, start),
+               ID128::rotating_id_at(&base, &app, period, start + period));
+    assert_ne!(ID128::rotating_id_at(&base, &app, period, start), base);
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn rotating_id_known_value() {
+    let base = ID128::default();
+    let app = ID128::default();
+    let period = std::time::Duration::from_secs(1);
+    let id = ID128::rotating_id_at(&base, &app, period, std::time::UNIX_EPOCH);
+    assert_eq!(id.to_string(), "527f4196-495c-4733-8707-f441fa5e37e6");
+}
+
+#[test]
+#[cfg(any(systemd_v255,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+fn app_specific() {
+    let app = ID128::random_id().unwrap();
+    let machine = ID128::machine_id().unwrap();
+    let hashed = ID128::app_specific(machine.clone(), app.clone()).unwrap();
+    assert_ne!(machine, hashed);
+    #[cfg(any(systemd_v233,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+    assert_eq!(hashed, ID128::machine_id_app_specific(app).unwrap());
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn app_specific_native_known_value() {
+    let base = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let app = ID128::from_str("fedcba9876543210fedcba9876543210").unwrap();
+    assert_eq!(ID128::app_specific_native(base, app).to_string(),
+               "64a4ee5d-a1a7-4323-8ba8-b5418f428e41");
+}
+
+#[test]
+#[cfg(all(feature = "crypto",
+          any(systemd_v233,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))))]
+fn machine_id_app_specific_native_parity() {
+    let app = ID128::random_id().unwrap();
+    assert_eq!(ID128::machine_id_app_specific_native(app.clone()).unwrap(),
+               ID128::machine_id_app_specific(app).unwrap());
+}
+
+#[test]
+#[cfg(all(feature = "crypto",
+          any(systemd_v240,
+              all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi")))))]
+fn boot_id_app_specific_native_parity() {
+    let app = ID128::random_id().unwrap();
+    assert_eq!(ID128::boot_id_app_specific_native(app.clone()).unwrap(),
+               ID128::boot_id_app_specific(app).unwrap());
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn derive_chain() {
+    let machine = ID128::from_seed(1);
+    let app = ID128::from_seed(2);
+    let sub = ID128::from_seed(3);
+    let app_id = machine.derive(&app);
+    assert_eq!(app_id,
+               ID128::app_specific_native(machine.clone(), app.clone()));
+    let sub_id = machine.derive(&app).derive(&sub);
+    assert_eq!(sub_id, ID128::app_specific_native(app_id, sub.clone()));
+    assert_ne!(sub_id, machine.derive(&sub).derive(&app));
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn app_id_from_name_rfc9562_example() {
+    assert_eq!(ID128::app_id_from_name("www.example.com").to_string(),
+               "5c146b14-3c52-8afd-938a-375d0df1fbf6");
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn app_id_from_name_distinct() {
+    assert_ne!(ID128::app_id_from_name("com.example.service"),
+               ID128::app_id_from_name("com.example.other"));
+}
+
+fn temporary_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("sd-id128-{}-{}-{}",
+                                      name,
+                                      std::process::id(),
+                                      ID128::random_id().unwrap()))
+}
+
+#[test]
+fn persistent_creates_and_loads() {
+    let path = temporary_path("persistent");
+    let created = ID128::persistent(&path).unwrap();
+    let loaded = ID128::persistent(&path).unwrap();
+    assert_eq!(created, loaded);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(),
+               format!("{}\n",
+                       created.to_string_formatted(Format::LibSystemD, Case::Lower)));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn persistent_concurrent_threads_agree() {
+    let path = std::sync::Arc::new(temporary_path("persistent-threads"));
+    let threads = (0..8).map(|_| {
+                            let path = path.clone();
+                            std::thread::spawn(move || ID128::persistent(&*path).unwrap())
+                        })
+                        .collect::<Vec<_>>();
+    let ids = threads.into_iter()
+                     .map(|thread| thread.join().unwrap())
+                     .collect::<Vec<_>>();
+    assert!(ids.iter().all(|id| *id == ids[0]));
+    std::fs::remove_file(&*path).unwrap();
+}
+
+#[test]
+fn persistent_invalid_content_fails() {
+    let path = temporary_path("persistent-invalid");
+    std::fs::write(&path, "no id\n").unwrap();
+    assert!(ID128::persistent(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn persistent_missing_directory_fails() {
+    let path = temporary_path("persistent-missing").join("id");
+    assert!(ID128::persistent(&path).is_err());
+}
+
+#[test]
+#[cfg(all(feature = "ffi", not(feature = "no-ffi")))]
+fn capabilities_of_linked_libsystemd() {
+    let capabilities = sd_id128::capabilities();
+    assert!(capabilities.libsystemd);
+    if cfg!(systemd_v240) {
+        assert!(capabilities.boot_id_app_specific);
+        assert!(capabilities.machine_id_app_specific);
+        assert!(capabilities.invocation_id);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "static", not(feature = "no-ffi")))]
+fn capabilities_of_static_libsystemd() {
+    let capabilities = sd_id128::capabilities();
+    assert!(capabilities.libsystemd);
+    assert_eq!(capabilities.invocation_id, cfg!(systemd_v232));
+    assert_eq!(capabilities.machine_id_app_specific, cfg!(systemd_v233));
+    assert_eq!(capabilities.boot_id_app_specific, cfg!(systemd_v240));
+    assert_eq!(capabilities.app_specific, cfg!(systemd_v255));
+}
+
+#[test]
+#[cfg(feature = "no-ffi")]
+fn capabilities_without_libsystemd() {
+    assert_eq!(sd_id128::capabilities(), sd_id128::Capabilities::default());
+}
+
+#[test]
+fn machine_id_of_root() {
+    let root = temporary_path("root");
+    std::fs::create_dir_all(root.join("etc")).unwrap();
+    assert_eq!(ID128::machine_id_of(&root),
+               Err(sd_id128::Error::SDError(-libc::ENOENT)));
+    let id = ID128::from_seed(42);
+    std::fs::write(root.join("etc/machine-id"),
+                   format!("{}\n",
+                           id.to_string_formatted(Format::LibSystemD, Case::Lower))).unwrap();
+    assert_eq!(ID128::machine_id_of(&root).unwrap(), id);
+    std::fs::write(root.join("etc/machine-id"), "uninitialized\n").unwrap();
+    assert_eq!(ID128::machine_id_of(&root),
+               Err(sd_id128::Error::SDError(-libc::ENOPKG)));
+    std::fs::write(root.join("etc/machine-id"), "").unwrap();
+    assert_eq!(ID128::machine_id_of(&root),
+               Err(sd_id128::Error::SDError(-libc::ENOMEDIUM)));
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+#[cfg(feature = "dbus")]
+fn machine_id_of_unknown_container_fails() {
+    assert!(ID128::machine_id_of_container("sd-id128-no-such-container").is_err());
+    assert!(ID128::machine_id_of_container("invalid\0name").is_err());
+}
+
+#[test]
+#[cfg(feature = "dbus")]
+fn machine_id_via_hostnamed() {
+    if let Ok(machine) = ID128::machine_id_via_hostnamed() {
+        assert_eq!(machine, ID128::machine_id().unwrap());
+    }
+}
+
+#[test]
+#[cfg(feature = "dbus")]
+fn bus_encoding_signatures() {
+    use sd_id128::bus::Encoding;
+    assert_eq!(Encoding::Bytes.signature(), "ay");
+    assert_eq!(Encoding::String.signature(), "s");
+}
+
+#[test]
+fn invocation_id_from_env() {
+    let _environment = lock_environment();
+    let id = ID128::from_seed(7);
+    std::env::set_var("INVOCATION_ID",
+                      id.to_string_formatted(Format::LibSystemD, Case::Lower));
+    assert_eq!(ID128::invocation_id_from_env().unwrap(), id);
+    std::env::set_var("INVOCATION_ID", "no invocation id");
+    assert!(matches!(ID128::invocation_id_from_env(),
+                     Err(sd_id128::Error::ParseStringError(..))));
+    std::env::set_var("INVOCATION_ID", "00000000000000000000000000000000");
+    assert_eq!(ID128::invocation_id_from_env(),
+               Err(sd_id128::Error::NotUnderSystemd));
+    std::env::remove_var("INVOCATION_ID");
+    let error = ID128::invocation_id_from_env().unwrap_err();
+    assert_eq!(error, sd_id128::Error::NotUnderSystemd);
+    assert!(error.is_not_under_systemd());
+    assert_eq!(error.errno(), Some(libc::ENXIO));
+}
+
+#[test]
+#[cfg(feature = "dbus")]
+fn invocation_id_of_unknown_unit_fails() {
+    assert!(ID128::invocation_id_of_unit("sd-id128-no-such-unit.service").is_err());
+}
+
+#[test]
+fn from_credential() {
+    let _environment = lock_environment();
+    let directory = temporary_path("credentials");
+    std::fs::create_dir_all(&directory).unwrap();
+    let id = ID128::from_seed(3);
+    std::fs::write(directory.join("my-id"), format!("{}\n", id)).unwrap();
+    std::env::set_var("CREDENTIALS_DIRECTORY", &directory);
+    assert_eq!(ID128::from_credential("my-id").unwrap(), id);
+    assert_eq!(ID128::from_credential("other-id"),
+               Err(sd_id128::Error::SDError(-libc::ENOENT)));
+    assert_eq!(ID128::from_credential("../my-id"),
+               Err(sd_id128::Error::SDError(-libc::EINVAL)));
+    std::env::remove_var("CREDENTIALS_DIRECTORY");
+    assert_eq!(ID128::from_credential("my-id"),
+               Err(sd_id128::Error::SDError(-libc::ENXIO)));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn container_uuid() {
+    let _environment = lock_environment();
+    let id = ID128::from_seed(4);
+    std::env::set_var("container", "sd-id128-test");
+    std::env::set_var("container_uuid", id.to_string());
+    assert_eq!(ID128::container_uuid().unwrap(), Some(id));
+    std::env::set_var("container_uuid", "no uuid");
+    assert!(ID128::container_uuid().is_err());
+    std::env::remove_var("container_uuid");
+    std::env::remove_var("container");
+}
+
+#[test]
+fn from_smbios_uuid() {
+    let raw = [0x33, 0x22, 0x11, 0x00, 0x55, 0x44, 0x77, 0x66, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD,
+               0xEE, 0xFF];
+    assert_eq!(ID128::from_smbios_uuid(raw, 2, 6).to_string(),
+               "00112233-4455-6677-8899-aabbccddeeff");
+    assert_eq!(ID128::from_smbios_uuid(raw, 3, 0).to_string(),
+               "00112233-4455-6677-8899-aabbccddeeff");
+    assert_eq!(ID128::from_smbios_uuid(raw, 2, 5).into_raw_value(), raw);
+}
+
+#[test]
+fn dmi_product_uuid() {
+    match ID128::dmi_product_uuid() {
+        Ok(uuid) => assert_ne!(uuid, ID128::default()),
+        Err(error) => assert!(matches!(error, sd_id128::Error::SDError(_)))
+    }
+}
+
+#[test]
+#[cfg(all(feature = "portable", windows))]
+fn windows_machine_guid() {
+    assert_ne!(ID128::windows_machine_guid().unwrap(), ID128::default());
+}
+
+#[test]
+#[cfg(all(feature = "portable", target_os = "macos"))]
+fn macos_platform_uuid() {
+    assert_ne!(ID128::macos_platform_uuid().unwrap(), ID128::default());
+}
+
+#[test]
+#[cfg(all(feature = "portable",
+          any(target_os = "freebsd", target_os = "openbsd")))]
+fn bsd_host_uuid() {
+    assert_ne!(ID128::bsd_host_uuid().unwrap(), ID128::default());
+}
+
+#[test]
+fn machine_id_cached() {
+    let cached = ID128::machine_id_cached().unwrap();
+    assert_eq!(cached, &ID128::machine_id().unwrap());
+    assert_eq!(cached, ID128::machine_id_cached().unwrap());
+}
+
+#[test]
+fn boot_id_cached() {
+    assert_eq!(ID128::boot_id_cached().unwrap(), &ID128::boot_id().unwrap());
+}
+
+#[test]
+fn machine_id_uncached() {
+    assert_eq!(ID128::machine_id_uncached(), ID128::machine_id());
+}
+
+#[test]
+fn refresh() {
+    let refreshed = sd_id128::refresh().unwrap();
+    assert_eq!(refreshed, &ID128::machine_id_uncached().unwrap());
+    assert!(std::ptr::eq(refreshed, ID128::machine_id_cached().unwrap()));
+    // an unchanged machine ID keeps the cache entry instead of leaking a new one
+    assert!(std::ptr::eq(refreshed, sd_id128::refresh().unwrap()));
+}
+
+#[test]
+fn to_string_urn() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(id.to_string_formatted(Format::Urn, Case::Lower),
+               "urn:uuid:01234567-89ab-cdef-0123-456789abcdef");
+    assert_eq!(id.to_string_formatted(Format::Urn, Case::Upper),
+               "urn:uuid:01234567-89AB-CDEF-0123-456789ABCDEF");
+}
+
+#[test]
+fn from_string_urn() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(ID128::from_str("urn:uuid:01234567-89ab-cdef-0123-456789abcdef").unwrap(),
+               id);
+    assert_eq!(ID128::from_str("URN:UUID:01234567-89AB-CDEF-0123-456789ABCDEF").unwrap(),
+               id);
+    assert_eq!(ID128::from_str_lax(" urn:uuid:0123456789abcdef0123456789abcdef ").unwrap(),
+               id);
+    assert!(ID128::from_str("urn:uuid:0123456789abcdef0123456789abcdef").is_err());
+    assert_eq!(parse_failure(ID128::from_str("urn:uuid:01234567-89ab-cdef-0123-456789abcdeX")),
+               Some((ParseProblem::InvalidCharacter, 44)));
+}
+
+#[test]
+fn to_string_braced() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(id.to_string_formatted(Format::Braced, Case::Upper),
+               "{01234567-89AB-CDEF-0123-456789ABCDEF}");
+}
+
+#[test]
+fn from_string_braced() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(ID128::from_str("{01234567-89AB-CDEF-0123-456789ABCDEF}").unwrap(),
+               id);
+    assert_eq!(ID128::from_str_lax("{0123456789abcdef0123456789abcdef}\n").unwrap(),
+               id);
+    assert!(ID128::from_str("{0123456789abcdef0123456789abcdef}").is_err());
+    assert!(ID128::from_str("{01234567-89ab-cdef-0123-456789abcdef").is_err());
+    assert_eq!(parse_failure(ID128::from_str("{01234567-89ab-cdef-0123-456789abcdeX}")),
+               Some((ParseProblem::InvalidCharacter, 36)));
+}
+
+#[test]
+fn to_string_custom() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let colons = Format::Custom { group_sizes: &[4; 8],
+                                  separator:   ':' };
+    assert_eq!(id.to_string_formatted(colons, Case::Upper),
+               "0123:4567:89AB:CDEF:0123:4567:89AB:CDEF");
+    let partial = Format::Custom { group_sizes: &[2, 3],
+                                   separator:   ' ' };
+    assert_eq!(id.to_string_formatted(partial, Case::Lower),
+               "01 234 56789abcdef0123456789abcdef");
+    let whole = Format::Custom { group_sizes: &[32],
+                                 separator:   '.' };
+    assert_eq!(id.to_string_formatted(whole, Case::Lower),
+               "0123456789abcdef0123456789abcdef");
+}
+
+#[test]
+fn to_string_formats() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(id.to_string_formatted(Format::Simple, Case::Lower),
+               "0123-4567-89ab-cdef-0123-4567-89ab-cdef");
+    assert_eq!(id.to_string_formatted(Format::LibSystemD, Case::Upper),
+               "0123456789ABCDEF0123456789ABCDEF");
+    assert_eq!(id.to_string_formatted(Format::RFC, Case::Lower),
+               "01234567-89ab-cdef-0123-456789abcdef");
+}
+
+#[test]
+fn format_detect() {
+    assert_eq!(Format::detect("0123-4567-89ab-cdef-0123-4567-89ab-cdef"),
+               Some((Format::Simple, Case::Lower)));
+    assert_eq!(Format::detect("0123456789ABCDEF0123456789ABCDEF"),
+               Some((Format::LibSystemD, Case::Upper)));
+    assert_eq!(Format::detect("01234567-89ab-cdef-0123-456789abcdef"),
+               Some((Format::RFC, Case::Lower)));
+    assert_eq!(Format::detect("urn:uuid:01234567-89AB-CDEF-0123-456789ABCDEF"),
+               Some((Format::Urn, Case::Upper)));
+    assert_eq!(Format::detect("{01234567-89AB-CDEF-0123-456789ABCDEF}"),
+               Some((Format::Braced, Case::Upper)));
+    assert_eq!(Format::detect("01234567890123456789012345678901"),
+               Some((Format::LibSystemD, Case::Lower)));
+    assert_eq!(Format::detect("0123456789ABCDEF0123456789abcdef"), None);
+    assert_eq!(Format::detect("0123456789abcdef"), None);
+}
+
+#[test]
+fn format_from_str() {
+    assert_eq!("simple".parse::<Format>().unwrap(), Format::Simple);
+    assert_eq!("LibSystemD".parse::<Format>().unwrap(), Format::LibSystemD);
+    assert_eq!("RFC".parse::<Format>().unwrap(), Format::RFC);
+    assert_eq!("urn".parse::<Format>().unwrap(), Format::Urn);
+    assert_eq!("braced".parse::<Format>().unwrap(), Format::Braced);
+    assert!("custom".parse::<Format>().is_err());
+}
+
+#[test]
+fn case_from_str() {
+    assert_eq!("upper".parse::<Case>().unwrap(), Case::Upper);
+    assert_eq!("Lower".parse::<Case>().unwrap(), Case::Lower);
+    assert!("mixed".parse::<Case>().is_err());
+}
+
+#[test]
+fn format_options() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(id.to_string_with(&FormatOptions::new()), id.to_string());
+    assert_eq!((Format::default(), Case::default()),
+               (Format::RFC, Case::Lower));
+    let format = Format::Urn;
+    let options = FormatOptions::new().format(format).case(Case::Upper);
+    assert_eq!(id.to_string_with(&options),
+               id.to_string_formatted(format, Case::Upper));
+    assert_eq!(id.to_string_with(&options.prefix("id:")),
+               "id:urn:uuid:01234567-89AB-CDEF-0123-456789ABCDEF");
+}
+
+#[test]
+fn display_adapters() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(format!("{}", id.rfc()),
+               "01234567-89ab-cdef-0123-456789abcdef");
+    assert_eq!(format!("{}", id.simple().upper()),
+               "0123-4567-89AB-CDEF-0123-4567-89AB-CDEF");
+    assert_eq!(format!("{}", id.libsystemd()),
+               "0123456789abcdef0123456789abcdef");
+    assert_eq!(format!("{}", id.urn()),
+               "urn:uuid:01234567-89ab-cdef-0123-456789abcdef");
+    assert_eq!(format!("{}", id.braced().upper().lower()),
+               "{01234567-89ab-cdef-0123-456789abcdef}");
+    let custom = Format::Custom { group_sizes: &[4; 8],
+                                  separator:   ':' };
+    assert_eq!(format!("{}", id.formatted(custom, Case::Upper)),
+               id.to_string_formatted(custom, Case::Upper));
+}
+
+#[test]
+fn display_flags() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(format!("{:.8}", id), "01234567");
+    assert_eq!(format!("{:>40}", id),
+               "    01234567-89ab-cdef-0123-456789abcdef");
+    assert_eq!(format!("{:*<10.4}|", id), "0123******|");
+    assert_eq!(format!("{:^12.8}", id.libsystemd().upper()), "  01234567  ");
+    assert_eq!(format!("{:.100}", id.urn()), id.urn().to_string());
+}
+
+#[test]
+fn hex() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(format!("{:x}", id), "0123456789abcdef0123456789abcdef");
+    assert_eq!(format!("{:X}", id), "0123456789ABCDEF0123456789ABCDEF");
+    assert_eq!(format!("{:#x}", id), "0x0123456789abcdef0123456789abcdef");
+    assert_eq!(format!("{:>34x}", id), "  0123456789abcdef0123456789abcdef");
+}
+
+#[test]
+fn display_alternate() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(format!("{:#}", id), "0123456789abcdef0123456789abcdef");
+    assert_eq!(format!("{:#.12}", id), "0123456789ab");
+}
+
+#[test]
+fn encoded_len() {
+    let id = ID128::random_id().unwrap();
+    let formats = [Format::Simple,
+                   Format::LibSystemD,
+                   Format::RFC,
+                   Format::Urn,
+                   Format::Braced,
+                   Format::Custom { group_sizes: &[0, 2, 40],
+                                    separator:   '·' }];
+    for format in formats.iter() {
+        let string = id.to_string_formatted(*format, Case::Upper);
+        assert_eq!(string.len(), format.encoded_len());
+    }
+}
+
+#[test]
+fn encode_into() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let mut buffer = [0u8; 64];
+    assert_eq!(id.encode_into(&mut buffer, Format::Urn, Case::Lower)
+                 .unwrap(),
+               "urn:uuid:01234567-89ab-cdef-0123-456789abcdef");
+    assert_eq!(id.encode_into(&mut buffer[..35], Format::RFC, Case::Lower),
+               Err(sd_id128::Error::SDError(-libc::ERANGE)));
+    assert_eq!(&id.to_libsystemd_bytes(Case::Upper),
+               b"0123456789ABCDEF0123456789ABCDEF");
+    assert_eq!(&id.to_rfc_bytes(Case::Lower),
+               b"01234567-89ab-cdef-0123-456789abcdef");
+}
+
+#[test]
+fn write_to() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let mut log = String::from("boot=");
+    id.write_to(&mut log, Format::LibSystemD, Case::Lower)
+      .unwrap();
+    assert_eq!(log, "boot=0123456789abcdef0123456789abcdef");
+    let mut file: Vec<u8> = b"id=".to_vec();
+    id.write_io(&mut file, Format::Braced, Case::Upper).unwrap();
+    assert_eq!(file, b"id={01234567-89AB-CDEF-0123-456789ABCDEF}".to_vec());
+    let long = Format::Custom { group_sizes: &[1; 31],
+                                separator:   '-' };
+    let mut long_file = Vec::new();
+    id.write_io(&mut long_file, long, Case::Lower).unwrap();
+    assert_eq!(long_file,
+               id.to_string_formatted(long, Case::Lower).into_bytes());
+}
+
+#[test]
+#[cfg(feature = "arrayvec")]
+fn to_array_string() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let rfc = id.to_array_string::<36>(Format::RFC, Case::Lower).unwrap();
+    assert_eq!(&*rfc, "01234567-89ab-cdef-0123-456789abcdef");
+    assert_eq!(id.to_array_string::<36>(Format::Urn, Case::Lower).err(),
+               Some(sd_id128::Error::SDError(-libc::ERANGE)));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_format_and_case() {
+    assert_eq!(serde_json::to_string(&Format::LibSystemD).unwrap(),
+               "\"libsystemd\"");
+    assert_eq!(serde_json::to_string(&Case::Upper).unwrap(), "\"upper\"");
+    assert_eq!(serde_json::from_str::<Format>("\"urn\"").unwrap(),
+               Format::Urn);
+    assert_eq!(serde_json::from_str::<Case>("\"Lower\"").unwrap(),
+               Case::Lower);
+    assert!(serde_json::from_str::<Format>("\"hex\"").is_err());
+    let custom = Format::Custom { group_sizes: &[4; 8],
+                                  separator:   ':' };
+    assert!(serde_json::to_string(&custom).is_err());
+}
+
+#[test]
+fn from_string_lax_with_options() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let defaults = LaxOptions::default();
+    assert_eq!(ID128::from_str_lax_with(" {01234567-89ab-cdef-0123-456789abcdef} ", &defaults).unwrap(),
+               id);
+    assert!(ID128::from_str_lax_with("0x0123456789abcdef0123456789abcdef", &defaults).is_err());
+    let hex = LaxOptions::default().hex_prefix(true);
+    assert_eq!(ID128::from_str_lax_with("0X0123456789abcdef0123456789abcdef", &hex).unwrap(),
+               id);
+    let strict = LaxOptions::default().dashes(false);
+    assert!(!strict.dashes && strict.braces);
+    assert!(ID128::from_str_lax_with("0123-4567-89ab-cdef0123456789abcdef", &strict).is_err());
+    let permissive = LaxOptions::permissive();
+    assert_eq!(ID128::from_str_lax_with("0123 4567_89ab:cdef\n0123-4567 89ab cdef", &permissive).unwrap(),
+               id);
+    assert!(ID128::from_str_lax_with("0123 4567_89ab:cdef", &permissive).is_err());
+}
+
+#[test]
+fn parse_detailed() {
+    for source in &["0123-4567-89ab-cdef-0123-4567-89ab-cdef",
+                    "0123456789ABCDEF0123456789ABCDEF",
+                    "01234567-89ab-cdef-0123-456789abcdef",
+                    "urn:uuid:01234567-89AB-CDEF-0123-456789ABCDEF",
+                    "{01234567-89ab-cdef-0123-456789abcdef}"]
+    {
+        let (id, format, case) = ID128::parse_detailed(source).unwrap();
+        assert_eq!(id, ID128::from_str(source).unwrap());
+        assert_eq!(id.to_string_formatted(format, case), *source);
+    }
+    let prefix = ID128::parse_detailed("urn:UUID:01234567-89ab-cdef-0123-456789abcdef");
+    assert_eq!(parse_failure(prefix),
+               Some((ParseProblem::InvalidCharacter, 4)));
+    let (_, format, case) =
+        ID128::parse_detailed("{01234567-89AB-CDEF-0123-456789ABCDEF}").unwrap();
+    assert_eq!((format, case), (Format::Braced, Case::Upper));
+    let mixed = ID128::parse_detailed("{01234567-89Ab-cdef-0123-456789abcdef}");
+    assert_eq!(parse_failure(mixed), Some((ParseProblem::MixedCase, 13)));
+    assert!(ID128::parse_detailed("01234567-89ab-cdef-0123-456789abcde").is_err());
+}
+
+#[test]
+fn from_str_format() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let custom = Format::Custom { group_sizes: &[16, 16],
+                                  separator:   '_' };
+    for format in &[Format::Simple,
+                    Format::LibSystemD,
+                    Format::RFC,
+                    Format::Urn,
+                    Format::Braced,
+                    custom]
+    {
+        for case in &[Case::Lower, Case::Upper] {
+            let source = id.to_string_formatted(*format, *case);
+            assert_eq!(ID128::from_str_format(&source, *format).unwrap(), id);
+        }
+    }
+    assert_eq!(ID128::from_str_format("URN:UUID:01234567-89ab-cdef-0123-456789abcdef",
+                                      Format::Urn).unwrap(),
+               id);
+    let rfc = "01234567-89ab-cdef-0123-456789abcdef";
+    assert!(ID128::from_str_format(rfc, Format::LibSystemD).is_err());
+    assert!(ID128::from_str_format(rfc, Format::Braced).is_err());
+    assert_eq!(parse_failure(ID128::from_str_format("0123456-789ab-cdef-0123-456789abcdef",
+                                                    Format::RFC)),
+               Some((ParseProblem::InvalidCharacter, 7)));
+    assert_eq!(parse_failure(ID128::from_str_format("{01234567-89ab-cdef-0123-456789abcdef)",
+                                                    Format::Braced)),
+               Some((ParseProblem::InvalidCharacter, 37)));
+    assert!(ID128::from_str_format("0123456789abcdef-0123456789abcdef", custom).is_err());
+}
+
+#[test]
+fn from_ascii() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(ID128::from_ascii(b"0123456789ABCDEF0123456789ABCDEF").unwrap(),
+               id);
+    assert_eq!(ID128::from_ascii(b"{01234567-89ab-cdef-0123-456789abcdef}").unwrap(),
+               id);
+    assert_eq!(ID128::from_ascii(b"urn:uuid:01234567-89ab-cdef-0123-456789abcdef").unwrap(),
+               id);
+    assert_eq!(parse_failure(ID128::from_ascii(b"0123456789abcdef\xff123456789abcdef")),
+               Some((ParseProblem::InvalidCharacter, 16)));
+}
+
+#[test]
+fn libsystemd_format_round_trip() {
+    for _ in 0..64 {
+        let id = ID128::random_id().unwrap();
+        let lower = id.as_raw_value()
+                      .iter()
+                      .map(|byte| format!("{:02x}", byte))
+                      .collect::<String>();
+        assert_eq!(id.to_string_formatted(Format::LibSystemD, Case::Lower),
+                   lower);
+        assert_eq!(id.to_string_formatted(Format::LibSystemD, Case::Upper),
+                   lower.to_uppercase());
+        assert_eq!(ID128::from_str(&lower).unwrap(), id);
+        assert_eq!(ID128::from_str(&lower.to_uppercase()).unwrap(), id);
+    }
+    for position in 0..32 {
+        for invalid in &[b'g', b'G', b'/', b':', b'@', b'`', 0xFF] {
+            let mut hex = *b"0123456789abcdef0123456789ABCDEF";
+            hex[position] = *invalid;
+            assert_eq!(parse_failure(ID128::from_ascii(&hex)),
+                       Some((ParseProblem::InvalidCharacter, position)));
+        }
+    }
+}
+
+#[test]
+fn parse_many() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let list = "0123456789abcdef0123456789abcdef,\n{01234567-89ab-cdef-0123-456789abcdef}\t,,\n";
+    let ids = ID128::parse_many(list).collect::<Result<Vec<_>, _>>()
+                                     .unwrap();
+    assert_eq!(ids, vec![id.clone(), id]);
+    assert_eq!(ID128::parse_many(" \n, ").count(), 0);
+    let results = ID128::parse_many("0123456789abcdef0123456789abcdeX 0123 \
+                                     0123456789abcdef0123456789abcdef").collect::<Vec<_>>();
+    assert_eq!(results.len(), 3);
+    let mut results = results.into_iter();
+    assert_eq!(parse_failure(results.next().unwrap()),
+               Some((ParseProblem::InvalidCharacter, 31)));
+    assert_eq!(parse_failure(results.next().unwrap()),
+               Some((ParseProblem::InvalidLength(4), 33)));
+    assert!(results.next().unwrap().is_ok());
+}
+
+#[test]
+fn is_valid() {
+    for valid in &["0123456789abcdef0123456789ABCDEF",
+                   "01234567-89ab-cdef-0123-456789abcdef",
+                   "0123-4567-89ab-cdef-0123-4567-89ab-cdef",
+                   "URN:uuid:01234567-89ab-cdef-0123-456789abcdef",
+                   "{01234567-89ab-cdef-0123-456789abcdef}"]
+    {
+        assert!(ID128::is_valid(valid));
+    }
+    for invalid in &["",
+                     "0123456789abcdef0123456789abcdeg",
+                     "01234567-89ab-cdef-0123-456789abcde-",
+                     "0123456-789ab-cdef-0123-456789abcdef",
+                     "{0123456789abcdef0123456789abcdef}",
+                     "urn:uuid:0123456789abcdef0123456789abcdef",
+                     "01234567-89ab-cdéf-0123-456789abcde"]
+    {
+        assert!(!ID128::is_valid(invalid));
+        assert!(ID128::from_str(invalid).is_err());
+    }
+    assert!(ID128::is_valid_format("0123456789abcdef0123456789abcdef", Format::LibSystemD));
+    assert!(!ID128::is_valid_format("01234567-89ab-cdef-0123-456789abcdef", Format::LibSystemD));
+}
+
+#[test]
+fn is_valid_format_matches_from_str_format() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let formats = [Format::Simple,
+                   Format::LibSystemD,
+                   Format::RFC,
+                   Format::Urn,
+                   Format::Braced,
+                   Format::Custom { group_sizes: &[4; 8],
+                                    separator:   ':' },
+                   Format::Custom { group_sizes: &[2, 3],
+                                    separator:   '.' }];
+    let mut candidates = vec![String::new(),
+                              String::from("URN:UUID:01234567-89ab-cdef-0123-456789abcdef"),
+                              String::from("{01234567-89ab-cdef-0123-456789abcdef)"),
+                              String::from("01234567-89ab-cdef-0123-456789abcdeg"),
+                              String::from("0123456789abcdef0123456789abcdéf")];
+    for format in &formats {
+        candidates.push(id.to_string_formatted(*format, Case::Upper));
+        candidates.push(id.to_string_formatted(*format, Case::Lower)
+                          .replacen('-', ":", 1));
+    }
+    for format in &formats {
+        for candidate in &candidates {
+            assert_eq!(ID128::is_valid_format(candidate, *format),
+                       ID128::from_str_format(candidate, *format).is_ok(),
+                       "{} {:?}",
+                       candidate,
+                       format);
+        }
+    }
+}
+
+#[test]
+fn equal_str() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    for format in &[Format::Simple,
+                    Format::LibSystemD,
+                    Format::RFC,
+                    Format::Urn,
+                    Format::Braced]
+    {
+        assert!(id.equal_str(&id.to_string_formatted(*format, Case::Upper)));
+        assert!(id.equal_str(&id.to_string_formatted(*format, Case::Lower)));
+    }
+    assert!(!id.equal_str("0123456789abcdef0123456789abcdee"));
+    assert!(!id.equal_str("1123456789abcdef0123456789abcdef"));
+    assert!(!id.equal_str("0123456789abcdef0123456789abcdeg"));
+    assert!(!id.equal_str("0123456789abcdef0123456789abcde"));
+    assert!(!id.equal_str(""));
+}
+
+#[test]
+fn matches_prefix() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert!(id.matches_prefix(""));
+    assert!(id.matches_prefix("0"));
+    assert!(id.matches_prefix("0123456789AB"));
+    assert!(id.matches_prefix("0123-4567-89"));
+    assert!(id.matches_prefix("01234567-89ab-cdef-0123-456789abcdef"));
+    assert!(!id.matches_prefix("1"));
+    assert!(!id.matches_prefix("012g"));
+    assert!(!id.matches_prefix("0123456789abcdef0123456789abcdef0"));
+    let ids = [id.clone(),
+               ID128::from_str("fedcba9876543210fedcba9876543210").unwrap()];
+    let found = ids.iter()
+                   .filter(|id| id.matches_prefix("fedc"))
+                   .collect::<Vec<_>>();
+    assert_eq!(found, vec![&ids[1]]);
+}
+
+#[test]
+fn well_known_ids() {
+    use sd_id128::{gpt, messages};
+    let all = gpt::PARTITION_TYPES.iter()
+                                  .map(|info| (info.name, &info.id))
+                                  .chain(messages::MESSAGE_IDS.iter()
+                                                              .map(|(name, id, _)| (*name, id)))
+                                  .collect::<Vec<_>>();
+    for (index, (name, id)) in all.iter().enumerate() {
+        assert!(all[index + 1..].iter()
+                                .all(|(other_name, other_id)| other_name != name
+                                                              && other_id != id));
+    }
+    assert_eq!(gpt::ROOT_X86_64,
+               ID128::from_str("4f68bce3-e8cd-4db1-96e7-fbcaf984b709").unwrap());
+    assert_eq!(messages::JOURNAL_START,
+               ID128::from_str("f77379a8490b408bbe5f6940505a777b").unwrap());
+}
+
+#[test]
+fn error_display() {
+    assert_eq!(sd_id128::Error::SDError(-libc::ENOENT).to_string(),
+               "ENOENT: no such file or directory");
+    assert_eq!(sd_id128::Error::SDError(-libc::ENXIO).errno(),
+               Some(libc::ENXIO));
+    assert_eq!(sd_id128::Error::SDError(-libc::ENXIO).to_string(),
+               "ENXIO: no such device or address");
+    assert!(!sd_id128::Error::SDError(-libc::ENOTDIR).to_string()
+                                                     .is_empty());
+    assert!(!sd_id128::Error::SDError(0).to_string().is_empty());
+    assert_eq!(ID128::from_str("0123").unwrap_err().errno(), None);
+}
+
+#[test]
+fn error_into_io_error() {
+    let error = std::io::Error::from(sd_id128::Error::SDError(-libc::ENOENT));
+    assert_eq!(error.raw_os_error(), Some(libc::ENOENT));
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    let error: std::io::Error = ID128::from_str("no id").unwrap_err().into();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(sd_id128::Error::SDError(0).raw_os_error(), None);
+}
+
+#[test]
+fn error_sd_errno() {
+    use sd_id128::SdErrno;
+    assert_eq!(sd_id128::Error::SDError(-libc::ENOMEDIUM).sd_errno(),
+               Some(SdErrno::ENOMEDIUM));
+    assert_eq!(SdErrno::from_errno(libc::ENOTDIR),
+               SdErrno::Other(libc::ENOTDIR));
+    assert_eq!(SdErrno::ENXIO.errno(), libc::ENXIO);
+    assert_eq!(SdErrno::EUCLEAN.name(), Some("EUCLEAN"));
+    assert_eq!(SdErrno::Other(libc::ENOTDIR).name(), None);
+    for sd_errno in [SdErrno::ENOMEDIUM, SdErrno::ENOPKG, SdErrno::EUCLEAN].iter() {
+        assert_eq!(SdErrno::from_errno(sd_errno.errno()), *sd_errno);
+    }
+    assert_eq!(ID128::from_str("no id").unwrap_err().sd_errno(), None);
+}
+
+#[test]
+fn result_alias() {
+    fn parse(string: &str) -> sd_id128::Result<ID128> {
+        let id128 = ID128::from_str(string)?;
+        Ok(id128)
+    }
+    assert!(parse("0123456789abcdef0123456789abcdef").is_ok());
+    assert!(parse("no id").is_err());
+}
+
+#[test]
+fn error_predicates() {
+    assert!(sd_id128::Error::SDError(-libc::ENOENT).is_not_found());
+    assert!(sd_id128::Error::SDError(-libc::ENOPKG).is_not_found());
+    assert!(!sd_id128::Error::SDError(-libc::EACCES).is_not_found());
+    assert!(sd_id128::Error::SDError(-libc::EACCES).is_permission_denied());
+    assert!(sd_id128::Error::NotUnderSystemd.is_not_under_systemd());
+    assert!(!sd_id128::Error::SDError(-libc::ENXIO).is_not_under_systemd());
+    assert!(ID128::from_str("no id").unwrap_err().is_parse_error());
+    assert!(!sd_id128::Error::SDError(-libc::EINVAL).is_parse_error());
+}
+
+#[test]
+fn parse_error_context() {
+    let error = match ID128::from_str("0123-4567-89A-BCDEF-0123-4567-89AB-CDEF") {
+        Err(sd_id128::Error::ParseStringError(error)) => error,
+        _ => panic!()
+    };
+    assert_eq!(error.input(), "0123-4567-89A-BCDEF-0123-4567-89AB-CDEF");
+    assert_eq!(error.expected(), &[Format::Simple]);
+    assert_eq!(error.to_string(),
+               "unexpected '-' at position 13 while parsing simple format");
+    assert_eq!(ID128::from_str("0123").unwrap_err().to_string(),
+               "invalid length 4 at position 0 while parsing simple, libsystemd, RFC, URN or \
+                braced format");
+    assert_eq!(ID128::parse_detailed("0123456789aBcdef0123456789abcdef").unwrap_err()
+                                                                        .to_string(),
+               "letter 'B' of mixed case at position 11 while parsing libsystemd format");
+    assert_eq!("json".parse::<Format>().unwrap_err().to_string(),
+               "unknown name \"json\" at position 0 while parsing format name");
+    let long = "0".repeat(100);
+    match ID128::from_str(&long) {
+        Err(sd_id128::Error::ParseStringError(error)) => assert_eq!(error.input().len(), 64),
+        _ => panic!()
+    }
+}
+
+#[test]
+#[cfg(feature = "diagnostics")]
+fn parse_error_diagnostic() {
+    use miette::Diagnostic;
+    let error = ID128::from_str("01234567-89ab-cdef-0123-456789abcdeX").unwrap_err();
+    let labels = error.labels().unwrap().collect::<Vec<_>>();
+    assert_eq!((labels[0].offset(), labels[0].len()), (35, 1));
+    assert_eq!(error.help().unwrap().to_string(),
+               "expected an ID in RFC format");
+    let mut list = ID128::parse_many("0123456789abcdef0123456789abcdef 0123");
+    list.next();
+    let error = list.next().unwrap().unwrap_err();
+    let labels = error.labels().unwrap().collect::<Vec<_>>();
+    assert_eq!((labels[0].offset(), labels[0].len()), (0, 4));
+    assert!(sd_id128::Error::SDError(-libc::ENOENT).labels().is_none());
+}
+
+#[test]
+fn partition_type_lookup() {
+    use sd_id128::gpt::{self, Architecture, Designator};
+    for known in gpt::PARTITION_TYPES {
+        let info = gpt::lookup(&known.id).unwrap();
+        assert_eq!(&info, known);
+        assert_eq!(info.architecture.is_some(),
+                   matches!(info.designator, Designator::Root | Designator::Usr));
+        if let (Designator::Root, Some(architecture)) = (info.designator, info.architecture) {
+            assert_eq!(gpt::root_for(architecture), known.id);
+        }
+    }
+    assert_eq!(gpt::lookup(&gpt::VAR).unwrap().designator, Designator::Var);
+    assert_eq!(gpt::lookup(&ID128::default()), None);
+    assert_eq!(gpt::usr_for(Architecture::X86_64), Some(gpt::USR_X86_64));
+    assert_eq!(gpt::usr_for(Architecture::S390x), None);
+    if cfg!(target_arch = "x86_64") {
+        assert_eq!(Architecture::native(), Some(Architecture::X86_64));
+    }
+}
+
+#[test]
+#[cfg(feature = "codegen")]
+fn generated_tables_match_constants() {
+    use sd_id128::{gpt, messages};
+    for (name, id) in messages::SYSTEMD_MESSAGE_IDS {
+        if let Some((_, known, _)) = messages::MESSAGE_IDS.iter()
+                                                          .find(|(known, ..)| known == name)
+        {
+            assert_eq!(id, known);
+        }
+    }
+    for (name, id) in gpt::SYSTEMD_PARTITION_TYPES {
+        if let Some(known) = gpt::PARTITION_TYPES.iter()
+                                                 .find(|known| known.name == *name)
+        {
+            assert_eq!(*id, known.id);
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "codegen")]
+fn generated_tables_are_resolved() {
+    use sd_id128::{gpt::{self, Architecture},
+                   messages::{self, MessageId}};
+    for (_, id) in messages::SYSTEMD_MESSAGE_IDS {
+        assert!(MessageId::new(id.clone()).is_well_known());
+    }
+    for (_, id) in gpt::SYSTEMD_PARTITION_TYPES {
+        if let Some(info) = gpt::lookup(id) {
+            assert_eq!(info.id, *id);
+        }
+    }
+    if let Some((_, id)) = gpt::SYSTEMD_PARTITION_TYPES.iter()
+                                                       .find(|(name, _)| *name == "usr-ia64")
+    {
+        assert_eq!(gpt::usr_for(Architecture::Ia64).as_ref(), Some(id));
+        let info = gpt::lookup(id).unwrap();
+        assert_eq!(info.architecture, Some(Architecture::Ia64));
+    }
+}
+
+#[test]
+fn message_id() {
+    use sd_id128::messages::{self, MessageId};
+    for (name, id, description) in messages::MESSAGE_IDS {
+        let message_id = MessageId::from(id.clone());
+        assert_eq!(message_id.name(), Some(*name));
+        assert_eq!(message_id.description(), Some(*description));
+        assert!(!description.is_empty());
+        assert_eq!(ID128::from(message_id), *id);
+    }
+    let message_id = MessageId::new(messages::JOURNAL_START);
+    assert_eq!(message_id.to_string(),
+               "f77379a8490b408bbe5f6940505a777b (journal-start: The journal was started)");
+    let unknown = MessageId::new(ID128::default());
+    assert!(!unknown.is_well_known());
+    assert_eq!(unknown.description(), None);
+}
+
+#[test]
+fn journal_matches() {
+    let id = ID128::from_str("0123456789ABCDEF0123456789ABCDEF").unwrap();
+    assert_eq!(id.boot_match(), "_BOOT_ID=0123456789abcdef0123456789abcdef");
+    assert_eq!(sd_id128::messages::UNIT_FAILED.message_id_match(),
+               "MESSAGE_ID=be02cf6855d2428ba40df7e9d022f03d");
+    assert_eq!(id.invocation_match(),
+               "_SYSTEMD_INVOCATION_ID=0123456789abcdef0123456789abcdef");
+    assert_eq!(id.journal_match("USER_INVOCATION_ID"),
+               "USER_INVOCATION_ID=0123456789abcdef0123456789abcdef");
+}
+
+#[test]
+fn as_id128() {
+    use sd_id128::{messages::MessageId, AsId128};
+    fn convert(id: impl AsId128) -> ID128 {
+        id.as_id128()
+    }
+    let id = ID128::from_seed(11);
+    let raw: [u8; 16] = id.clone().into();
+    assert_eq!(convert(&id), id);
+    assert_eq!(convert(raw), id);
+    assert_eq!(convert(MessageId::new(id.clone())), id);
+    assert_eq!(convert(sd_id128::messages::JOURNAL_START),
+               sd_id128::messages::JOURNAL_START);
+}
+
+#[test]
+fn const_constructors() {
+    const RAW: ID128 = ID128::from_raw_value([0x11; 16]);
+    const LIBSYSTEMD: ID128 = ID128::from_str_const("be02cf6855d2428ba40df7e9d022f03d");
+    const SIMPLE: ID128 = ID128::from_str_const("be02-cf68-55d2-428b-a40d-f7e9-d022-f03d");
+    static RFC: ID128 = ID128::from_str_const("BE02CF68-55D2-428B-A40D-F7E9D022F03D");
+    const BRACED: ID128 = ID128::from_str_const("{be02cf68-55d2-428b-a40d-f7e9d022f03d}");
+    const URN: ID128 = ID128::from_str_const("URN:uuid:be02cf68-55d2-428b-a40d-f7e9d022f03d");
+    assert_eq!(RAW, ID128::from_raw_value([0x11; 16]));
+    assert_eq!(ID128::NULL, ID128::default());
+    for id in [&LIBSYSTEMD, &SIMPLE, &RFC, &BRACED, &URN] {
+        assert_eq!(id, &sd_id128::messages::UNIT_FAILED);
+    }
+    let name = |id: &ID128| match *id {
+        ID128::NULL => "null",
+        SIMPLE => "unit failed",
+        _ => "other"
+    };
+    assert_eq!(name(&ID128::NULL), "null");
+    assert_eq!(name(&sd_id128::messages::UNIT_FAILED), "unit failed");
+    assert_eq!(name(&RAW), "other");
+}
+
+#[test]
+fn from_str_const_invalid() {
+    for invalid in ["0123456789abcdef0123456789abcde",
+                    "0123456789abcdef0123456789abcdeX",
+                    "01234567-89ab-cdef-0123+456789abcdef",
+                    "0123-4567-89ab-cdef-0123-4567-89ab+cdef",
+                    "0123456-789ab-cdef-0123-4567-89ab-cdef",
+                    "(01234567-89ab-cdef-0123-456789abcdef)",
+                    "uri:uuid:01234567-89ab-cdef-0123-456789abcdef"]
+    {
+        assert!(std::panic::catch_unwind(|| ID128::from_str_const(invalid)).is_err(),
+                "{}",
+                invalid);
+    }
+}
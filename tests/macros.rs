@@ -0,0 +1,42 @@
+// testing on sd-id128 macros
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::ID128;
+
+mod identity {
+    sd_id128::app_id!("4e7290a0a9a14f3fb7bbf5a05b46f1ad");
+}
+
+#[test]
+fn app_id() {
+    assert_eq!(identity::app_id(),
+               ID128::from_str("4e7290a0-a9a1-4f3f-b7bb-f5a05b46f1ad").unwrap());
+}
+
+#[test]
+#[cfg(any(systemd_v233,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+fn app_id_machine_id_app_specific() {
+    assert_eq!(identity::machine_id_app_specific().unwrap(),
+               ID128::machine_id_app_specific(identity::app_id()).unwrap());
+}
+
+#[test]
+#[cfg(any(systemd_v240,
+          all(feature = "dynamic", not(feature = "ffi"), not(feature = "no-ffi"))))]
+fn app_id_boot_id_app_specific() {
+    assert_eq!(identity::boot_id_app_specific().unwrap(),
+               ID128::boot_id_app_specific(identity::app_id()).unwrap());
+}
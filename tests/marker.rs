@@ -0,0 +1,102 @@
+// testing on sd-id128 markers
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{id_file::{self, Content},
+               marker::{BootMarker, BootState, InvocationMarker, InvocationState},
+               Error, ID128};
+#[cfg(feature = "crypto")]
+use sd_id128::{marker::{MachineMarker, MachineState},
+               Case, Format};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Guards `INVOCATION_ID`, which invocation markers read through libsystemd
+/// without the lock of `std::env`.
+static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+/// Locks the environment, also after a test failed holding it.
+fn lock_environment() -> MutexGuard<'static, ()> {
+    ENVIRONMENT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn state_file(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("sd-id128-marker-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn boot_marker() {
+    let path = state_file("boot");
+    let marker = BootMarker::new(&path);
+    assert_eq!(marker.check().unwrap(), BootState::FirstStart);
+    assert_eq!(marker.check().unwrap(), BootState::SameBoot);
+    let previous = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    id_file::write(&path, &Content::Id(previous.clone())).unwrap();
+    assert_eq!(marker.check().unwrap(), BootState::Rebooted(previous));
+    assert_eq!(id_file::read(&path).unwrap(),
+               Content::Id(ID128::boot_id().unwrap()));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn invocation_marker() {
+    let _environment = lock_environment();
+    let path = state_file("invocation");
+    let marker = InvocationMarker::new(&path);
+    let first = ID128::from_seed(1);
+    let second = ID128::from_seed(2);
+    std::env::set_var("INVOCATION_ID", first.to_string());
+    assert_eq!(marker.check().unwrap(), InvocationState::FreshStart);
+    assert_eq!(marker.check().unwrap(), InvocationState::SameInvocation);
+    std::env::set_var("INVOCATION_ID", second.to_string());
+    assert_eq!(marker.check().unwrap(),
+               InvocationState::Restarted { previous: first.clone(),
+                                            restarts: 1 });
+    std::env::set_var("INVOCATION_ID", first.to_string());
+    assert_eq!(marker.check().unwrap(),
+               InvocationState::Restarted { previous: second.clone(),
+                                            restarts: 2 });
+    let other_boot = format!("BOOT_ID={}\nINVOCATION_ID={}\nRESTARTS=7\n",
+                             ID128::from_seed(3),
+                             second);
+    std::fs::write(&path, other_boot).unwrap();
+    assert_eq!(marker.check().unwrap(), InvocationState::FreshStart);
+    std::fs::write(&path, "garbage").unwrap();
+    assert_eq!(marker.check(), Err(Error::SDError(-libc::EUCLEAN)));
+    std::env::remove_var("INVOCATION_ID");
+    assert_eq!(marker.check(), Err(Error::NotUnderSystemd));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "crypto")]
+fn machine_marker() {
+    let path = state_file("machine");
+    let marker = MachineMarker::new(&path);
+    let machine = ID128::from_seed(1);
+    assert_eq!(marker.check_id(&machine).unwrap(), MachineState::FirstStart);
+    assert_eq!(marker.check_id(&machine).unwrap(), MachineState::Unchanged);
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(!content.contains(&machine.to_string_formatted(Format::LibSystemD, Case::Lower)));
+    assert_eq!(marker.check_id(&ID128::from_seed(2)).unwrap(),
+               MachineState::Changed);
+    assert_eq!(marker.check_id(&ID128::from_seed(2)).unwrap(),
+               MachineState::Unchanged);
+    std::fs::write(&path, "garbage").unwrap();
+    assert_eq!(marker.check_id(&machine),
+               Err(Error::SDError(-libc::EUCLEAN)));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(marker.check().unwrap(), MachineState::FirstStart);
+    assert_eq!(marker.check().unwrap(), MachineState::Unchanged);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,70 @@
+// testing on sd-id128 machine ID sources
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{source::{self, CmdlineMachineId, MachineIdSource},
+               Error, ID128};
+
+#[test]
+fn first_successful_source_wins() {
+    let missing = std::env::temp_dir().join("sd-id128-no-such-machine-id");
+    let path = std::env::temp_dir().join(format!("sd-id128-source-{}", std::process::id()));
+    std::fs::write(&path, "0123456789abcdef0123456789abcdef\n").unwrap();
+    let chain = [MachineIdSource::File(missing.clone()),
+                 MachineIdSource::File(path.clone()),
+                 MachineIdSource::Random];
+    assert_eq!(ID128::machine_id_from(&chain).unwrap(),
+               ID128::from_str("0123456789abcdef0123456789abcdef").unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(ID128::machine_id_from(&[MachineIdSource::File(missing)]),
+               Err(Error::SDError(-libc::ENOENT)));
+}
+
+#[test]
+fn random_terminates_chain() {
+    let missing = std::env::temp_dir().join("sd-id128-no-such-machine-id");
+    let chain = [MachineIdSource::File(missing), MachineIdSource::Random];
+    assert_ne!(ID128::machine_id_from(&chain).unwrap(),
+               ID128::machine_id_from(&chain).unwrap());
+}
+
+#[test]
+fn empty_chain_fails() {
+    assert_eq!(ID128::machine_id_from(&[]),
+               Err(Error::SDError(-libc::ENOENT)));
+}
+
+#[test]
+fn default_chain() {
+    assert_eq!(ID128::machine_id_default_chain().unwrap(),
+               ID128::machine_id().unwrap());
+}
+
+#[test]
+fn cmdline() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    assert_eq!(source::parse_cmdline("quiet splash").unwrap(), None);
+    assert_eq!(source::parse_cmdline("quiet \
+                                      systemd.machine_id=0123456789abcdef0123456789abcdef")
+               .unwrap(),
+               Some(CmdlineMachineId::Id(id.clone())));
+    assert_eq!(source::parse_cmdline("systemd.machine-id=firmware ro").unwrap(),
+               Some(CmdlineMachineId::Firmware));
+    assert_eq!(source::parse_cmdline("systemd.machine_id=firmware \
+                                      \"systemd.machine_id=0123456789abcdef0123456789abcdef\"")
+               .unwrap(),
+               Some(CmdlineMachineId::Id(id)));
+    assert!(source::parse_cmdline("systemd.machine_id=invalid").is_err());
+    assert!(source::kernel_cmdline_machine_id().is_ok());
+}
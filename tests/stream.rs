@@ -0,0 +1,95 @@
+// testing on sd-id128 streams
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use sd_id128::{Case, Error, Format, Id128Reader, Id128Writer, ParseProblem, ID128};
+
+#[test]
+fn reader_text() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let input = "0123456789abcdef0123456789abcdef\r\n\n  \
+                 {01234567-89ab-cdef-0123-456789abcdef}\ninvalid\n0123456789ABCDEF0123456789ABCDEF";
+    let results = Id128Reader::new(input.as_bytes()).collect::<Vec<_>>();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0], Ok(id.clone()));
+    assert_eq!(results[1], Ok(id.clone()));
+    assert!(results[2].is_err());
+    assert_eq!(results[3], Ok(id));
+}
+
+#[test]
+fn reader_invalid_utf8() {
+    let input = b"0123456789abcdef0123456789abcdef\n\xff\xfe\n0123456789abcdef0123456789abcdef\n";
+    let results = Id128Reader::new(&input[..]).collect::<Vec<_>>();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(Error::ParseStringError(_))));
+    assert!(results[2].is_ok());
+}
+
+#[test]
+fn reader_oversized_line() {
+    let mut input = b"0123456789abcdef0123456789abcdef\n".to_vec();
+    input.resize(input.len() + 100_000, b'0');
+    input.extend_from_slice(b"\n0123456789abcdef0123456789abcdef\n");
+    let results = Id128Reader::new(input.as_slice()).collect::<Vec<_>>();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    match &results[1] {
+        Err(Error::ParseStringError(error)) => {
+            assert_eq!(error.problem(), ParseProblem::InvalidLength(100_001));
+        },
+        result => panic!("unexpected {:?}", result)
+    }
+    assert!(results[2].is_ok());
+    let results = Id128Reader::new(&[b' '; 1000][..]).collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+}
+
+#[test]
+fn reader_binary() {
+    let first = ID128::from_raw_value([1u8; 16]);
+    let second = ID128::from_raw_value([2u8; 16]);
+    let mut input = Vec::new();
+    input.extend_from_slice(first.as_raw_value());
+    input.extend_from_slice(second.as_raw_value());
+    let ids = Id128Reader::binary(input.as_slice()).collect::<Result<Vec<_>, _>>()
+                                                   .unwrap();
+    assert_eq!(ids, vec![first, second]);
+    input.push(3);
+    let results = Id128Reader::binary(input.as_slice()).collect::<Vec<_>>();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[2], Err(Error::SDError(-libc::EIO)));
+}
+
+#[test]
+fn writer() {
+    let id = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    let mut writer = Id128Writer::new(Vec::new());
+    writer.write_ids(&[id.clone(), id.clone()]).unwrap();
+    let output = writer.into_inner();
+    assert_eq!(output,
+               b"01234567-89ab-cdef-0123-456789abcdef\n01234567-89ab-cdef-0123-456789abcdef\n".to_vec());
+    let ids = Id128Reader::new(output.as_slice()).collect::<Result<Vec<_>, _>>()
+                                                 .unwrap();
+    assert_eq!(ids, vec![id.clone(), id.clone()]);
+    let mut writer = Id128Writer::new(Vec::new()).format(Format::Urn)
+                                                 .case(Case::Upper)
+                                                 .delimiter(", ");
+    writer.write_id(&id).unwrap();
+    writer.flush().unwrap();
+    assert_eq!(writer.into_inner(),
+               b"urn:uuid:01234567-89AB-CDEF-0123-456789ABCDEF, ".to_vec());
+}
@@ -0,0 +1,48 @@
+// testing on sd-id128 watching ID files
+// Copyright (C) 2020 Christian Klaue [mail@ck76.de]
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+#![cfg(all(feature = "watch", target_os = "linux"))]
+use sd_id128::{id_file::{self, Content},
+               watch, ID128};
+use std::sync::mpsc;
+
+#[test]
+fn replaced_file_is_reported() {
+    let directory = std::env::temp_dir().join(format!("sd-id128-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("machine-id");
+    let (sender, receiver) = mpsc::channel();
+    let watcher = watch::watch_id_file(&path, move |id128| {
+                      let _ = sender.send(id128);
+                  }).unwrap();
+    std::fs::write(directory.join("unrelated"), "x").unwrap();
+    let id128 = ID128::from_str("0123456789abcdef0123456789abcdef").unwrap();
+    id_file::write(&path, &Content::Id(id128.clone())).unwrap();
+    let reported = receiver.iter()
+                           .find(|result| result.is_ok())
+                           .map(Result::unwrap);
+    assert_eq!(reported, Some(id128));
+    // dropping the watcher ends the thread and thereby the channel
+    drop(watcher);
+    assert!(receiver.iter().all(|result| result.is_ok()));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn missing_directory() {
+    let path = std::env::temp_dir().join("sd-id128-no-such-directory/machine-id");
+    assert_eq!(watch::watch_id_file(path, |_| {}).err(),
+               Some(sd_id128::Error::SDError(-libc::ENOENT)));
+}